@@ -0,0 +1,18 @@
+use midi::dump::{Dump, MiddleC};
+
+fn main() {
+    let mut middle_c = MiddleC::C4;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--middle-c=C3" => middle_c = MiddleC::C3,
+            "--middle-c=C4" => middle_c = MiddleC::C4,
+            "--middle-c=C5" => middle_c = MiddleC::C5,
+            path => {
+                let bytes = std::fs::read(path).expect("failed to read file");
+                let smf = midi::Smf::read(&bytes).expect("failed to parse file");
+                println!("{}", path);
+                println!("{}", Dump::new(&smf).middle_c(middle_c));
+            }
+        }
+    }
+}
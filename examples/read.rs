@@ -1,3 +1,5 @@
+#![allow(dead_code, clippy::single_component_path_imports)]
+
 use midi;
 
 fn no_allocation_read(bytes: &[u8]) -> Result<(), midi::Error> {
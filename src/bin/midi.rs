@@ -0,0 +1,421 @@
+//! Command line tool built on `midi` library.
+
+use midi::read::{HeaderChunk, SmfReader};
+use midi::synth::{self, sf2::SoundFont, Synth};
+use midi::transform::{Transform, Transpose};
+use midi::{dump, EventKind, Format, MergeOptions, MetaEvent, MidiEventKind, Smf, TempoMap};
+use std::{env, fs, process};
+
+const USAGE: &str = "\
+usage: midi <command> [arguments]
+
+commands:
+  info <file>                             format, timing, track names, duration, notes and tempo changes
+  dump [--middle-c=C3|C4|C5] <file>       all events in human readable form
+  validate <file>...                      check files for errors
+  convert --format <0|1> <input> <output> convert file to format 0 or 1
+  transpose [--skip-drums] <semitones> <input> <output>
+                                          transpose all notes
+  merge [--remap-channels] <output> <input>...
+                                          merge files into a single format 1 file
+  append <output> <input>...              play files one after another
+  split <input> <prefix>                  write every track to <prefix><n>.mid
+  tocsv <input> [output]                  convert file to midicsv text
+  fromcsv <input> <output>                convert midicsv text to file
+  render [--rate=<hz>] [--soundfont=<sf2>] <input> <output>
+                                          render file to 16 bit stereo wav
+";
+
+type Result<T> = std::result::Result<T, String>;
+
+fn read_file(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn write_file(path: &str, data: &[u8]) -> Result<()> {
+    fs::write(path, data).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn read_smf<'a>(path: &str, data: &'a [u8]) -> Result<Smf<'a>> {
+    Smf::read(data).map_err(|err| format!("{}: {}", path, err.context))
+}
+
+fn write_smf(path: &str, smf: &Smf) -> Result<()> {
+    let data = smf
+        .write()
+        .map_err(|err| format!("{}: {}", path, err.context))?;
+    write_file(path, &data)
+}
+
+fn usage() -> Result<()> {
+    Err(USAGE.trim_end().to_string())
+}
+
+fn info(path: &str) -> Result<()> {
+    let data = read_file(path)?;
+    let smf = read_smf(path, &data)?;
+    print!("{}", dump::Dump::new(&smf).header());
+
+    let encoding = smf.guess_encoding();
+    let summary = smf.summary();
+    for (index, name) in summary.track_names.iter().enumerate() {
+        match name {
+            Some(name) => println!("  {}: {}", index, name.decode(encoding)),
+            None => println!("  {}:", index),
+        }
+    }
+    if let Some(copyright) = summary.copyright {
+        println!("Copyright: {}", copyright.decode(encoding));
+    }
+
+    println!(
+        "Duration: {} ticks, {:.3} seconds",
+        summary.ticks, summary.seconds
+    );
+    let notes = summary.track_notes.iter().sum::<usize>();
+    match summary.pitch_range {
+        Some((low, high)) => println!("Notes: {}, keys {}..={}", notes, low, high),
+        None => println!("Notes: 0"),
+    }
+    println!("Polyphony: {}", summary.max_polyphony);
+    if !summary.markers.is_empty() {
+        println!("Markers:");
+        for (tick, marker) in &summary.markers {
+            println!("  {:>8}  {}", tick, marker.decode(encoding));
+        }
+    }
+
+    let tempo_map = TempoMap::new(&smf);
+    println!("Tempo:");
+    for change in tempo_map.changes() {
+        println!(
+            "  {:>8}  {:.2} bpm",
+            change.tick,
+            60_000_000.0 / f64::from(change.tempo)
+        );
+    }
+    Ok(())
+}
+
+fn dump(args: &[String]) -> Result<()> {
+    let (middle_c, path) = match args {
+        [path] => (dump::MiddleC::C4, path),
+        [option, path] => {
+            let middle_c = match option.as_str() {
+                "--middle-c=C3" => dump::MiddleC::C3,
+                "--middle-c=C4" => dump::MiddleC::C4,
+                "--middle-c=C5" => dump::MiddleC::C5,
+                _ => return usage(),
+            };
+            (middle_c, path)
+        }
+        _ => return usage(),
+    };
+
+    let data = read_file(path)?;
+    let smf = read_smf(path, &data)?;
+    print!("{}", dump::Dump::new(&smf).middle_c(middle_c));
+    Ok(())
+}
+
+/// Returns problems of the `smf` which do not prevent reading it.
+fn problems(smf: &Smf, header: HeaderChunk) -> Vec<String> {
+    let mut problems = Vec::new();
+    if usize::from(header.tracks) != smf.tracks.len() {
+        problems.push(format!(
+            "header specifies {} tracks, but there are {} track chunks",
+            header.tracks,
+            smf.tracks.len()
+        ));
+    }
+    for (index, track) in smf.tracks.iter().enumerate() {
+        let ends = track
+            .events
+            .iter()
+            .filter(|event| matches!(event.kind, EventKind::Meta(MetaEvent::EndOfTrack)))
+            .count();
+        let last_is_end = matches!(
+            track.events.last().map(|event| &event.kind),
+            Some(EventKind::Meta(MetaEvent::EndOfTrack))
+        );
+        if !last_is_end {
+            problems.push(format!("track {}: does not end with end of track", index));
+        } else if ends > 1 {
+            problems.push(format!(
+                "track {}: end of track before the last event",
+                index
+            ));
+        }
+
+        let mut active = [[0u32; 128]; 16];
+        for event in &track.events {
+            if let EventKind::Midi(midi_event) = event.kind {
+                let channel = &mut active[(midi_event.channel & 0x0f) as usize];
+                match midi_event.kind {
+                    MidiEventKind::NoteOn { key, velocity } if velocity > 0 => {
+                        channel[(key & 0x7f) as usize] += 1;
+                    }
+                    MidiEventKind::NoteOn { key, .. } | MidiEventKind::NoteOff { key, .. } => {
+                        let count = &mut channel[(key & 0x7f) as usize];
+                        *count = count.saturating_sub(1);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let hanging: u32 = active.iter().flat_map(|channel| channel.iter()).sum();
+        if hanging > 0 {
+            problems.push(format!(
+                "track {}: {} notes are never released",
+                index, hanging
+            ));
+        }
+    }
+    problems
+}
+
+fn validate(paths: &[String]) -> Result<()> {
+    if paths.is_empty() {
+        return usage();
+    }
+
+    let mut valid = true;
+    for path in paths {
+        let data = read_file(path)?;
+        match SmfReader::new(&data)
+            .and_then(|reader| Ok((reader.header_chunk(), Smf::read(&data)?)))
+        {
+            Ok((header, smf)) => {
+                let problems = problems(&smf, header);
+                if problems.is_empty() {
+                    println!("{}: ok", path);
+                }
+                for problem in &problems {
+                    println!("{}: {}", path, problem);
+                }
+                valid &= problems.is_empty();
+            }
+            Err(err) => {
+                println!("{}: {}", path, err.context);
+                valid = false;
+            }
+        }
+    }
+
+    if valid {
+        Ok(())
+    } else {
+        Err("validation failed".to_string())
+    }
+}
+
+fn convert(args: &[String]) -> Result<()> {
+    let (format, input, output) = match args {
+        [option, format, input, output] if option == "--format" => (format, input, output),
+        _ => return usage(),
+    };
+    let format = match format.as_str() {
+        "0" => Format::Single,
+        "1" => Format::MultiTrack,
+        _ => return usage(),
+    };
+
+    let data = read_file(input)?;
+    let smf = read_smf(input, &data)?;
+    let converted = smf
+        .to_format(format)
+        .map_err(|err| format!("{}: {}", input, err.context))?;
+    write_smf(output, &converted)
+}
+
+fn transpose(args: &[String]) -> Result<()> {
+    let (skip_drums, semitones, input, output) = match args {
+        [semitones, input, output] => (false, semitones, input, output),
+        [option, semitones, input, output] if option == "--skip-drums" => {
+            (true, semitones, input, output)
+        }
+        _ => return usage(),
+    };
+    let semitones = semitones
+        .parse::<i8>()
+        .map_err(|_| format!("invalid number of semitones: {}", semitones))?;
+
+    let data = read_file(input)?;
+    let mut smf = read_smf(input, &data)?;
+    let mut transpose = Transpose::new(semitones).skip_drums(skip_drums);
+    for track in &mut smf.tracks {
+        transpose.apply_track(track);
+    }
+    write_smf(output, &smf)
+}
+
+fn merge(args: &[String], append: bool) -> Result<()> {
+    let (remap_channels, args) = match args.split_first() {
+        Some((option, args)) if !append && option == "--remap-channels" => (true, args),
+        _ => (false, args),
+    };
+    let (output, inputs) = match args.split_first() {
+        Some((output, inputs)) if !inputs.is_empty() => (output, inputs),
+        _ => return usage(),
+    };
+
+    let data = inputs
+        .iter()
+        .map(|input| read_file(input))
+        .collect::<Result<Vec<_>>>()?;
+    let smfs = inputs
+        .iter()
+        .zip(&data)
+        .map(|(input, data)| read_smf(input, data))
+        .collect::<Result<Vec<_>>>()?;
+    let merged = if append {
+        Smf::append(&smfs)
+    } else {
+        Smf::merge_with(&smfs, MergeOptions::new().remap_channels(remap_channels))
+    };
+    let merged = merged.map_err(|err| err.context.to_string())?;
+    write_smf(output, &merged)
+}
+
+fn split(args: &[String]) -> Result<()> {
+    let (input, prefix) = match args {
+        [input, prefix] => (input, prefix),
+        _ => return usage(),
+    };
+
+    let data = read_file(input)?;
+    let smf = read_smf(input, &data)?;
+    let smf = match smf.format {
+        Format::Single => smf.to_format(Format::MultiTrack),
+        _ => Ok(smf),
+    }
+    .map_err(|err| format!("{}: {}", input, err.context))?;
+
+    let parts = match smf.format {
+        // sequences are independent of each other
+        Format::MultiSequence => smf
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| (index, vec![track.clone()]))
+            .collect(),
+        _ if smf.tracks.len() < 2 => vec![(0, smf.tracks.clone())],
+        // the first track contains tempo and other global events
+        _ => smf
+            .tracks
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(index, track)| (index, vec![smf.tracks[0].clone(), track.clone()]))
+            .collect::<Vec<_>>(),
+    };
+    for (index, tracks) in parts {
+        let part = Smf {
+            format: match tracks.len() {
+                1 => Format::Single,
+                _ => Format::MultiTrack,
+            },
+            tracks,
+            timing: smf.timing,
+            unknown_chunks: Vec::new(),
+            rmid: None,
+        };
+        write_smf(&format!("{}{}.mid", prefix, index), &part)?;
+    }
+    Ok(())
+}
+
+fn to_csv(args: &[String]) -> Result<()> {
+    let input = match args {
+        [input] | [input, _] => input,
+        _ => return usage(),
+    };
+
+    let data = read_file(input)?;
+    let smf = read_smf(input, &data)?;
+    let csv = midi::csv::write(&smf).map_err(|err| format!("{}: {}", input, err.context))?;
+    match args.get(1) {
+        Some(output) => write_file(output, csv.as_bytes()),
+        None => {
+            print!("{}", csv);
+            Ok(())
+        }
+    }
+}
+
+fn from_csv(args: &[String]) -> Result<()> {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => return usage(),
+    };
+
+    let text = fs::read_to_string(input).map_err(|err| format!("{}: {}", input, err))?;
+    let smf = midi::csv::read(&text).map_err(|err| format!("{}: {}", input, err.context))?;
+    write_smf(output, &smf.as_smf())
+}
+
+fn render(args: &[String]) -> Result<()> {
+    let (options, input, output) = match args {
+        [options @ .., input, output] => (options, input, output),
+        _ => return usage(),
+    };
+    let mut rate = 44_100;
+    let mut sound_font = None;
+    for option in options {
+        if let Some(value) = option.strip_prefix("--rate=") {
+            rate = value
+                .parse::<u32>()
+                .map_err(|_| format!("invalid sample rate: {}", value))?;
+        } else if let Some(path) = option.strip_prefix("--soundfont=") {
+            sound_font = Some((path, read_file(path)?));
+        } else {
+            return usage();
+        }
+    }
+
+    let sound_font = match &sound_font {
+        Some((path, data)) => {
+            Some(SoundFont::read(data).map_err(|err| format!("{}: {}", path, err.context))?)
+        }
+        None => None,
+    };
+    let data = read_file(input)?;
+    let smf = read_smf(input, &data)?;
+    let mut synth = Synth::new(rate);
+    if let Some(sound_font) = &sound_font {
+        synth = synth.sound_font(sound_font);
+    }
+    let samples = synth.render_smf(&smf);
+    let wav = synth::write_wav_i16(&synth::to_i16(&samples), synth::CHANNELS, rate);
+    write_file(output, &wav)
+}
+
+fn run(args: &[String]) -> Result<()> {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return usage(),
+    };
+
+    match (command, args) {
+        ("info", [path]) => info(path),
+        ("dump", args) => dump(args),
+        ("validate", paths) => validate(paths),
+        ("convert", args) => convert(args),
+        ("transpose", args) => transpose(args),
+        ("merge", args) => merge(args, false),
+        ("append", args) => merge(args, true),
+        ("split", args) => split(args),
+        ("tocsv", args) => to_csv(args),
+        ("fromcsv", args) => from_csv(args),
+        ("render", args) => render(args),
+        _ => usage(),
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Err(err) = run(&args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,666 @@
+//! Conversion between `SMF` and `midicsv` text format.
+//!
+//! Every line is a record of comma separated fields: track number, absolute time in ticks,
+//! record type and its parameters.
+//!
+//! ```text
+//! 0, 0, Header, 1, 2, 480
+//! 1, 0, Start_track
+//! 1, 0, Title_t, "Piano"
+//! 1, 0, Note_on_c, 0, 60, 100
+//! 1, 480, Note_off_c, 0, 60, 0
+//! 1, 480, End_track
+//! 0, 0, End_of_file
+//! ```
+//!
+//! The format is compatible with `midicsv` and `csvmidi` tools. Text is quoted, quotes are
+//! doubled and bytes which cannot be printed are written as `\` followed by three octal digits.
+//! Unknown chunks and RIFF `RMID` container are not represented.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! # fn edit(bytes: &[u8]) -> Result<Vec<u8>, midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let csv = midi::csv::write(&smf)?;
+//! let edited = midi::csv::read(&csv.replace("Program_c, 0, 0", "Program_c, 0, 24"))?;
+//! # edited.as_smf().write()
+//! # }
+//! ```
+
+use crate::{
+    read, write, Error, ErrorKind, Event, EventKind, Format, MetaEvent, MidiEvent, MidiEventKind,
+    OwnedEvent, OwnedEventKind, OwnedMetaEvent, OwnedSmf, OwnedSysexEvent, OwnedTrack, Smf,
+    SysexEvent,
+};
+use alloc::{string::String, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt::{self, Write},
+    str::{self, FromStr},
+};
+
+fn invalid(context: &'static str) -> Error {
+    Error {
+        context,
+        kind: ErrorKind::Invalid,
+    }
+}
+
+fn push_record(out: &mut String, track: usize, tick: u64, kind: &str) {
+    // writing to String never fails
+    let _ = write!(out, "{}, {}, {}", track, tick, kind);
+}
+
+fn push_number<T: fmt::Display>(out: &mut String, number: T) {
+    let _ = write!(out, ", {}", number);
+}
+
+fn push_data(out: &mut String, data: &[u8]) {
+    push_number(out, data.len());
+    for byte in data {
+        push_number(out, byte);
+    }
+}
+
+fn push_escaped(out: &mut String, bytes: &[u8]) {
+    for &byte in bytes {
+        match byte {
+            b'"' => out.push_str("\"\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{:03o}", byte);
+            }
+        }
+    }
+}
+
+fn push_text(out: &mut String, text: &[u8]) {
+    out.push_str(", \"");
+    match str::from_utf8(text) {
+        // keep readable non-ascii characters of utf8 text
+        Ok(text) => {
+            for c in text.chars() {
+                if c.is_ascii() || c.is_control() {
+                    push_escaped(out, c.encode_utf8(&mut [0; 4]).as_bytes());
+                } else {
+                    out.push(c);
+                }
+            }
+        }
+        Err(_) => push_escaped(out, text),
+    }
+    out.push('"');
+}
+
+/// Writes `smf` as `midicsv` text.
+pub fn write(smf: &Smf) -> Result<String, Error> {
+    let mut division = Vec::new();
+    write::write_timing(&mut division, smf.timing)?;
+    let format = match smf.format {
+        Format::Single => 0,
+        Format::MultiTrack => 1,
+        Format::MultiSequence => 2,
+    };
+
+    let mut out = String::new();
+    push_record(&mut out, 0, 0, "Header");
+    push_number(&mut out, format);
+    push_number(&mut out, smf.tracks.len());
+    push_number(&mut out, u16::from_be_bytes([division[0], division[1]]));
+    out.push('\n');
+
+    for (index, track) in smf.tracks.iter().enumerate() {
+        push_record(&mut out, index + 1, 0, "Start_track");
+        out.push('\n');
+        for (tick, event) in track.absolute_iter() {
+            write_event(&mut out, index + 1, tick, event)?;
+            out.push('\n');
+        }
+    }
+
+    push_record(&mut out, 0, 0, "End_of_file");
+    out.push('\n');
+    Ok(out)
+}
+
+fn write_event(out: &mut String, track: usize, tick: u64, event: &Event) -> Result<(), Error> {
+    match event.kind {
+        EventKind::Midi(ref midi_event) => {
+            let status = write::midi_status(midi_event)?;
+            let mut data = Vec::with_capacity(2);
+            write::write_midi_event_data(&mut data, midi_event)?;
+            let kind = match status & 0xf0 {
+                0x80 => "Note_off_c",
+                0x90 => "Note_on_c",
+                0xa0 => "Poly_aftertouch_c",
+                0xb0 => "Control_c",
+                0xc0 => "Program_c",
+                0xd0 => "Channel_aftertouch_c",
+                _ => "Pitch_bend_c",
+            };
+            push_record(out, track, tick, kind);
+            push_number(out, status & 0x0f);
+            match status & 0xf0 {
+                0xe0 => push_number(out, u16::from(data[0]) | (u16::from(data[1]) << 7)),
+                _ => data.iter().for_each(|byte| push_number(out, byte)),
+            }
+        }
+        EventKind::Meta(ref meta_event) => write_meta_event(out, track, tick, meta_event),
+        EventKind::Sysex(SysexEvent::F0(data)) => {
+            push_record(out, track, tick, "System_exclusive");
+            push_data(out, data);
+        }
+        EventKind::Sysex(SysexEvent::F7(data)) => {
+            push_record(out, track, tick, "System_exclusive_packet");
+            push_data(out, data);
+        }
+    }
+    Ok(())
+}
+
+fn write_meta_event(out: &mut String, track: usize, tick: u64, meta_event: &MetaEvent) {
+    let kind = match *meta_event {
+        MetaEvent::SequenceNumber(_) => "Sequence_number",
+        MetaEvent::Text(_) => "Text_t",
+        MetaEvent::CopyrightNotice(_) => "Copyright_t",
+        MetaEvent::Name(_) => "Title_t",
+        MetaEvent::InstrumentName(_) => "Instrument_name_t",
+        MetaEvent::Lyric(_) => "Lyric_t",
+        MetaEvent::Marker(_) => "Marker_t",
+        MetaEvent::CuePoint(_) => "Cue_point_t",
+        MetaEvent::ChannelPrefix(_) => "Channel_prefix",
+        MetaEvent::EndOfTrack => "End_track",
+        MetaEvent::SetTempo(_) => "Tempo",
+        MetaEvent::SMTPEOffset { .. } => "SMPTE_offset",
+        MetaEvent::TimeSignature { .. } => "Time_signature",
+        MetaEvent::KeySignature { .. } => "Key_signature",
+        MetaEvent::SequencerSpecific(_) => "Sequencer_specific",
+        MetaEvent::Unknown {
+            meta_type: 0x21,
+            data: [_],
+        } => "MIDI_port",
+        MetaEvent::Unknown { .. } => "Unknown_meta_event",
+    };
+    push_record(out, track, tick, kind);
+
+    if let Some(text) = meta_event.text() {
+        push_text(out, text.raw());
+        return;
+    }
+
+    match *meta_event {
+        MetaEvent::SequenceNumber(number) => push_number(out, number),
+        MetaEvent::ChannelPrefix(channel) => push_number(out, channel),
+        MetaEvent::SetTempo(tempo) => push_number(out, tempo),
+        MetaEvent::SMTPEOffset { hh, mm, ss, fr, ff } => {
+            [hh, mm, ss, fr, ff]
+                .iter()
+                .for_each(|number| push_number(out, number));
+        }
+        MetaEvent::TimeSignature { nn, dd, cc, bb } => {
+            [nn, dd, cc, bb]
+                .iter()
+                .for_each(|number| push_number(out, number));
+        }
+        MetaEvent::KeySignature { sf, mi } => {
+            push_number(out, sf as i8);
+            out.push_str(if mi == 0 {
+                ", \"major\""
+            } else {
+                ", \"minor\""
+            });
+        }
+        MetaEvent::SequencerSpecific(data) => push_data(out, data),
+        MetaEvent::Unknown {
+            meta_type: 0x21,
+            data: [port],
+        } => push_number(out, port),
+        MetaEvent::Unknown { meta_type, data } => {
+            push_number(out, meta_type);
+            push_data(out, data);
+        }
+        _ => {}
+    }
+}
+
+/// Field of `midicsv` record.
+enum Field<'a> {
+    Plain(&'a str),
+    Quoted(Vec<u8>),
+}
+
+impl<'a> Field<'a> {
+    fn number<T: FromStr>(&self) -> Result<T, Error> {
+        match *self {
+            Field::Plain(field) => field
+                .parse()
+                .map_err(|_| invalid("csv::read: field must be a number")),
+            Field::Quoted(_) => Err(invalid("csv::read: field must be a number")),
+        }
+    }
+
+    fn into_text(self) -> Vec<u8> {
+        match self {
+            Field::Plain(field) => field.as_bytes().to_vec(),
+            Field::Quoted(text) => text,
+        }
+    }
+}
+
+fn read_quoted(field: &str) -> Result<(Vec<u8>, &str), Error> {
+    let bytes = field.as_bytes();
+    let mut text = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' if bytes.get(i + 1) == Some(&b'"') => {
+                text.push(b'"');
+                i += 2;
+            }
+            b'"' => return Ok((text, &field[i + 1..])),
+            b'\\' => {
+                let octal = bytes
+                    .get(i + 1..i + 4)
+                    .filter(|digits| digits.iter().all(|digit| (b'0'..=b'7').contains(digit)));
+                if let Some(digits) = octal {
+                    let value = digits
+                        .iter()
+                        .fold(0u16, |value, digit| value * 8 + u16::from(digit - b'0'));
+                    let byte = u8::try_from(value)
+                        .map_err(|_| invalid("csv::read: octal escape must fit into a byte"))?;
+                    text.push(byte);
+                    i += 4;
+                } else if bytes.get(i + 1) == Some(&b'\\') {
+                    text.push(b'\\');
+                    i += 2;
+                } else {
+                    text.push(b'\\');
+                    i += 1;
+                }
+            }
+            byte => {
+                text.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    Err(invalid("csv::read: quoted field must be terminated"))
+}
+
+fn read_fields(line: &str) -> Result<Vec<Field<'_>>, Error> {
+    let mut fields = Vec::new();
+    let mut rest = line;
+    loop {
+        let field = rest.trim_start();
+        if let Some(quoted) = field.strip_prefix('"') {
+            let (text, after) = read_quoted(quoted)?;
+            fields.push(Field::Quoted(text));
+            let after = after.trim_start();
+            match after.strip_prefix(',') {
+                Some(after) => rest = after,
+                None if after.is_empty() => return Ok(fields),
+                None => {
+                    return Err(invalid(
+                        "csv::read: quoted field must be followed by a comma",
+                    ))
+                }
+            }
+        } else {
+            match field.find(',') {
+                Some(end) => {
+                    fields.push(Field::Plain(field[..end].trim_end()));
+                    rest = &field[end + 1..];
+                }
+                None => {
+                    fields.push(Field::Plain(field.trim_end()));
+                    return Ok(fields);
+                }
+            }
+        }
+    }
+}
+
+fn expect_params(params: &[Field], len: usize) -> Result<(), Error> {
+    if params.len() != len {
+        return Err(invalid(
+            "csv::read: record must have expected number of fields",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads `length, data...` parameters.
+fn read_data(params: &[Field]) -> Result<Vec<u8>, Error> {
+    let (len, data) = params
+        .split_first()
+        .ok_or_else(|| invalid("csv::read: data must start with its length"))?;
+    if len.number::<usize>()? != data.len() {
+        return Err(invalid("csv::read: data length must match number of bytes"));
+    }
+    data.iter().map(Field::number).collect()
+}
+
+fn read_midi_event(status: u8, params: &[Field]) -> Result<MidiEvent, Error> {
+    let channel = params
+        .first()
+        .ok_or_else(|| invalid("csv::read: midi event must specify channel"))?
+        .number::<u8>()?;
+    if channel > 0x0f {
+        return Err(invalid("csv::read: channel must fit into 4 bits"));
+    }
+
+    let mut bytes = [0, status | channel, 0, 0];
+    let len = match status {
+        0xc0 | 0xd0 => {
+            expect_params(params, 2)?;
+            bytes[2] = params[1].number()?;
+            3
+        }
+        0xe0 => {
+            expect_params(params, 2)?;
+            let value = params[1].number::<u16>()?;
+            bytes[2] = (value & 0x7f) as u8;
+            bytes[3] = (value >> 7) as u8;
+            4
+        }
+        _ => {
+            expect_params(params, 3)?;
+            bytes[2] = params[1].number()?;
+            bytes[3] = params[2].number()?;
+            4
+        }
+    };
+
+    match read::read_event(&mut &bytes[..len]) {
+        Ok(Event {
+            kind: EventKind::Midi(midi_event),
+            ..
+        }) => Ok(midi_event),
+        Ok(_) => Err(invalid("csv::read: record must be a midi event")),
+        // `midicsv` writes channel mode messages with any value, which are rejected by
+        // `read_event`, so they are kept as plain controller changes
+        Err(_) if status == 0xb0 && (0x78..0x80).contains(&bytes[2]) && bytes[3] < 0x80 => {
+            Ok(MidiEvent {
+                channel,
+                kind: MidiEventKind::ControllerChange {
+                    number: bytes[2],
+                    value: bytes[3],
+                },
+            })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn read_event_kind(kind: &str, mut params: Vec<Field>) -> Result<OwnedEventKind, Error> {
+    let status = match kind {
+        "note_off_c" => Some(0x80),
+        "note_on_c" => Some(0x90),
+        "poly_aftertouch_c" => Some(0xa0),
+        "control_c" => Some(0xb0),
+        "program_c" => Some(0xc0),
+        "channel_aftertouch_c" => Some(0xd0),
+        "pitch_bend_c" => Some(0xe0),
+        _ => None,
+    };
+    if let Some(status) = status {
+        return read_midi_event(status, &params).map(OwnedEventKind::Midi);
+    }
+
+    let text = |params: Vec<Field>| -> Result<Vec<u8>, Error> {
+        expect_params(&params, 1)?;
+        Ok(params
+            .into_iter()
+            .next()
+            .expect("params len is 1; qed")
+            .into_text())
+    };
+
+    let meta_event = match kind {
+        "sequence_number" => {
+            expect_params(&params, 1)?;
+            OwnedMetaEvent::SequenceNumber(params[0].number()?)
+        }
+        "text_t" => OwnedMetaEvent::Text(text(params)?),
+        "copyright_t" => OwnedMetaEvent::CopyrightNotice(text(params)?),
+        "title_t" => OwnedMetaEvent::Name(text(params)?),
+        "instrument_name_t" => OwnedMetaEvent::InstrumentName(text(params)?),
+        "lyric_t" => OwnedMetaEvent::Lyric(text(params)?),
+        "marker_t" => OwnedMetaEvent::Marker(text(params)?),
+        "cue_point_t" => OwnedMetaEvent::CuePoint(text(params)?),
+        "channel_prefix" => {
+            expect_params(&params, 1)?;
+            OwnedMetaEvent::ChannelPrefix(params[0].number()?)
+        }
+        "midi_port" => {
+            expect_params(&params, 1)?;
+            OwnedMetaEvent::Unknown {
+                meta_type: 0x21,
+                data: vec![params[0].number()?],
+            }
+        }
+        "end_track" => {
+            expect_params(&params, 0)?;
+            OwnedMetaEvent::EndOfTrack
+        }
+        "tempo" => {
+            expect_params(&params, 1)?;
+            OwnedMetaEvent::SetTempo(params[0].number()?)
+        }
+        "smpte_offset" => {
+            expect_params(&params, 5)?;
+            OwnedMetaEvent::SMTPEOffset {
+                hh: params[0].number()?,
+                mm: params[1].number()?,
+                ss: params[2].number()?,
+                fr: params[3].number()?,
+                ff: params[4].number()?,
+            }
+        }
+        "time_signature" => {
+            expect_params(&params, 4)?;
+            OwnedMetaEvent::TimeSignature {
+                nn: params[0].number()?,
+                dd: params[1].number()?,
+                cc: params[2].number()?,
+                bb: params[3].number()?,
+            }
+        }
+        "key_signature" => {
+            expect_params(&params, 2)?;
+            let sf = params[0].number::<i8>()? as u8;
+            let mode = params.pop().expect("params len is 2; qed").into_text();
+            let mi = match &mode.to_ascii_lowercase()[..] {
+                b"major" => 0,
+                b"minor" => 1,
+                _ => return Err(invalid("csv::read: key mode must be major or minor")),
+            };
+            OwnedMetaEvent::KeySignature { sf, mi }
+        }
+        "sequencer_specific" => OwnedMetaEvent::SequencerSpecific(read_data(&params)?),
+        "unknown_meta_event" => {
+            let meta_type = params
+                .first()
+                .ok_or_else(|| invalid("csv::read: unknown meta event must specify type"))?
+                .number()?;
+            OwnedMetaEvent::Unknown {
+                meta_type,
+                data: read_data(&params[1..])?,
+            }
+        }
+        "system_exclusive" => {
+            return Ok(OwnedEventKind::Sysex(OwnedSysexEvent::F0(read_data(
+                &params,
+            )?)))
+        }
+        "system_exclusive_packet" => {
+            return Ok(OwnedEventKind::Sysex(OwnedSysexEvent::F7(read_data(
+                &params,
+            )?)))
+        }
+        _ => return Err(invalid("csv::read: record type must be known")),
+    };
+
+    Ok(OwnedEventKind::Meta(meta_event))
+}
+
+/// Reads `midicsv` text.
+///
+/// Empty lines and lines starting with `#` or `;` are ignored. Record types are case insensitive.
+/// Events of every track must be ordered by time.
+pub fn read(text: &str) -> Result<OwnedSmf, Error> {
+    let mut header = None;
+    let mut tracks: Vec<OwnedTrack> = Vec::new();
+    let mut last_ticks: Vec<u64> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        let mut fields = read_fields(line)?;
+        if fields.len() < 3 {
+            return Err(invalid(
+                "csv::read: record must specify track, time and type",
+            ));
+        }
+        let params = fields.split_off(3);
+        let track = fields[0].number::<usize>()?;
+        let tick = fields[1].number::<u64>()?;
+        let kind = match fields.pop() {
+            Some(Field::Plain(kind)) => kind.to_ascii_lowercase(),
+            _ => return Err(invalid("csv::read: record type must not be quoted")),
+        };
+
+        match &kind[..] {
+            "header" => {
+                expect_params(&params, 3)?;
+                let format = match params[0].number::<u16>()? {
+                    0 => Format::Single,
+                    1 => Format::MultiTrack,
+                    2 => Format::MultiSequence,
+                    _ => return Err(invalid("csv::read: format must be 0, 1 or 2")),
+                };
+                let division = params[2].number::<u16>()?.to_be_bytes();
+                let timing = read::read_timing(&mut &division[..])
+                    .map_err(|_| invalid("csv::read: division must be valid timing"))?;
+                header = Some((format, timing));
+                continue;
+            }
+            "start_track" => {
+                if track != tracks.len() + 1 {
+                    return Err(invalid("csv::read: tracks must be started in order"));
+                }
+                tracks.push(OwnedTrack::default());
+                last_ticks.push(0);
+                continue;
+            }
+            "end_of_file" => break,
+            _ => {}
+        }
+
+        let index = track
+            .checked_sub(1)
+            .filter(|index| *index < tracks.len())
+            .ok_or_else(|| invalid("csv::read: track must be started before its events"))?;
+        let time = tick
+            .checked_sub(last_ticks[index])
+            .ok_or_else(|| invalid("csv::read: events must be ordered by time"))
+            .and_then(|time| {
+                u32::try_from(time)
+                    .map_err(|_| invalid("csv::read: delta time must fit into 32 bits"))
+            })?;
+        let kind = read_event_kind(&kind, params)?;
+        tracks[index].events.push(OwnedEvent { time, kind });
+        last_ticks[index] = tick;
+    }
+
+    let (format, timing) = header.ok_or_else(|| invalid("csv::read: header must be specified"))?;
+    let smf = OwnedSmf {
+        format,
+        tracks,
+        timing,
+        unknown_chunks: Vec::new(),
+        rmid: None,
+    };
+
+    Ok(smf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write, MidiEvent, MidiEventKind, OwnedEventKind};
+
+    const CSV: &str = "\
+0, 0, Header, 1, 1, 480
+1, 0, Start_track
+1, 0, Title_t, \"Say \"\"hi\"\" \\\\ \\001 żółw\"
+1, 0, Key_signature, -3, \"minor\"
+1, 0, MIDI_port, 2
+1, 0, Note_on_c, 9, 36, 100
+1, 240, Note_on_c, 9, 36, 0
+1, 240, Pitch_bend_c, 0, 8192
+1, 480, System_exclusive, 3, 126, 127, 247
+1, 480, End_track
+0, 0, End_of_file
+";
+
+    #[test]
+    fn test_round_trip() {
+        let smf = read(CSV).unwrap();
+        assert_eq!(smf.tracks[0].events.len(), 8);
+        assert_eq!(smf.tracks[0].events[4].time, 240);
+        assert_eq!(write(&smf.as_smf()).unwrap(), CSV);
+    }
+
+    #[test]
+    fn test_lenient_input() {
+        let csv = "# comment\n0,0,HEADER,0,1,96\n\n1,0,start_track\n1, 10 ,text_t,plain\n";
+        let smf = read(csv).unwrap();
+        let written = write(&smf.as_smf()).unwrap();
+        assert_eq!(
+            written,
+            "0, 0, Header, 0, 1, 96\n1, 0, Start_track\n1, 10, Text_t, \"plain\"\n0, 0, End_of_file\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(read("1, 0, Start_track\n").is_err());
+        assert!(read("0, 0, Header, 0, 1, 96\n1, 0, Note_on_c, 0, 60, 64\n").is_err());
+        assert!(read("0, 0, Header, 0, 1, 96\n1, 0, Start_track\n1, 0, Text_t, \"x\n").is_err());
+        assert!(
+            read("0, 0, Header, 0, 1, 96\n1, 0, Start_track\n1, 0, Note_on_c, 16, 0, 0\n").is_err()
+        );
+        assert!(read("0, 0, Header, 0, 1, 96\n1, 9, Start_track\n1, 0, Foo\n").is_err());
+    }
+
+    #[test]
+    fn test_channel_mode_value() {
+        let csv = "\
+0, 0, Header, 0, 1, 96
+1, 0, Start_track
+1, 0, Control_c, 0, 121, 5
+1, 0, Control_c, 0, 123, 127
+1, 0, End_track
+0, 0, End_of_file
+";
+        let smf = read(csv).unwrap();
+        assert_eq!(
+            smf.tracks[0].events[0].kind,
+            OwnedEventKind::Midi(MidiEvent {
+                channel: 0,
+                kind: MidiEventKind::ControllerChange {
+                    number: 121,
+                    value: 5
+                },
+            })
+        );
+        assert_eq!(write(&smf.as_smf()).unwrap(), csv);
+        assert!(read(&csv.replace("121, 5", "121, 128")).is_err());
+    }
+}
@@ -0,0 +1,519 @@
+//! Human readable text dump of `SMF`.
+//!
+//! Every event is printed on a separate line with its absolute time in ticks, position in bars,
+//! beats and ticks (BBT), time in seconds, channel and description.
+//!
+//! ```text
+//!     tick         bbt    seconds  ch  event
+//!        0     1:1:000      0.000   1  Note on C4 (60), velocity 100
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! # fn print(bytes: &[u8]) -> Result<(), midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let dump = midi::dump::Dump::new(&smf).middle_c(midi::dump::MiddleC::C3);
+//! println!("{}", dump);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    Action, Encoding, EventKind, Format, Fps, MetaEvent, Meters, MidiEventKind, Smf, SysexEvent,
+    TempoMap, Text, Timing,
+};
+use alloc::format;
+use core::fmt::{self, Write};
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+const MAJOR_KEYS: [&str; 15] = [
+    "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+];
+
+const MINOR_KEYS: [&str; 15] = [
+    "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#", "G#", "D#", "A#",
+];
+
+/// Octave naming convention, specified by the name of the middle C (key 60).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MiddleC {
+    /// Used by Yamaha and many DAWs.
+    C3,
+    /// Scientific pitch notation.
+    #[default]
+    C4,
+    /// Used by some older software.
+    C5,
+}
+
+/// Name of the note, e.g. `C#4`.
+///
+/// # Example
+///
+/// ```
+/// # use midi::dump::{MiddleC, NoteName};
+/// assert_eq!(NoteName::new(61, MiddleC::C4).to_string(), "C#4");
+/// assert_eq!(NoteName::new(0, MiddleC::C3).to_string(), "C-2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteName {
+    key: u8,
+    middle_c: MiddleC,
+}
+
+impl NoteName {
+    /// Creates new [`NoteName`] of the `key`.
+    ///
+    /// [`NoteName`]: struct.NoteName.html
+    pub fn new(key: u8, middle_c: MiddleC) -> Self {
+        NoteName { key, middle_c }
+    }
+}
+
+impl fmt::Display for NoteName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let offset = match self.middle_c {
+            MiddleC::C3 => 2,
+            MiddleC::C4 => 1,
+            MiddleC::C5 => 0,
+        };
+        let octave = i16::from(self.key / 12) - offset;
+        write!(f, "{}{}", NOTE_NAMES[(self.key % 12) as usize], octave)
+    }
+}
+
+/// Escapes control characters, so that every event stays on a single line.
+struct EscapeControl<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b> fmt::Write for EscapeControl<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c.is_control() {
+                write!(self.0, "{}", c.escape_default())?;
+            } else {
+                self.0.write_char(c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_bytes(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        write!(f, " {:02x}", byte)?;
+    }
+    Ok(())
+}
+
+fn controller_name(number: u8) -> Option<&'static str> {
+    let name = match number {
+        0 => "bank select",
+        1 => "modulation",
+        2 => "breath",
+        4 => "foot",
+        5 => "portamento time",
+        6 => "data entry",
+        7 => "volume",
+        8 => "balance",
+        10 => "pan",
+        11 => "expression",
+        32 => "bank select lsb",
+        38 => "data entry lsb",
+        64 => "sustain",
+        65 => "portamento",
+        66 => "sostenuto",
+        67 => "soft pedal",
+        71 => "resonance",
+        72 => "release time",
+        73 => "attack time",
+        74 => "cutoff",
+        91 => "reverb",
+        93 => "chorus",
+        98 => "nrpn lsb",
+        99 => "nrpn msb",
+        100 => "rpn lsb",
+        101 => "rpn msb",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn sysex_name(data: &[u8]) -> Option<&'static str> {
+    let name = match data {
+        [0x7e, _, 0x09, 0x01, 0xf7] => "GM system on",
+        [0x7e, _, 0x09, 0x02, 0xf7] => "GM system off",
+        [0x7e, _, 0x09, 0x03, 0xf7] => "GM2 system on",
+        [0x41, _, 0x42, 0x12, 0x40, 0x00, 0x7f, 0x00, 0x41, 0xf7] => "GS reset",
+        [0x43, _, 0x4c, 0x00, 0x00, 0x7e, 0x00, 0xf7] => "XG system on",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Human readable description of [`EventKind`].
+///
+/// # Example
+///
+/// ```
+/// # use midi::{dump::EventDisplay, EventKind, MidiEvent, MidiEventKind};
+/// let kind = EventKind::Midi(MidiEvent {
+///     channel: 0,
+///     kind: MidiEventKind::NoteOn { key: 60, velocity: 100 },
+/// });
+/// assert_eq!(EventDisplay::new(kind).to_string(), "Note on C4 (60), velocity 100");
+/// ```
+///
+/// [`EventKind`]: ../enum.EventKind.html
+#[derive(Debug, Clone, Copy)]
+pub struct EventDisplay<'a> {
+    kind: EventKind<'a>,
+    middle_c: MiddleC,
+    encoding: Encoding,
+}
+
+impl<'a> EventDisplay<'a> {
+    /// Creates new [`EventDisplay`] using [`MiddleC::C4`] and utf8 text encoding.
+    ///
+    /// [`EventDisplay`]: struct.EventDisplay.html
+    /// [`MiddleC::C4`]: enum.MiddleC.html#variant.C4
+    pub fn new(kind: EventKind<'a>) -> Self {
+        EventDisplay {
+            kind,
+            middle_c: MiddleC::default(),
+            encoding: Encoding::Utf8,
+        }
+    }
+
+    /// Sets octave naming convention.
+    pub fn middle_c(mut self, middle_c: MiddleC) -> Self {
+        self.middle_c = middle_c;
+        self
+    }
+
+    /// Sets encoding of text meta events.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    fn note(&self, key: u8) -> NoteName {
+        NoteName::new(key, self.middle_c)
+    }
+
+    fn fmt_text(&self, f: &mut fmt::Formatter, name: &str, text: Text) -> fmt::Result {
+        write!(f, "{} \"", name)?;
+        write!(EscapeControl(f), "{}", text.decode(self.encoding))?;
+        write!(f, "\"")
+    }
+
+    fn fmt_midi(&self, f: &mut fmt::Formatter, kind: MidiEventKind) -> fmt::Result {
+        match kind {
+            MidiEventKind::NoteOff { key, velocity } => write!(
+                f,
+                "Note off {} ({}), velocity {}",
+                self.note(key),
+                key,
+                velocity
+            ),
+            MidiEventKind::NoteOn { key, velocity } => write!(
+                f,
+                "Note on {} ({}), velocity {}",
+                self.note(key),
+                key,
+                velocity
+            ),
+            MidiEventKind::PolyphonicKeyPressure { key, velocity } => write!(
+                f,
+                "Key pressure {} ({}), pressure {}",
+                self.note(key),
+                key,
+                velocity
+            ),
+            MidiEventKind::ControllerChange { number, value } => match controller_name(number) {
+                Some(name) => write!(f, "Controller {} ({}), value {}", number, name, value),
+                None => write!(f, "Controller {}, value {}", number, value),
+            },
+            MidiEventKind::ProgramChange(program) => write!(f, "Program change {}", program),
+            MidiEventKind::ChannelKeyPressure(pressure) => {
+                write!(f, "Channel pressure {}", pressure)
+            }
+            MidiEventKind::PitchBend { lsb, msb } => {
+                let value = (i32::from(msb) << 7 | i32::from(lsb)) - 0x2000;
+                write!(f, "Pitch bend {:+}", value)
+            }
+            MidiEventKind::AllSoundOff => write!(f, "All sound off"),
+            MidiEventKind::ResetAllControllers => write!(f, "Reset all controllers"),
+            MidiEventKind::LocalControl(Action::Disconnect) => write!(f, "Local control off"),
+            MidiEventKind::LocalControl(Action::Reconnect) => write!(f, "Local control on"),
+            MidiEventKind::AllNotesOff => write!(f, "All notes off"),
+            MidiEventKind::OmniModeOff => write!(f, "Omni mode off"),
+            MidiEventKind::OmniModeOn => write!(f, "Omni mode on"),
+            MidiEventKind::MonoModeOn(channels) => write!(f, "Mono mode on, {} channels", channels),
+            MidiEventKind::PolyModeOn => write!(f, "Poly mode on"),
+        }
+    }
+
+    fn fmt_meta(&self, f: &mut fmt::Formatter, meta_event: MetaEvent) -> fmt::Result {
+        match meta_event {
+            MetaEvent::SequenceNumber(number) => write!(f, "Sequence number {}", number),
+            MetaEvent::Text(text) => self.fmt_text(f, "Text", text),
+            MetaEvent::CopyrightNotice(text) => self.fmt_text(f, "Copyright", text),
+            MetaEvent::Name(text) => self.fmt_text(f, "Track name", text),
+            MetaEvent::InstrumentName(text) => self.fmt_text(f, "Instrument name", text),
+            MetaEvent::Lyric(text) => self.fmt_text(f, "Lyric", text),
+            MetaEvent::Marker(text) => self.fmt_text(f, "Marker", text),
+            MetaEvent::CuePoint(text) => self.fmt_text(f, "Cue point", text),
+            MetaEvent::ChannelPrefix(channel) => write!(f, "Channel prefix {}", channel),
+            MetaEvent::EndOfTrack => write!(f, "End of track"),
+            MetaEvent::SetTempo(tempo) => write!(
+                f,
+                "Tempo {:.2} bpm ({} us per quarter note)",
+                60_000_000.0 / f64::from(tempo),
+                tempo
+            ),
+            MetaEvent::SMTPEOffset { hh, mm, ss, fr, ff } => write!(
+                f,
+                "SMPTE offset {:02}:{:02}:{:02}:{:02}.{:02}",
+                hh, mm, ss, fr, ff
+            ),
+            MetaEvent::TimeSignature { nn, dd, cc, bb } => {
+                write!(f, "Time signature {}/", nn)?;
+                match 1u64.checked_shl(u32::from(dd)) {
+                    Some(denominator) => write!(f, "{}", denominator)?,
+                    None => write!(f, "2^{}", dd)?,
+                }
+                write!(
+                    f,
+                    ", {} clocks per click, {} 32nd notes per quarter note",
+                    cc, bb
+                )
+            }
+            MetaEvent::KeySignature { sf, mi } => {
+                let keys = if mi == 0 { &MAJOR_KEYS } else { &MINOR_KEYS };
+                let mode = if mi == 0 { "major" } else { "minor" };
+                match keys.get((i16::from(sf as i8) + 7) as usize) {
+                    Some(key) => write!(f, "Key signature {} {}", key, mode),
+                    None => write!(f, "Key signature {} sharps, {}", sf as i8, mode),
+                }
+            }
+            MetaEvent::SequencerSpecific(data) => {
+                write!(f, "Sequencer specific")?;
+                write_bytes(f, data)
+            }
+            MetaEvent::Unknown {
+                meta_type: 0x21,
+                data: [port],
+            } => write!(f, "Port {}", port),
+            MetaEvent::Unknown { meta_type, data } => {
+                write!(f, "Unknown meta event {:02x}:", meta_type)?;
+                write_bytes(f, data)
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for EventDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            EventKind::Midi(midi_event) => self.fmt_midi(f, midi_event.kind),
+            EventKind::Meta(meta_event) => self.fmt_meta(f, meta_event),
+            EventKind::Sysex(sysex_event) => {
+                let (name, data) = match sysex_event {
+                    SysexEvent::F0(data) => ("Sysex f0", data),
+                    SysexEvent::F7(data) => ("Sysex f7", data),
+                };
+                write!(f, "{}", name)?;
+                write_bytes(f, data)?;
+                match sysex_name(data) {
+                    Some(name) => write!(f, " ({})", name),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Human readable dump of [`Smf`].
+///
+/// Text meta events are decoded using [`Smf::guess_encoding`] unless [`encoding`] is set.
+/// Channels are numbered from 1 to 16.
+///
+/// [`Smf`]: ../struct.Smf.html
+/// [`Smf::guess_encoding`]: ../struct.Smf.html#method.guess_encoding
+/// [`encoding`]: #method.encoding
+#[derive(Debug, Clone)]
+pub struct Dump<'a> {
+    smf: &'a Smf<'a>,
+    middle_c: MiddleC,
+    encoding: Option<Encoding>,
+}
+
+impl<'a> Dump<'a> {
+    /// Creates new [`Dump`] of the `smf`.
+    ///
+    /// [`Dump`]: struct.Dump.html
+    pub fn new(smf: &'a Smf<'a>) -> Self {
+        Dump {
+            smf,
+            middle_c: MiddleC::default(),
+            encoding: None,
+        }
+    }
+
+    /// Sets octave naming convention.
+    pub fn middle_c(mut self, middle_c: MiddleC) -> Self {
+        self.middle_c = middle_c;
+        self
+    }
+
+    /// Sets encoding of text meta events.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Returns dump of the header only: format, timing and number of tracks.
+    pub fn header(&self) -> DumpHeader<'a> {
+        DumpHeader { smf: self.smf }
+    }
+}
+
+/// Human readable dump of [`Smf`] header.
+///
+/// Created using [`Dump::header`] method.
+///
+/// [`Smf`]: ../struct.Smf.html
+/// [`Dump::header`]: struct.Dump.html#method.header
+#[derive(Debug, Clone, Copy)]
+pub struct DumpHeader<'a> {
+    smf: &'a Smf<'a>,
+}
+
+impl<'a> fmt::Display for DumpHeader<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self.smf.format {
+            Format::Single => "single track",
+            Format::MultiTrack => "multiple tracks",
+            Format::MultiSequence => "multiple sequences",
+        };
+        writeln!(f, "Format: {}", format)?;
+        match self.smf.timing {
+            Timing::Metrical(ppqn) => writeln!(f, "Timing: {} ticks per quarter note", ppqn)?,
+            Timing::Timecode { fps, subframe } => {
+                let fps = match fps {
+                    Fps::Fps24 => "24",
+                    Fps::Fps25 => "25",
+                    Fps::Fps30Drop => "29.97",
+                    Fps::Fps30NonDrop => "30",
+                };
+                writeln!(f, "Timing: {} fps, {} subframes per frame", fps, subframe)?;
+            }
+        }
+        writeln!(f, "Tracks: {}", self.smf.tracks.len())
+    }
+}
+
+impl<'a> fmt::Display for Dump<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let smf = self.smf;
+        write!(f, "{}", self.header())?;
+        let meters = match smf.timing {
+            Timing::Metrical(ppqn) => Some(Meters::new(smf, ppqn)),
+            Timing::Timecode { .. } => None,
+        };
+
+        let tempo_map = TempoMap::new(smf);
+        let encoding = self.encoding.unwrap_or_else(|| smf.guess_encoding());
+        for (index, track) in smf.tracks.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "Track {}", index)?;
+            writeln!(
+                f,
+                "{:>8} {:>11} {:>10} {:>3}  event",
+                "tick", "bbt", "seconds", "ch"
+            )?;
+            for (tick, event) in track.absolute_iter() {
+                write!(f, "{:>8} ", tick)?;
+                match meters {
+                    Some(ref meters) => {
+                        let (bar, beat, ticks) = meters.bbt(tick);
+                        write!(
+                            f,
+                            "{:>11}",
+                            format!("{}:{}:{:03}", bar + 1, beat + 1, ticks)
+                        )?
+                    }
+                    None => write!(f, "{:>11}", "-")?,
+                }
+                write!(f, " {:>10.3} ", tempo_map.seconds(tick))?;
+                match event.kind {
+                    EventKind::Midi(midi_event) => write!(f, "{:>3}", midi_event.channel + 1)?,
+                    _ => write!(f, "{:>3}", "")?,
+                }
+                let display = EventDisplay::new(event.kind)
+                    .middle_c(self.middle_c)
+                    .encoding(encoding);
+                writeln!(f, "  {}", display)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dump, MiddleC, NoteName};
+    use crate::test_util::{end_of_track, meta, midi, note_on, smf};
+    use crate::{Format, MetaEvent, MidiEventKind, Text};
+
+    #[test]
+    fn test_note_name() {
+        assert_eq!(NoteName::new(60, MiddleC::C4).to_string(), "C4");
+        assert_eq!(NoteName::new(60, MiddleC::C3).to_string(), "C3");
+        assert_eq!(NoteName::new(69, MiddleC::C5).to_string(), "A5");
+        assert_eq!(NoteName::new(127, MiddleC::C4).to_string(), "G9");
+    }
+
+    #[test]
+    fn test_dump() {
+        let events = vec![
+            meta(0, MetaEvent::Name(Text::new(b"Lead\r"))),
+            meta(
+                0,
+                MetaEvent::TimeSignature {
+                    nn: 3,
+                    dd: 2,
+                    cc: 24,
+                    bb: 8,
+                },
+            ),
+            meta(0, MetaEvent::KeySignature { sf: 0xfd, mi: 0 }),
+            note_on(0, 9, 36, 100),
+            midi(400, 0, MidiEventKind::PitchBend { lsb: 0, msb: 0 }),
+            end_of_track(0),
+        ];
+        let smf = smf(Format::Single, 96, vec![events]);
+
+        let expected = "\
+Format: single track
+Timing: 96 ticks per quarter note
+Tracks: 1
+
+Track 0
+    tick         bbt    seconds  ch  event
+       0     1:1:000      0.000      Track name \"Lead\\r\"
+       0     1:1:000      0.000      Time signature 3/4, 24 clocks per click, 8 32nd notes per quarter note
+       0     1:1:000      0.000      Key signature Eb major
+       0     1:1:000      0.000  10  Note on C2 (36), velocity 100
+     400     2:2:016      2.083   1  Pitch bend -8192
+     400     2:2:016      2.083      End of track
+";
+        assert_eq!(Dump::new(&smf).to_string(), expected);
+    }
+}
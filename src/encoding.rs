@@ -0,0 +1,290 @@
+//! [`Text`] decoding.
+//!
+//! [`Text`]: struct.Text.html
+
+use crate::Text;
+use core::{fmt, str};
+
+/// [`Text`] encoding.
+///
+/// The `SMF` specification does not define any text encoding. Old files are commonly encoded
+/// using Latin-1 or Windows-1252, Japanese files using Shift-JIS.
+///
+/// Encodings other than `Utf8`, `Latin1` and `Windows1252` require `encoding` feature.
+///
+/// [`Text`]: struct.Text.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    /// ISO-8859-1.
+    Latin1,
+    Windows1252,
+    #[cfg(feature = "encoding")]
+    ShiftJis,
+    #[cfg(feature = "encoding")]
+    EucJp,
+    #[cfg(feature = "encoding")]
+    EucKr,
+    #[cfg(feature = "encoding")]
+    Gbk,
+    #[cfg(feature = "encoding")]
+    Big5,
+    #[cfg(feature = "encoding")]
+    Windows1251,
+}
+
+#[rustfmt::skip]
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{0081}', '\u{201a}', '\u{0192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02c6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008d}', '\u{017d}', '\u{008f}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02dc}', '\u{2122}', '\u{0161}', '\u{203a}', '\u{0153}', '\u{009d}', '\u{017e}', '\u{0178}',
+];
+
+/// Returns true if the byte is not assigned to any character in Windows-1252.
+fn is_undefined_windows_1252(byte: u8) -> bool {
+    matches!(byte, 0x81 | 0x8d | 0x8f | 0x90 | 0x9d)
+}
+
+fn decode_windows_1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9f => WINDOWS_1252[(byte - 0x80) as usize],
+        _ => char::from(byte),
+    }
+}
+
+/// Decoded [`Text`].
+///
+/// Created using [`Text::decode`] method. Invalid sequences are replaced with
+/// `U+FFFD REPLACEMENT CHARACTER`.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Encoding, Text};
+/// let text = Text::new(b"caf\xe9");
+/// assert_eq!(text.decode(Encoding::Latin1).to_string(), "café");
+/// ```
+///
+/// [`Text`]: struct.Text.html
+/// [`Text::decode`]: struct.Text.html#method.decode
+#[derive(Debug, Clone, Copy)]
+pub struct Decoded<'a> {
+    data: &'a [u8],
+    encoding: Encoding,
+}
+
+impl<'a> fmt::Display for Decoded<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use fmt::Write;
+
+        match self.encoding {
+            Encoding::Utf8 => {
+                let mut data = self.data;
+                loop {
+                    match str::from_utf8(data) {
+                        Ok(valid) => return f.write_str(valid),
+                        Err(err) => {
+                            let (valid, rest) = data.split_at(err.valid_up_to());
+                            f.write_str(str::from_utf8(valid).expect("valid utf8; qed"))?;
+                            f.write_char(char::REPLACEMENT_CHARACTER)?;
+                            data = &rest[err.error_len().unwrap_or(rest.len())..];
+                        }
+                    }
+                }
+            }
+            Encoding::Latin1 => self
+                .data
+                .iter()
+                .try_for_each(|&byte| f.write_char(char::from(byte))),
+            Encoding::Windows1252 => self
+                .data
+                .iter()
+                .try_for_each(|&byte| f.write_char(decode_windows_1252(byte))),
+            #[cfg(feature = "encoding")]
+            other => {
+                let encoding = match other {
+                    Encoding::ShiftJis => encoding_rs::SHIFT_JIS,
+                    Encoding::EucJp => encoding_rs::EUC_JP,
+                    Encoding::EucKr => encoding_rs::EUC_KR,
+                    Encoding::Gbk => encoding_rs::GBK,
+                    Encoding::Big5 => encoding_rs::BIG5,
+                    Encoding::Windows1251 => encoding_rs::WINDOWS_1251,
+                    Encoding::Utf8 | Encoding::Latin1 | Encoding::Windows1252 => unreachable!(),
+                };
+                f.write_str(&encoding.decode_without_bom_handling(self.data).0)
+            }
+        }
+    }
+}
+
+impl<'a> Text<'a> {
+    /// Decodes text using given `encoding`.
+    ///
+    /// Use [`guess_encoding`] if the encoding is not known.
+    ///
+    /// [`guess_encoding`]: fn.guess_encoding.html
+    pub fn decode(&self, encoding: Encoding) -> Decoded<'a> {
+        Decoded {
+            data: self.raw(),
+            encoding,
+        }
+    }
+}
+
+/// Shift-JIS structure statistics.
+///
+/// Japanese text consists of runs of double byte characters, whereas double byte sequences
+/// found in Latin texts are usually isolated accented letters followed by `ASCII`.
+#[derive(Default)]
+struct ShiftJisScore {
+    invalid: bool,
+    // double byte characters in runs of at least two
+    runs: usize,
+    // double byte characters surrounded by single byte characters
+    isolated: usize,
+}
+
+impl ShiftJisScore {
+    fn update(&mut self, data: &[u8]) {
+        let mut run = 0;
+        let mut bytes = data.iter();
+        loop {
+            let double = match bytes.next() {
+                Some(0x00..=0x7f) | Some(0xa1..=0xdf) => false,
+                Some(0x81..=0x9f) | Some(0xe0..=0xfc) => match bytes.next() {
+                    Some(0x40..=0x7e) | Some(0x80..=0xfc) => true,
+                    _ => {
+                        self.invalid = true;
+                        return;
+                    }
+                },
+                Some(_) => {
+                    self.invalid = true;
+                    return;
+                }
+                None => false,
+            };
+
+            if double {
+                run += 1;
+                continue;
+            }
+
+            match run {
+                0 => {}
+                1 => self.isolated += 1,
+                _ => self.runs += run,
+            }
+            run = 0;
+
+            if bytes.as_slice().is_empty() {
+                return;
+            }
+        }
+    }
+
+    #[cfg_attr(not(feature = "encoding"), allow(dead_code))]
+    fn is_likely(&self) -> bool {
+        !self.invalid && self.runs > 0 && self.runs >= self.isolated
+    }
+}
+
+/// Guesses encoding of the `texts`.
+///
+/// All texts of the file should be passed at once, because single short text is usually
+/// ambiguous. Guessing prefers `Utf8` (which also covers plain `ASCII`), then `ShiftJis`
+/// (with `encoding` feature) and falls back to `Windows1252`, or `Latin1` if the texts
+/// contain bytes not assigned in Windows-1252.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{guess_encoding, Encoding, Text};
+/// let texts = vec![Text::new(b"Piano"), Text::new(b"\x93Caf\xe9\x94")];
+/// assert_eq!(guess_encoding(texts), Encoding::Windows1252);
+/// ```
+pub fn guess_encoding<'a, I>(texts: I) -> Encoding
+where
+    I: IntoIterator<Item = Text<'a>>,
+{
+    let mut utf8 = true;
+    let mut undefined_windows_1252 = false;
+    let mut shift_jis = ShiftJisScore::default();
+
+    for text in texts {
+        let data = text.raw();
+        if data.is_ascii() {
+            continue;
+        }
+
+        utf8 &= str::from_utf8(data).is_ok();
+        undefined_windows_1252 |= data.iter().cloned().any(is_undefined_windows_1252);
+        shift_jis.update(data);
+    }
+
+    if utf8 {
+        return Encoding::Utf8;
+    }
+
+    #[cfg(feature = "encoding")]
+    {
+        if shift_jis.is_likely() {
+            return Encoding::ShiftJis;
+        }
+    }
+
+    if undefined_windows_1252 {
+        Encoding::Latin1
+    } else {
+        Encoding::Windows1252
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{guess_encoding, Encoding};
+    use crate::Text;
+
+    #[test]
+    fn test_decode() {
+        fn decode(data: &[u8], encoding: Encoding) -> String {
+            Text::new(data).decode(encoding).to_string()
+        }
+
+        assert_eq!(decode(b"Piano", Encoding::Utf8), "Piano");
+        assert_eq!(decode(b"caf\xc3\xa9", Encoding::Utf8), "café");
+        assert_eq!(decode(b"a\xffb\xc3", Encoding::Utf8), "a\u{fffd}b\u{fffd}");
+        assert_eq!(decode(b"caf\xe9", Encoding::Latin1), "café");
+        assert_eq!(decode(b"\x80\x81", Encoding::Latin1), "\u{80}\u{81}");
+        assert_eq!(decode(b"\x80\x81\x9f", Encoding::Windows1252), "€\u{81}Ÿ");
+        assert_eq!(decode(b"\x93hi\x94", Encoding::Windows1252), "“hi”");
+    }
+
+    #[test]
+    fn test_guess_encoding() {
+        fn guess(texts: &[&'static [u8]]) -> Encoding {
+            guess_encoding(texts.iter().map(|data| Text::new(data)))
+        }
+
+        assert_eq!(guess(&[]), Encoding::Utf8);
+        assert_eq!(guess(&[b"Piano", b"caf\xc3\xa9"]), Encoding::Utf8);
+        assert_eq!(guess(&[b"Piano", b"caf\xe9"]), Encoding::Windows1252);
+        assert_eq!(guess(&[b"caf\xe9", b"\x81"]), Encoding::Latin1);
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_shift_jis() {
+        // "ピアノ" and "海賊"
+        let texts = [b"\x83s\x83A\x83m" as &[u8], b"\x8aC\x91\xaf"];
+        let encoding = guess_encoding(texts.iter().map(|data| Text::new(data)));
+        assert_eq!(encoding, Encoding::ShiftJis);
+        assert_eq!(Text::new(texts[0]).decode(encoding).to_string(), "ピアノ");
+        assert_eq!(Text::new(texts[1]).decode(encoding).to_string(), "海賊");
+        assert_eq!(
+            guess_encoding(vec![Text::new(b"r\xe9sum\xe9")]),
+            Encoding::Windows1252
+        );
+    }
+}
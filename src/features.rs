@@ -1,5 +1,14 @@
 #[cfg(feature = "alloc")]
 mod alloc;
 
+#[cfg(feature = "serde")]
+mod serde;
+
+#[cfg(feature = "std")]
+mod std;
+
 #[cfg(feature = "alloc")]
 pub use self::alloc::*;
+
+#[cfg(feature = "std")]
+pub use self::std::*;
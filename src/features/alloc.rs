@@ -1,15 +1,67 @@
-/// Crate options behind `alloc` feature.
-extern crate alloc;
+//! Crate options behind `alloc` feature.
 
-use crate::{read, Error, Event, Format, Timing};
+mod clip;
+mod convert;
+mod lossless;
+mod meter;
+mod notes;
+mod owned;
+mod slice;
+mod state;
+mod stretch;
+mod summary;
+mod tempo;
+
+pub use self::clip::{Clip, ClipEvent};
+pub use self::convert::{ConductorMerge, MergeOptions};
+pub use self::lossless::RawLayout;
+pub(crate) use self::meter::Meters;
+pub use self::notes::*;
+pub use self::owned::*;
+pub use self::slice::Position;
+pub use self::state::{ChannelState, Parameter};
+pub use self::stretch::TempoScaling;
+pub use self::summary::Summary;
+pub use self::tempo::*;
+
+use crate::{
+    guess_encoding, read, write, Encoding, Error, ErrorKind, Event, EventKind, Format, Timing,
+};
 use alloc::vec::Vec;
 
 /// `MTrk` chunk.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Track<'a> {
     pub events: Vec<Event<'a>>,
 }
 
+impl<'a> Track<'a> {
+    /// Creates iterator over [`Event`]s paired with their absolute time in ticks.
+    ///
+    /// [`Event`]: struct.Event.html
+    pub fn absolute_iter(&self) -> impl Iterator<Item = (u64, &Event<'a>)> {
+        self.events.iter().scan(0u64, |tick, event| {
+            *tick += u64::from(event.time);
+            Some((*tick, event))
+        })
+    }
+}
+
+/// Chunk of unknown type preserved by [`Smf::read`].
+///
+/// [`Smf::read`]: struct.Smf.html#method.read
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnknownChunk<'a> {
+    /// Chunk type.
+    pub id: [u8; 4],
+    /// Chunk data.
+    pub data: &'a [u8],
+    /// Number of track chunks preceding this chunk.
+    pub position: usize,
+}
+
 /// Standard Midi File.
 ///
 /// # Example
@@ -26,33 +78,198 @@ pub struct Track<'a> {
 /// # }
 ///
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Smf<'a> {
     pub format: Format,
     pub tracks: Vec<Track<'a>>,
     pub timing: Timing,
+    /// Chunks of unknown types, written back by [`write`].
+    ///
+    /// [`write`]: #method.write
+    pub unknown_chunks: Vec<UnknownChunk<'a>>,
+    /// RIFF `RMID` container metadata. If set, [`write`] wraps `SMF` in the container.
+    ///
+    /// [`write`]: #method.write
+    pub rmid: Option<read::Rmid<'a>>,
 }
 
 impl<'a> Smf<'a> {
+    /// Reads entire `SMF`.
+    ///
+    /// Chunks of unknown types are preserved in [`unknown_chunks`]. `SMF` wrapped in RIFF `RMID`
+    /// container is unwrapped and container metadata is preserved in [`rmid`].
+    ///
+    /// [`unknown_chunks`]: #structfield.unknown_chunks
+    /// [`rmid`]: #structfield.rmid
     pub fn read(data: &'a [u8]) -> Result<Self, Error> {
         let reader = read::SmfReader::new(data)?;
         let header = reader.header_chunk();
         let mut tracks = Vec::with_capacity(header.tracks as usize);
-        let track_chunks = reader.track_chunk_iter();
-        for track_chunk_data in track_chunks {
-            let events = track_chunk_data?;
-            let track = Track {
-                events: events.collect::<Result<Vec<_>, _>>()?,
-            };
-            tracks.push(track);
+        let mut unknown_chunks = Vec::new();
+        for chunk in reader.chunk_iter() {
+            match chunk? {
+                read::Chunk::Track(events) => {
+                    let track = Track {
+                        events: events.collect::<Result<Vec<_>, _>>()?,
+                    };
+                    tracks.push(track);
+                }
+                read::Chunk::Unknown { id, data } => unknown_chunks.push(UnknownChunk {
+                    id,
+                    data,
+                    position: tracks.len(),
+                }),
+            }
         }
 
         let smf = Smf {
             format: header.format,
             tracks,
             timing: header.timing,
+            unknown_chunks,
+            rmid: reader.rmid(),
         };
 
         Ok(smf)
     }
+
+    /// Writes entire `SMF`, including [`unknown_chunks`] and [`rmid`] container.
+    ///
+    /// Fails if [`format`] is [`Format::Single`] and there is not exactly one track.
+    ///
+    /// [`format`]: #structfield.format
+    /// [`Format::Single`]: enum.Format.html#variant.Single
+    /// [`unknown_chunks`]: #structfield.unknown_chunks
+    /// [`rmid`]: #structfield.rmid
+    pub fn write(&self) -> Result<Vec<u8>, Error> {
+        let out = self.write_chunks(None, |out, _, track| {
+            write::write_track_chunk(out, &track.events)
+        })?;
+        if let Some(ref rmid) = self.rmid {
+            let mut container = Vec::new();
+            write::write_rmid(&mut container, rmid, &out)?;
+            return Ok(container);
+        }
+
+        Ok(out)
+    }
+
+    /// Reads entire `SMF` together with its original encoding.
+    ///
+    /// Unlike [`read`], bytes following the last chunk or the end of a track are not read as
+    /// events, but preserved in the returned [`RawLayout`]. Like [`read`], it accepts a header
+    /// with a number of tracks different from the track chunks, the number is preserved as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn edit(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let (mut smf, layout) = midi::Smf::read_lossless(bytes)?;
+    /// smf.tracks[0].events[0].time += 1;
+    /// let written = smf.write_lossless(&layout)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`read`]: #method.read
+    /// [`RawLayout`]: struct.RawLayout.html
+    pub fn read_lossless(data: &'a [u8]) -> Result<(Self, RawLayout<'a>), Error> {
+        lossless::read(data)
+    }
+
+    /// Writes entire `SMF` reusing the original encoding from [`RawLayout`].
+    ///
+    /// Unchanged `SMF` is written byte for byte identically, including running status,
+    /// redundant variable length quantity bytes and trailing data. Edited events are written
+    /// the same way as by [`write`].
+    ///
+    /// [`RawLayout`]: struct.RawLayout.html
+    /// [`write`]: #method.write
+    pub fn write_lossless(&self, layout: &RawLayout) -> Result<Vec<u8>, Error> {
+        // the original number of tracks in the header is kept unless tracks were added or removed
+        let header_tracks =
+            Some(layout.header_tracks()).filter(|_| layout.track_count() == self.tracks.len());
+        let mut out = self.write_chunks(header_tracks, |out, index, track| {
+            lossless::write_track_chunk(out, &track.events, layout, index)
+        })?;
+        out.extend_from_slice(layout.trailing());
+        if let Some(ref rmid) = self.rmid {
+            let mut container = Vec::new();
+            lossless::write_container(&mut container, rmid, &out, layout)?;
+            return Ok(container);
+        }
+
+        Ok(out)
+    }
+
+    /// Writes header chunk, track chunks and unknown chunks.
+    ///
+    /// The header contains `header_tracks` or the number of tracks if it is `None`.
+    fn write_chunks<F>(
+        &self,
+        header_tracks: Option<u16>,
+        mut write_track: F,
+    ) -> Result<Vec<u8>, Error>
+    where
+        F: FnMut(&mut Vec<u8>, usize, &Track<'a>) -> Result<(), Error>,
+    {
+        if self.tracks.len() > u16::MAX as usize {
+            return Err(Error {
+                context: "Smf::write: number of tracks must fit into 16 bits",
+                kind: ErrorKind::Invalid,
+            });
+        }
+        // `Smf::read` accepts format 0 files with any number of track chunks
+        if self.format == Format::Single && self.tracks.len() != 1 {
+            return Err(Error {
+                context: "Smf::write: format 0 smf must contain exactly 1 track",
+                kind: ErrorKind::Invalid,
+            });
+        }
+
+        let header = read::HeaderChunk {
+            format: self.format,
+            tracks: header_tracks.unwrap_or(self.tracks.len() as u16),
+            timing: self.timing,
+        };
+
+        let mut out = Vec::new();
+        write::write_header_chunk(&mut out, &header)?;
+        let mut unknown_chunks = self.unknown_chunks.iter().peekable();
+        for position in 0..=self.tracks.len() {
+            while let Some(chunk) = unknown_chunks.next_if(|chunk| chunk.position <= position) {
+                write::write_raw_chunk(&mut out, &chunk.id, chunk.data)?;
+            }
+
+            if let Some(track) = self.tracks.get(position) {
+                write_track(&mut out, position, track)?;
+            }
+        }
+
+        // chunks with position past the last track
+        for chunk in unknown_chunks {
+            write::write_raw_chunk(&mut out, &chunk.id, chunk.data)?;
+        }
+
+        Ok(out)
+    }
+
+    /// Guesses encoding of all text meta events.
+    ///
+    /// See [`guess_encoding`] for details.
+    ///
+    /// [`guess_encoding`]: fn.guess_encoding.html
+    pub fn guess_encoding(&self) -> Encoding {
+        let texts = self
+            .tracks
+            .iter()
+            .flat_map(|track| track.events.iter())
+            .filter_map(|event| match event.kind {
+                EventKind::Meta(ref meta) => meta.text(),
+                _ => None,
+            });
+        guess_encoding(texts)
+    }
 }
@@ -0,0 +1,395 @@
+use crate::read::{ClipReader, END_OF_CLIP};
+use crate::ump::{Form, Packet, Ump};
+use crate::{
+    write, Error, ErrorKind, EventKind, Format, MetaEvent, OwnedEvent, OwnedEventKind,
+    OwnedMetaEvent, OwnedSmf, OwnedSysexEvent, OwnedTrack, Smf, SysexEvent, Timing, Track,
+};
+use alloc::vec::Vec;
+
+/// Flex data status bank and status of metadata and performance text converted to meta events.
+const TEXTS: [(u8, u8); 3] = [(1, 3), (1, 4), (2, 1)];
+
+/// Packet of MIDI Clip File sequence data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipEvent {
+    /// Delta time in ticks.
+    pub time: u32,
+    pub packet: Packet,
+}
+
+/// MIDI Clip File (`SMF2CLIP`), storing Universal MIDI Packets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    pub ticks_per_quarter_note: u16,
+    /// Configuration header packets, excluding ticks per quarter note.
+    pub header: Vec<Packet>,
+    /// Sequence data, ending with end of clip message.
+    pub events: Vec<ClipEvent>,
+}
+
+fn flex(status_bank: u8, status: u8, form: Form, data: [u32; 3]) -> Packet {
+    Ump::FlexData {
+        group: 0,
+        form,
+        address: 1,
+        channel: 0,
+        status_bank,
+        status,
+        data,
+    }
+    .to_packet()
+}
+
+/// Splits `text` into flex data packets of 12 bytes.
+fn flex_text(status_bank: u8, status: u8, text: &[u8]) -> Vec<Packet> {
+    let chunks = text.chunks(12).collect::<Vec<_>>();
+    let last = chunks.len().saturating_sub(1);
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let form = match (index, last) {
+                (0, 0) => Form::Complete,
+                (0, _) => Form::Start,
+                (index, last) if index == last => Form::End,
+                _ => Form::Continue,
+            };
+            let mut bytes = [0; 12];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            let mut data = [0; 3];
+            for (word, bytes) in data.iter_mut().zip(bytes.chunks(4)) {
+                *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            flex(status_bank, status, form, data)
+        })
+        .collect()
+}
+
+/// Splits system exclusive `data` into 7 bit system exclusive packets of 6 bytes.
+fn sysex7(data: &[u8]) -> Vec<Packet> {
+    let data = data.strip_suffix(&[0xf7]).unwrap_or(data);
+    if data.is_empty() {
+        let ump = Ump::Sysex7 {
+            group: 0,
+            form: Form::Complete,
+            data: [0; 6],
+            len: 0,
+        };
+        return vec![ump.to_packet()];
+    }
+
+    let last = (data.len() - 1) / 6;
+    data.chunks(6)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let form = match (index, last) {
+                (0, 0) => Form::Complete,
+                (0, _) => Form::Start,
+                (index, last) if index == last => Form::End,
+                _ => Form::Continue,
+            };
+            let mut bytes = [0; 6];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            Ump::Sysex7 {
+                group: 0,
+                form,
+                data: bytes,
+                len: chunk.len() as u8,
+            }
+            .to_packet()
+        })
+        .collect()
+}
+
+impl Clip {
+    /// Reads entire MIDI Clip File.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn archive(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let clip = midi::Clip::read(bytes)?;
+    /// let smf = clip.to_smf();
+    /// let bytes = smf.as_smf().write()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read(data: &[u8]) -> Result<Clip, Error> {
+        let reader = ClipReader::new(data)?;
+        let clip = Clip {
+            ticks_per_quarter_note: reader.ticks_per_quarter_note(),
+            header: reader.header_iter().collect::<Result<_, _>>()?,
+            events: reader
+                .packet_iter()
+                .map(|result| result.map(|(time, packet)| ClipEvent { time, packet }))
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(clip)
+    }
+
+    /// Writes MIDI Clip File.
+    pub fn write(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write::write_clip(
+            &mut out,
+            self.ticks_per_quarter_note,
+            &self.header,
+            self.events.iter().map(|event| (event.time, event.packet)),
+        );
+        out
+    }
+
+    /// Converts MIDI 1.0 `track` to [`Clip`].
+    ///
+    /// MIDI events are stored as MIDI 1.0 channel voice messages, system exclusive events as 7 bit
+    /// system exclusive data. Tempo, time signature, track name, copyright notice and lyrics are
+    /// stored as flex data, other meta events are dropped.
+    ///
+    /// [`Clip`]: struct.Clip.html
+    pub fn from_track(track: &Track, ticks_per_quarter_note: u16) -> Clip {
+        let mut events = Vec::new();
+        let mut time = 0u32;
+        for event in &track.events {
+            time = time.saturating_add(event.time);
+            let packets = match event.kind {
+                EventKind::Midi(event) => vec![Ump::Midi1 { group: 0, event }.to_packet()],
+                EventKind::Sysex(SysexEvent::F0(data)) => sysex7(data),
+                EventKind::Meta(MetaEvent::SetTempo(tempo)) => {
+                    // tempo in units of 10 nanoseconds
+                    let tempo = tempo.saturating_mul(100);
+                    vec![flex(0, 0, Form::Complete, [tempo, 0, 0])]
+                }
+                EventKind::Meta(MetaEvent::TimeSignature { nn, dd, bb, .. }) => {
+                    let signature = u32::from_be_bytes([nn, dd, bb, 0]);
+                    vec![flex(0, 1, Form::Complete, [signature, 0, 0])]
+                }
+                EventKind::Meta(MetaEvent::Name(text)) => flex_text(1, 3, text.raw()),
+                EventKind::Meta(MetaEvent::CopyrightNotice(text)) => flex_text(1, 4, text.raw()),
+                EventKind::Meta(MetaEvent::Lyric(text)) => flex_text(2, 1, text.raw()),
+                EventKind::Meta(MetaEvent::EndOfTrack) => {
+                    vec![Packet::new(&[END_OF_CLIP, 0, 0, 0]).unwrap()]
+                }
+                _ => Vec::new(),
+            };
+            for packet in packets {
+                events.push(ClipEvent { time, packet });
+                time = 0;
+            }
+        }
+
+        Clip {
+            ticks_per_quarter_note,
+            header: Vec::new(),
+            events,
+        }
+    }
+
+    /// Converts `smf` to [`Clip`], merging all tracks. Fails if `smf` uses
+    /// [`Timing::Timecode`].
+    ///
+    /// [`Clip`]: struct.Clip.html
+    /// [`Timing::Timecode`]: enum.Timing.html#variant.Timecode
+    pub fn from_smf(smf: &Smf) -> Result<Clip, Error> {
+        match smf.timing {
+            Timing::Metrical(ppqn) => Ok(Clip::from_track(&Track::merge(&smf.tracks), ppqn)),
+            Timing::Timecode { .. } => Err(Error {
+                context: "Clip::from_smf: smf uses timecode timing",
+                kind: ErrorKind::Invalid,
+            }),
+        }
+    }
+
+    /// Converts the clip to MIDI 1.0 track.
+    ///
+    /// MIDI 2.0 channel voice messages are translated with [`Midi2Event::to_midi1`]. Flex data
+    /// written by [`from_track`] is converted back to meta events. Packets without MIDI 1.0
+    /// equivalent are dropped and their delta time is added to the following event.
+    ///
+    /// [`Midi2Event::to_midi1`]: ump/struct.Midi2Event.html#method.to_midi1
+    /// [`from_track`]: #method.from_track
+    pub fn to_track(&self) -> OwnedTrack {
+        let mut events = Vec::new();
+        let mut time = 0u32;
+        let mut sysex = Vec::new();
+        let mut text = Vec::new();
+        for event in &self.events {
+            time = time.saturating_add(event.time);
+            let kinds = match Ump::from_packet(&event.packet) {
+                Ok(Ump::Midi1 { event, .. }) => vec![OwnedEventKind::Midi(event)],
+                Ok(Ump::Midi2 { event, .. }) => {
+                    event.to_midi1().map(OwnedEventKind::Midi).collect()
+                }
+                Ok(Ump::Sysex7 {
+                    form, data, len, ..
+                }) => {
+                    if let Form::Complete | Form::Start = form {
+                        sysex.clear();
+                    }
+                    sysex.extend_from_slice(&data[..usize::from(len)]);
+                    match form {
+                        Form::Complete | Form::End => {
+                            sysex.push(0xf7);
+                            vec![OwnedEventKind::Sysex(OwnedSysexEvent::F0(core::mem::take(
+                                &mut sysex,
+                            )))]
+                        }
+                        Form::Start | Form::Continue => Vec::new(),
+                    }
+                }
+                Ok(Ump::FlexData {
+                    form,
+                    status_bank,
+                    status,
+                    data,
+                    ..
+                }) => match (status_bank, status) {
+                    (0, 0) => vec![OwnedEventKind::Meta(OwnedMetaEvent::SetTempo(
+                        (data[0] / 100).min(0xff_ffff),
+                    ))],
+                    (0, 1) => {
+                        let [nn, dd, bb, _] = data[0].to_be_bytes();
+                        vec![OwnedEventKind::Meta(OwnedMetaEvent::TimeSignature {
+                            nn,
+                            dd,
+                            cc: 24,
+                            bb,
+                        })]
+                    }
+                    key if TEXTS.contains(&key) => {
+                        if let Form::Complete | Form::Start = form {
+                            text.clear();
+                        }
+                        text.extend(data.iter().flat_map(|word| word.to_be_bytes()));
+                        match form {
+                            Form::Complete | Form::End => {
+                                let end = text.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+                                text.truncate(end);
+                                let text = core::mem::take(&mut text);
+                                let meta = match key {
+                                    (1, 3) => OwnedMetaEvent::Name(text),
+                                    (1, 4) => OwnedMetaEvent::CopyrightNotice(text),
+                                    _ => OwnedMetaEvent::Lyric(text),
+                                };
+                                vec![OwnedEventKind::Meta(meta)]
+                            }
+                            Form::Start | Form::Continue => Vec::new(),
+                        }
+                    }
+                    _ => Vec::new(),
+                },
+                Ok(Ump::Stream { .. }) if event.packet.words()[0] == END_OF_CLIP => {
+                    vec![OwnedEventKind::Meta(OwnedMetaEvent::EndOfTrack)]
+                }
+                _ => Vec::new(),
+            };
+            for kind in kinds {
+                events.push(OwnedEvent { time, kind });
+                time = 0;
+            }
+        }
+
+        let ended = events.last().map(|event| &event.kind)
+            == Some(&OwnedEventKind::Meta(OwnedMetaEvent::EndOfTrack));
+        if !ended {
+            events.push(OwnedEvent {
+                time,
+                kind: OwnedEventKind::Meta(OwnedMetaEvent::EndOfTrack),
+            });
+        }
+        OwnedTrack { events }
+    }
+
+    /// Converts the clip to single track `SMF`, see [`to_track`].
+    ///
+    /// [`to_track`]: #method.to_track
+    pub fn to_smf(&self) -> OwnedSmf {
+        OwnedSmf {
+            format: Format::Single,
+            tracks: vec![self.to_track()],
+            timing: Timing::Metrical(self.ticks_per_quarter_note),
+            unknown_chunks: Vec::new(),
+            rmid: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clip, ClipEvent};
+    use crate::test_util::{end_of_track, meta, note_on, tempo};
+    use crate::ump::{Form, Ump, Utility};
+    use crate::{Event, EventKind, MetaEvent, OwnedTrack, SysexEvent, Text, Track};
+
+    #[test]
+    fn test_track_round_trip() {
+        let track = Track {
+            events: vec![
+                meta(0, MetaEvent::Name(Text::new(b"A long track name"))),
+                tempo(0, 500_000),
+                meta(
+                    0,
+                    MetaEvent::TimeSignature {
+                        nn: 3,
+                        dd: 2,
+                        cc: 24,
+                        bb: 8,
+                    },
+                ),
+                note_on(10, 1, 60, 100),
+                Event {
+                    time: 0x12_3456,
+                    kind: EventKind::Sysex(SysexEvent::F0(&[1, 2, 3, 4, 5, 6, 7, 0xf7])),
+                },
+                end_of_track(5),
+            ],
+        };
+
+        let clip = Clip::from_track(&track, 96);
+        // name takes 2 packets and sysex takes 2 packets
+        assert_eq!(clip.events.len(), 8);
+        assert_eq!(clip.events[4].time, 10);
+        assert_eq!(
+            Ump::from_packet(&clip.events[5].packet).unwrap(),
+            Ump::Sysex7 {
+                group: 0,
+                form: Form::Start,
+                data: [1, 2, 3, 4, 5, 6],
+                len: 6
+            }
+        );
+
+        let bytes = clip.write();
+        assert!(bytes.starts_with(b"SMF2CLIP"));
+        let read = Clip::read(&bytes).unwrap();
+        assert_eq!(read, clip);
+        assert_eq!(read.to_track(), OwnedTrack::from(track));
+    }
+
+    #[test]
+    fn test_read() {
+        let no_op = Ump::Utility(Utility::NoOp).to_packet();
+        let clip = Clip {
+            ticks_per_quarter_note: 480,
+            header: vec![no_op],
+            events: vec![ClipEvent {
+                time: 0x20_0000,
+                packet: no_op,
+            }],
+        };
+        let bytes = clip.write();
+        // delta clockstamps are limited to 20 bits, so the no-op needs 3 of them
+        assert_eq!(bytes.len(), 8 + 8 + 8 + 20 + 16 + 20);
+
+        let read = Clip::read(&bytes).unwrap();
+        assert_eq!(read.ticks_per_quarter_note, 480);
+        assert_eq!(read.header, vec![no_op]);
+        assert_eq!(read.events.len(), 2);
+        assert_eq!(read.events[0].time, 0x20_0000);
+        assert_eq!(read.to_track().events.len(), 1);
+
+        assert!(Clip::read(&bytes[..40]).is_err());
+        assert!(Clip::read(b"SMF2CLIP").is_err());
+    }
+}
@@ -0,0 +1,626 @@
+use crate::transform::{ChannelMap, Transform, DRUM_CHANNEL};
+use crate::{
+    Error, ErrorKind, Event, EventKind, Format, MetaEvent, Smf, Timing, Track, DEFAULT_TEMPO,
+};
+use alloc::vec::Vec;
+
+fn end_of_track(time: u32) -> Event<'static> {
+    Event {
+        time,
+        kind: EventKind::Meta(MetaEvent::EndOfTrack),
+    }
+}
+
+fn is_end_of_track(event: &Event) -> bool {
+    matches!(event.kind, EventKind::Meta(MetaEvent::EndOfTrack))
+}
+
+/// Returns absolute time of the end of the `track`.
+fn end_tick(track: &Track) -> u64 {
+    track.absolute_iter().last().map_or(0, |(tick, _)| tick)
+}
+
+impl<'a> Track<'a> {
+    /// Creates new [`Track`] from [`Event`]s paired with their absolute time in ticks.
+    ///
+    /// Events must be ordered by time, their `time` field is overwritten. Delta times which do
+    /// not fit into 32 bits are saturated.
+    ///
+    /// [`Track`]: struct.Track.html
+    /// [`Event`]: struct.Event.html
+    pub fn from_absolute<I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, Event<'a>)>,
+    {
+        let mut last = 0;
+        let events = events
+            .into_iter()
+            .map(|(tick, mut event)| {
+                event.time = tick.saturating_sub(last).min(u64::from(u32::MAX)) as u32;
+                last = last.max(tick);
+                event
+            })
+            .collect();
+        Track { events }
+    }
+
+    /// Merges `tracks` into a single [`Track`] ordered by time.
+    ///
+    /// Events at the same time keep the order of the `tracks`. [`MetaEvent::EndOfTrack`] events
+    /// are replaced with a single one at the end of the longest track.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn merge(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let smf = midi::Smf::read(bytes)?;
+    /// let merged = midi::Track::merge(&smf.tracks);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Track`]: struct.Track.html
+    /// [`MetaEvent::EndOfTrack`]: enum.MetaEvent.html#variant.EndOfTrack
+    pub fn merge<'t, I>(tracks: I) -> Self
+    where
+        'a: 't,
+        I: IntoIterator<Item = &'t Track<'a>>,
+    {
+        let mut end = 0;
+        let mut events = Vec::new();
+        for track in tracks {
+            end = end.max(end_tick(track));
+            events.extend(
+                track
+                    .absolute_iter()
+                    .filter(|(_, event)| !is_end_of_track(event))
+                    .map(|(tick, event)| (tick, *event)),
+            );
+        }
+        // stable sort keeps the order of tracks
+        events.sort_by_key(|&(tick, _)| tick);
+        events.push((end, end_of_track(0)));
+        Track::from_absolute(events)
+    }
+
+    /// Splits the track into a track of meta and sysex events, followed by a track for every
+    /// used channel.
+    ///
+    /// [`MetaEvent::EndOfTrack`] of every track is at the end of the original track.
+    ///
+    /// [`MetaEvent::EndOfTrack`]: enum.MetaEvent.html#variant.EndOfTrack
+    pub fn split_channels(&self) -> Vec<Track<'a>> {
+        let end = end_tick(self);
+        let mut channels = [false; 16];
+        for event in &self.events {
+            if let EventKind::Midi(midi_event) = event.kind {
+                channels[(midi_event.channel & 0x0f) as usize] = true;
+            }
+        }
+
+        let track = |channel: Option<u8>| {
+            let events = self
+                .absolute_iter()
+                .filter(|(_, event)| !is_end_of_track(event))
+                .filter(|(_, event)| match event.kind {
+                    EventKind::Midi(midi_event) => Some(midi_event.channel & 0x0f) == channel,
+                    _ => channel.is_none(),
+                })
+                .map(|(tick, event)| (tick, *event))
+                .chain(Some((end, end_of_track(0))));
+            Track::from_absolute(events)
+        };
+
+        let mut tracks = vec![track(None)];
+        tracks.extend(
+            (0..16u8)
+                .filter(|&channel| channels[channel as usize])
+                .map(|channel| track(Some(channel))),
+        );
+        tracks
+    }
+}
+
+impl<'a> Smf<'a> {
+    /// Converts `SMF` to `format`.
+    ///
+    /// Converting to [`Format::Single`] merges all tracks, see [`Track::merge`]. Converting from
+    /// [`Format::Single`] to [`Format::MultiTrack`] splits the track by channels, see
+    /// [`Track::split_channels`]. Independent sequences of [`Format::MultiSequence`] cannot be
+    /// converted.
+    ///
+    /// [`Format::Single`]: enum.Format.html#variant.Single
+    /// [`Format::MultiTrack`]: enum.Format.html#variant.MultiTrack
+    /// [`Format::MultiSequence`]: enum.Format.html#variant.MultiSequence
+    /// [`Track::merge`]: struct.Track.html#method.merge
+    /// [`Track::split_channels`]: struct.Track.html#method.split_channels
+    pub fn to_format(&self, format: Format) -> Result<Smf<'a>, Error> {
+        let tracks = match (self.format, format) {
+            (from, to) if from == to => self.tracks.clone(),
+            (Format::MultiTrack, Format::Single) => vec![Track::merge(&self.tracks)],
+            (Format::Single, Format::MultiTrack) => self
+                .tracks
+                .first()
+                .map(Track::split_channels)
+                .unwrap_or_default(),
+            _ => {
+                return Err(Error {
+                    context: "Smf::to_format: multi sequence format cannot be converted",
+                    kind: ErrorKind::Invalid,
+                })
+            }
+        };
+
+        let smf = Smf {
+            format,
+            tracks,
+            timing: self.timing,
+            // unknown chunks are related to the original tracks
+            unknown_chunks: Vec::new(),
+            rmid: self.rmid,
+        };
+
+        Ok(smf)
+    }
+
+    /// Merges tracks of all `smfs` into a single [`Format::MultiTrack`] `SMF` using default
+    /// [`MergeOptions`].
+    ///
+    /// [`Format::MultiTrack`]: enum.Format.html#variant.MultiTrack
+    /// [`MergeOptions`]: struct.MergeOptions.html
+    pub fn merge(smfs: &[Smf<'a>]) -> Result<Smf<'a>, Error> {
+        Self::merge_with(smfs, MergeOptions::default())
+    }
+
+    /// Merges tracks of all `smfs` into a single [`Format::MultiTrack`] `SMF`.
+    ///
+    /// [`Timing::Metrical`] `smfs` are rescaled to the highest ppqn, see [`Smf::to_ppqn`].
+    /// [`Timing::Timecode`] `smfs` must use the same timing. [`Format::Single`] `smfs` are
+    /// split by channels first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// use midi::{ConductorMerge, MergeOptions, Smf};
+    ///
+    /// # fn merge(first: &[u8], second: &[u8]) -> Result<(), midi::Error> {
+    /// let smfs = [Smf::read(first)?, Smf::read(second)?];
+    /// let options = MergeOptions::new()
+    ///     .conductor(ConductorMerge::First)
+    ///     .remap_channels(true);
+    /// let merged = Smf::merge_with(&smfs, options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Format::MultiTrack`]: enum.Format.html#variant.MultiTrack
+    /// [`Format::Single`]: enum.Format.html#variant.Single
+    /// [`Timing::Metrical`]: enum.Timing.html#variant.Metrical
+    /// [`Timing::Timecode`]: enum.Timing.html#variant.Timecode
+    /// [`Smf::to_ppqn`]: struct.Smf.html#method.to_ppqn
+    pub fn merge_with(smfs: &[Smf<'a>], options: MergeOptions) -> Result<Smf<'a>, Error> {
+        let timing = common_timing(smfs, "Smf::merge: smfs use different timecode timing")?;
+        let mut smfs = smfs
+            .iter()
+            .map(|smf| normalize(smf, timing))
+            .collect::<Result<Vec<_>, _>>()?;
+        if options.remap_channels {
+            remap_channels(&mut smfs)?;
+        }
+
+        let mut conductors = Vec::new();
+        let mut tracks = Vec::new();
+        for smf in &smfs {
+            let mut smf_tracks = smf.tracks.iter();
+            match (options.conductor, has_conductor(smf)) {
+                (ConductorMerge::Keep, _) | (_, false) => (),
+                (ConductorMerge::First, true) if !conductors.is_empty() => {
+                    smf_tracks.next();
+                }
+                (_, true) => conductors.extend(smf_tracks.next()),
+            }
+            tracks.extend(smf_tracks.cloned());
+        }
+
+        if !conductors.is_empty() {
+            tracks.insert(0, Track::merge(conductors));
+        }
+
+        let smf = Smf {
+            format: Format::MultiTrack,
+            tracks,
+            timing,
+            unknown_chunks: Vec::new(),
+            rmid: None,
+        };
+
+        Ok(smf)
+    }
+
+    /// Concatenates `smfs` back-to-back into a single [`Format::MultiTrack`] `SMF`.
+    ///
+    /// Every song starts after the end of the longest track of the previous song. Conductor
+    /// tracks are concatenated into the first track, other tracks are added as new tracks
+    /// starting at the beginning of their song. Timing is reconciled like in [`Smf::merge`]. A
+    /// song which does not set tempo at its beginning is played with [`DEFAULT_TEMPO`].
+    ///
+    /// [`Format::MultiTrack`]: enum.Format.html#variant.MultiTrack
+    /// [`Smf::merge`]: struct.Smf.html#method.merge
+    /// [`DEFAULT_TEMPO`]: constant.DEFAULT_TEMPO.html
+    pub fn append(smfs: &[Smf<'a>]) -> Result<Smf<'a>, Error> {
+        let timing = common_timing(smfs, "Smf::append: smfs use different timecode timing")?;
+        let smfs = smfs
+            .iter()
+            .map(|smf| normalize(smf, timing))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut offset = 0;
+        let mut conductor = Vec::new();
+        let mut tracks = Vec::new();
+        for smf in &smfs {
+            let mut smf_tracks = smf.tracks.iter();
+            let initial_tempo = |track: &Track| {
+                track
+                    .absolute_iter()
+                    .take_while(|&(tick, _)| tick == 0)
+                    .any(|(_, event)| matches!(event.kind, EventKind::Meta(MetaEvent::SetTempo(_))))
+            };
+            if offset > 0 && !smf.tracks.iter().any(initial_tempo) {
+                let tempo = Event {
+                    time: 0,
+                    kind: EventKind::Meta(MetaEvent::SetTempo(DEFAULT_TEMPO)),
+                };
+                conductor.push((offset, tempo));
+            }
+            if has_conductor(smf) {
+                conductor.extend(events_from(smf_tracks.next(), offset));
+            }
+            for track in smf_tracks {
+                let events = events_from(Some(track), offset)
+                    .chain(Some((offset + end_tick(track), end_of_track(0))));
+                tracks.push(Track::from_absolute(events));
+            }
+            offset += smf.tracks.iter().map(end_tick).max().unwrap_or(0);
+        }
+
+        conductor.push((offset, end_of_track(0)));
+        tracks.insert(0, Track::from_absolute(conductor));
+
+        let smf = Smf {
+            format: Format::MultiTrack,
+            tracks,
+            timing,
+            unknown_chunks: Vec::new(),
+            rmid: None,
+        };
+
+        Ok(smf)
+    }
+}
+
+/// What [`Smf::merge_with`] does with conductor tracks.
+///
+/// Conductor track is the first track of [`Format::MultiTrack`] `SMF` without midi events.
+///
+/// [`Smf::merge_with`]: struct.Smf.html#method.merge_with
+/// [`Format::MultiTrack`]: enum.Format.html#variant.MultiTrack
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConductorMerge {
+    /// Merge all conductor tracks into the first track.
+    #[default]
+    Merge,
+    /// Use the first conductor track and drop the others.
+    First,
+    /// Keep conductor tracks as ordinary tracks.
+    Keep,
+}
+
+/// Options of [`Smf::merge_with`].
+///
+/// [`Smf::merge_with`]: struct.Smf.html#method.merge_with
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MergeOptions {
+    conductor: ConductorMerge,
+    remap_channels: bool,
+}
+
+impl MergeOptions {
+    /// Creates new [`MergeOptions`] merging conductor tracks and keeping channels.
+    ///
+    /// [`MergeOptions`]: struct.MergeOptions.html
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what to do with conductor tracks.
+    pub fn conductor(mut self, conductor: ConductorMerge) -> Self {
+        self.conductor = conductor;
+        self
+    }
+
+    /// Moves channels used by several `SMF`s to unused channels. [`DRUM_CHANNEL`] is shared.
+    ///
+    /// [`DRUM_CHANNEL`]: transform/constant.DRUM_CHANNEL.html
+    pub fn remap_channels(mut self, remap_channels: bool) -> Self {
+        self.remap_channels = remap_channels;
+        self
+    }
+}
+
+/// Returns timing all `smfs` can be converted to.
+fn common_timing(smfs: &[Smf], context: &'static str) -> Result<Timing, Error> {
+    let mut timing = match smfs.first() {
+        Some(smf) => smf.timing,
+        None => return Ok(Timing::Metrical(96)),
+    };
+
+    for smf in smfs {
+        timing = match (timing, smf.timing) {
+            (Timing::Metrical(ppqn), Timing::Metrical(other)) => Timing::Metrical(ppqn.max(other)),
+            (timing, other) if timing == other => timing,
+            _ => {
+                return Err(Error {
+                    context,
+                    kind: ErrorKind::Invalid,
+                })
+            }
+        };
+    }
+
+    Ok(timing)
+}
+
+/// Converts `smf` to `timing` and [`Format::MultiTrack`].
+///
+/// [`Format::MultiTrack`]: enum.Format.html#variant.MultiTrack
+fn normalize<'a>(smf: &Smf<'a>, timing: Timing) -> Result<Smf<'a>, Error> {
+    let smf = match (smf.timing, timing) {
+        (Timing::Metrical(ppqn), Timing::Metrical(target)) if ppqn != target => {
+            smf.to_ppqn(target)?
+        }
+        _ => smf.clone(),
+    };
+    smf.to_format(Format::MultiTrack)
+}
+
+fn has_conductor(smf: &Smf) -> bool {
+    match smf.tracks.first() {
+        Some(track) => track
+            .events
+            .iter()
+            .all(|event| !matches!(event.kind, EventKind::Midi(_))),
+        None => false,
+    }
+}
+
+/// Returns events of the `track` without [`MetaEvent::EndOfTrack`], moved by `offset` ticks.
+///
+/// [`MetaEvent::EndOfTrack`]: enum.MetaEvent.html#variant.EndOfTrack
+fn events_from<'t, 'a: 't>(
+    track: Option<&'t Track<'a>>,
+    offset: u64,
+) -> impl Iterator<Item = (u64, Event<'a>)> + 't {
+    track
+        .into_iter()
+        .flat_map(|track| track.absolute_iter())
+        .filter(|(_, event)| !is_end_of_track(event))
+        .map(move |(tick, event)| (offset + tick, *event))
+}
+
+/// Moves channels of later `smfs` which collide with channels of earlier `smfs`.
+fn remap_channels(smfs: &mut [Smf]) -> Result<(), Error> {
+    let mut allocated = [false; 16];
+    allocated[DRUM_CHANNEL as usize] = true;
+    for smf in smfs {
+        let mut used = [false; 16];
+        for event in smf.tracks.iter().flat_map(|track| &track.events) {
+            if let EventKind::Midi(midi_event) = event.kind {
+                used[(midi_event.channel & 0x0f) as usize] = true;
+            }
+        }
+
+        let mut channel_map = ChannelMap::new();
+        for channel in (0..16u8).filter(|&channel| channel != DRUM_CHANNEL) {
+            if !used[channel as usize] || !allocated[channel as usize] {
+                allocated[channel as usize] |= used[channel as usize];
+                continue;
+            }
+            let free = (0..16u8)
+                .find(|&free| !allocated[free as usize] && !used[free as usize])
+                .ok_or(Error {
+                    context: "Smf::merge: not enough channels to avoid collisions",
+                    kind: ErrorKind::Invalid,
+                })?;
+            allocated[free as usize] = true;
+            channel_map = channel_map.map(channel, free);
+        }
+
+        for track in &mut smf.tracks {
+            channel_map.apply_track(track);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{end_of_track, tempo};
+    use crate::{test_util, ConductorMerge, Event, Format, MergeOptions, Smf, Timing, Track};
+
+    fn note_on(time: u32, channel: u8, key: u8) -> Event<'static> {
+        test_util::note_on(time, channel, key, 64)
+    }
+
+    #[test]
+    fn test_merge_and_split() {
+        let conductor = Track {
+            events: vec![tempo(0, 400_000), tempo(20, 400_000), end_of_track(0)],
+        };
+        let drums = Track {
+            events: vec![note_on(10, 9, 36), note_on(10, 9, 38), end_of_track(30)],
+        };
+        let bass = Track {
+            events: vec![note_on(20, 1, 40), end_of_track(0)],
+        };
+
+        let merged = Track::merge(&[conductor.clone(), drums.clone(), bass.clone()]);
+        assert_eq!(
+            merged.events,
+            vec![
+                tempo(0, 400_000),
+                note_on(10, 9, 36),
+                tempo(10, 400_000),
+                note_on(0, 9, 38),
+                note_on(0, 1, 40),
+                end_of_track(30),
+            ]
+        );
+
+        let split = merged.split_channels();
+        assert_eq!(split.len(), 3);
+        assert_eq!(
+            split[0].events,
+            vec![tempo(0, 400_000), tempo(20, 400_000), end_of_track(30)]
+        );
+        assert_eq!(split[1].events, vec![note_on(20, 1, 40), end_of_track(30)]);
+        assert_eq!(split[2], Track::merge(&[drums]));
+    }
+
+    #[test]
+    fn test_to_format() {
+        let smf = test_util::smf(
+            Format::Single,
+            96,
+            vec![vec![tempo(0, 400_000), note_on(10, 2, 60), end_of_track(0)]],
+        );
+
+        let multi_track = smf.to_format(Format::MultiTrack).unwrap();
+        assert_eq!(multi_track.tracks.len(), 2);
+        assert_eq!(multi_track.to_format(Format::Single).unwrap(), smf);
+        assert!(smf.to_format(Format::MultiSequence).is_err());
+    }
+
+    #[test]
+    fn test_merge_with() {
+        let first = test_util::smf(
+            Format::MultiTrack,
+            96,
+            vec![
+                vec![tempo(0, 400_000), end_of_track(96)],
+                vec![note_on(48, 0, 60), end_of_track(0)],
+            ],
+        );
+        let second = test_util::smf(
+            Format::MultiTrack,
+            48,
+            vec![
+                vec![tempo(12, 400_000), end_of_track(0)],
+                vec![note_on(24, 0, 62), note_on(0, 9, 36), end_of_track(0)],
+            ],
+        );
+        let smfs = [first, second];
+
+        let merged = Smf::merge(&smfs).unwrap();
+        assert_eq!(merged.timing, Timing::Metrical(96));
+        assert_eq!(
+            merged.tracks,
+            vec![
+                Track {
+                    events: vec![tempo(0, 400_000), tempo(24, 400_000), end_of_track(72)],
+                },
+                smfs[0].tracks[1].clone(),
+                Track {
+                    events: vec![note_on(48, 0, 62), note_on(0, 9, 36), end_of_track(0)],
+                },
+            ]
+        );
+
+        let options = MergeOptions::new()
+            .conductor(ConductorMerge::First)
+            .remap_channels(true);
+        let merged = Smf::merge_with(&smfs, options).unwrap();
+        assert_eq!(merged.tracks.len(), 3);
+        assert_eq!(merged.tracks[0], smfs[0].tracks[0]);
+        assert_eq!(
+            merged.tracks[2].events,
+            vec![note_on(48, 1, 62), note_on(0, 9, 36), end_of_track(0)]
+        );
+
+        let options = MergeOptions::new().conductor(ConductorMerge::Keep);
+        assert_eq!(Smf::merge_with(&smfs, options).unwrap().tracks.len(), 4);
+    }
+
+    #[test]
+    fn test_merge_first_conductor() {
+        let smfs = [
+            test_util::smf(
+                Format::MultiTrack,
+                96,
+                vec![vec![note_on(48, 0, 60), end_of_track(0)]],
+            ),
+            test_util::smf(
+                Format::MultiTrack,
+                96,
+                vec![
+                    vec![tempo(24, 400_000), end_of_track(0)],
+                    vec![end_of_track(0)],
+                ],
+            ),
+            test_util::smf(
+                Format::MultiTrack,
+                96,
+                vec![
+                    vec![tempo(48, 400_000), end_of_track(0)],
+                    vec![end_of_track(0)],
+                ],
+            ),
+        ];
+
+        // the first smf has no conductor track, so the conductor of the second one is used
+        let options = MergeOptions::new().conductor(ConductorMerge::First);
+        let merged = Smf::merge_with(&smfs, options).unwrap();
+        assert_eq!(merged.tracks.len(), 4);
+        assert_eq!(merged.tracks[0], smfs[1].tracks[0]);
+        assert_eq!(merged.tracks[1], smfs[0].tracks[0]);
+    }
+
+    #[test]
+    fn test_append() {
+        let first = test_util::smf(
+            Format::MultiTrack,
+            96,
+            vec![
+                vec![tempo(0, 400_000), end_of_track(0)],
+                vec![note_on(48, 0, 60), end_of_track(48)],
+            ],
+        );
+        let second = test_util::smf(
+            Format::MultiTrack,
+            96,
+            vec![vec![note_on(10, 0, 62), end_of_track(0)]],
+        );
+
+        let appended = Smf::append(&[first, second]).unwrap();
+        assert_eq!(
+            appended.tracks,
+            vec![
+                Track {
+                    events: vec![
+                        tempo(0, 400_000),
+                        tempo(96, crate::DEFAULT_TEMPO),
+                        end_of_track(10),
+                    ],
+                },
+                Track {
+                    events: vec![note_on(48, 0, 60), end_of_track(48)],
+                },
+                Track {
+                    events: vec![note_on(106, 0, 62), end_of_track(0)],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use crate::read::{self, RawEvent};
+use crate::{write, Error, ErrorKind, Event, EventKind, MetaEvent, Smf, Track, UnknownChunk};
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// Number of original events searched for a match when writing an edited track.
+const LOOKAHEAD: usize = 16;
+
+/// Original bytes of `SMF` read by [`Smf::read_lossless`].
+///
+/// Used by [`Smf::write_lossless`] to reproduce the original encoding of unchanged parts.
+///
+/// [`Smf::read_lossless`]: struct.Smf.html#method.read_lossless
+/// [`Smf::write_lossless`]: struct.Smf.html#method.write_lossless
+#[derive(Debug, Clone)]
+pub struct RawLayout<'a> {
+    data: &'a [u8],
+    /// Number of tracks specified by the header chunk.
+    header_tracks: u16,
+    container: Option<RawContainer<'a>>,
+    tracks: Vec<RawTrack<'a>>,
+    trailing: &'a [u8],
+}
+
+/// Location of `SMF` within RIFF `RMID` container.
+#[derive(Debug, Clone)]
+struct RawContainer<'a> {
+    rmid: read::Rmid<'a>,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Clone)]
+struct RawTrack<'a> {
+    events: Vec<RawEvent<'a>>,
+    trailing: &'a [u8],
+}
+
+impl<'a> RawLayout<'a> {
+    /// Returns original events of the track at `index`.
+    pub fn track_events(&self, index: usize) -> Option<&[RawEvent<'a>]> {
+        self.tracks.get(index).map(|track| &track.events[..])
+    }
+
+    /// Returns bytes following the last chunk, which could not be read as a chunk.
+    pub fn trailing(&self) -> &'a [u8] {
+        self.trailing
+    }
+
+    pub(crate) fn header_tracks(&self) -> u16 {
+        self.header_tracks
+    }
+
+    pub(crate) fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+}
+
+pub(crate) fn read(data: &[u8]) -> Result<(Smf<'_>, RawLayout<'_>), Error> {
+    let (container, smf_data) = if read::is_rmid(data) {
+        let (rmid, smf_data) = read::read_rmid(data)?;
+        let start = smf_data.as_ptr() as usize - data.as_ptr() as usize;
+        let container = RawContainer {
+            rmid,
+            start,
+            end: start + smf_data.len(),
+        };
+        (Some(container), smf_data)
+    } else {
+        (None, data)
+    };
+
+    let mut cursor = smf_data;
+    let header = read::read_header_chunk(&mut cursor)?;
+    let mut tracks = Vec::with_capacity(header.tracks as usize);
+    let mut raw_tracks = Vec::with_capacity(header.tracks as usize);
+    let mut unknown_chunks = Vec::new();
+    while !cursor.is_empty() {
+        let mut next = cursor;
+        // the rest of the data is preserved as it is
+        let chunk = match read::read_chunk(&mut next) {
+            Ok(chunk) => chunk,
+            Err(_) => break,
+        };
+        cursor = next;
+
+        match chunk {
+            read::Chunk::Track(mut track_chunk) => {
+                let mut events = Vec::new();
+                let mut raw_events = Vec::new();
+                while let Some(raw_event) = track_chunk.next_raw() {
+                    let raw_event = raw_event?;
+                    events.push(raw_event.event);
+                    raw_events.push(raw_event);
+                    // bytes following the end of track are preserved as they are
+                    if let EventKind::Meta(MetaEvent::EndOfTrack) = raw_event.event.kind {
+                        break;
+                    }
+                }
+                tracks.push(Track { events });
+                raw_tracks.push(RawTrack {
+                    events: raw_events,
+                    trailing: track_chunk.raw(),
+                });
+            }
+            read::Chunk::Unknown { id, data } => unknown_chunks.push(UnknownChunk {
+                id,
+                data,
+                position: tracks.len(),
+            }),
+        }
+    }
+
+    let smf = Smf {
+        format: header.format,
+        tracks,
+        timing: header.timing,
+        unknown_chunks,
+        rmid: container.as_ref().map(|container| container.rmid),
+    };
+
+    let layout = RawLayout {
+        data,
+        header_tracks: header.tracks,
+        container,
+        tracks: raw_tracks,
+        trailing: cursor,
+    };
+
+    Ok((smf, layout))
+}
+
+pub(crate) fn write_track_chunk(
+    out: &mut Vec<u8>,
+    events: &[Event],
+    layout: &RawLayout,
+    index: usize,
+) -> Result<(), Error> {
+    let raw_track = match layout.tracks.get(index) {
+        Some(raw_track) => raw_track,
+        None => return write::write_track_chunk(out, events),
+    };
+
+    let mut data = Vec::new();
+    let mut running_status = None;
+    let mut next = 0;
+    for event in events {
+        let original = raw_track.events[next..]
+            .iter()
+            .take(LOOKAHEAD)
+            .position(|raw_event| raw_event.event == *event);
+
+        let status = match event.kind {
+            EventKind::Midi(ref midi_event) => Some(write::midi_status(midi_event)?),
+            _ => None,
+        };
+
+        match original {
+            Some(offset) => {
+                let raw_event = &raw_track.events[next + offset];
+                next += offset + 1;
+                // omitted status byte can be reused only if the running status is the same
+                if !raw_event.uses_running_status() || running_status == status {
+                    data.extend_from_slice(raw_event.raw);
+                } else {
+                    write::write_event(&mut data, event)?;
+                }
+            }
+            None => write::write_event(&mut data, event)?,
+        }
+
+        if status.is_some() {
+            running_status = status;
+        }
+    }
+    data.extend_from_slice(raw_track.trailing);
+
+    write::write_raw_chunk(out, b"MTrk", &data)
+}
+
+pub(crate) fn write_container(
+    out: &mut Vec<u8>,
+    rmid: &read::Rmid,
+    smf: &[u8],
+    layout: &RawLayout,
+) -> Result<(), Error> {
+    let container = match layout.container {
+        Some(ref container) if container.rmid == *rmid => container,
+        _ => return write::write_rmid(out, rmid, smf),
+    };
+
+    if smf.len() > u32::MAX as usize {
+        return Err(Error {
+            context: "Smf::write_lossless: smf length must fit into 32 bits",
+            kind: ErrorKind::Invalid,
+        });
+    }
+
+    let data = layout.data;
+    let original_len = container.end - container.start;
+    let mut suffix = &data[container.end..];
+    if original_len % 2 == 1 && !suffix.is_empty() {
+        // pad byte of the 'data' chunk
+        suffix = &suffix[1..];
+    }
+
+    let start = out.len();
+    out.extend_from_slice(&data[..container.start]);
+    let data_len = out.len() - 4;
+    out[data_len..].copy_from_slice(&(smf.len() as u32).to_le_bytes());
+    out.extend_from_slice(smf);
+    if smf.len() % 2 == 1 {
+        out.push(0);
+    }
+    out.extend_from_slice(suffix);
+
+    // keep the original container length error, if there was any
+    let original_riff_len = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    let riff_len =
+        (i64::from(original_riff_len) + (out.len() - start) as i64 - data.len() as i64) as u32;
+    out[start + 4..start + 8].copy_from_slice(&riff_len.to_le_bytes());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::note_on;
+    use crate::Smf;
+
+    #[rustfmt::skip]
+    const SMF: &[u8] = &[
+        // header
+        b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0, 96,
+        // track
+        b'M', b'T', b'r', b'k', 0, 0, 0, 19,
+        // note on with redundant vlq byte
+        0x80, 0x00, 0x90, 0x3c, 0x40,
+        // running status
+        0x10, 0x3e, 0x40,
+        // note off as note on with zero velocity
+        0x10, 0x3c, 0x00,
+        0x00, 0x3e, 0x00,
+        // end of track
+        0x00, 0xff, 0x2f, 0x00,
+        // trailing byte within track
+        0x42,
+        // unknown chunk
+        b'X', b'F', b'I', b'H', 0, 0, 0, 2, 1, 2,
+        // trailing data
+        0xde, 0xad,
+    ];
+
+    #[test]
+    fn test_unchanged() {
+        let (smf, layout) = Smf::read_lossless(SMF).unwrap();
+        assert_eq!(smf.tracks[0].events.len(), 5);
+        assert_eq!(smf.tracks[0].events[2], note_on(0x10, 0, 0x3c, 0));
+        assert_eq!(smf.unknown_chunks.len(), 1);
+        assert_eq!(layout.trailing(), &[0xde, 0xad]);
+        assert_eq!(smf.write_lossless(&layout).unwrap(), SMF);
+    }
+
+    #[test]
+    fn test_edited() {
+        let (mut smf, layout) = Smf::read_lossless(SMF).unwrap();
+        smf.tracks[0].events[0] = note_on(0, 0, 0x3c, 0x50);
+        let written = smf.write_lossless(&layout).unwrap();
+
+        // only the edited event is encoded again
+        let mut expected = SMF[..22].to_vec();
+        expected[21] = 18;
+        expected.extend_from_slice(&[0x00, 0x90, 0x3c, 0x50]);
+        expected.extend_from_slice(&SMF[27..]);
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_removed_status() {
+        let (mut smf, layout) = Smf::read_lossless(SMF).unwrap();
+        smf.tracks[0].events.remove(0);
+        let written = smf.write_lossless(&layout).unwrap();
+
+        // the first remaining event can no longer rely on running status
+        let mut expected = SMF[..22].to_vec();
+        expected[21] = 15;
+        expected.extend_from_slice(&[0x10, 0x90, 0x3e, 0x40]);
+        expected.extend_from_slice(&SMF[30..]);
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_wrong_track_count() {
+        // format 1 header with 2 tracks, but only 1 track chunk
+        let mut data = SMF.to_vec();
+        data[9] = 1;
+        data[11] = 2;
+        let (mut smf, layout) = Smf::read_lossless(&data).unwrap();
+        assert_eq!(smf.tracks.len(), 1);
+        assert_eq!(smf.write_lossless(&layout).unwrap(), data);
+
+        // the header is corrected if tracks are added
+        smf.tracks.push(smf.tracks[0].clone());
+        assert_eq!(smf.write_lossless(&layout).unwrap()[11], 2);
+        smf.tracks.push(smf.tracks[0].clone());
+        assert_eq!(smf.write_lossless(&layout).unwrap()[11], 3);
+    }
+}
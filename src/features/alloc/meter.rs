@@ -0,0 +1,132 @@
+use crate::{EventKind, MetaEvent, Smf};
+use alloc::vec::Vec;
+
+/// Time signature in effect from `tick`.
+#[derive(Debug, Clone, Copy)]
+struct Meter {
+    tick: u64,
+    /// Number of bars preceding the `tick`.
+    bar: u64,
+    beats: u64,
+    beat_ticks: u64,
+}
+
+/// Conversion of ticks to bars, beats and ticks.
+#[derive(Debug, Clone)]
+pub(crate) struct Meters {
+    meters: Vec<Meter>,
+}
+
+impl Meters {
+    // `u64::div_ceil` requires Rust 1.73
+    #[allow(clippy::manual_div_ceil)]
+    pub(crate) fn new(smf: &Smf, ppqn: u16) -> Self {
+        let mut signatures = smf
+            .tracks
+            .iter()
+            .flat_map(|track| {
+                track
+                    .absolute_iter()
+                    .filter_map(|(tick, event)| match event.kind {
+                        EventKind::Meta(MetaEvent::TimeSignature { nn, dd, .. }) => {
+                            Some((tick, nn, dd))
+                        }
+                        _ => None,
+                    })
+            })
+            .collect::<Vec<_>>();
+        signatures.sort_by_key(|&(tick, _, _)| tick);
+
+        let quarter = u64::from(ppqn.max(1));
+        let mut meters = vec![Meter {
+            tick: 0,
+            bar: 0,
+            beats: 4,
+            beat_ticks: quarter,
+        }];
+        for (tick, nn, dd) in signatures {
+            let last = *meters.last().expect("meters are never empty; qed");
+            let bar_ticks = last.beats * last.beat_ticks;
+            // signature changed in the middle of a bar starts a new bar
+            let bar = last.bar + (tick - last.tick + bar_ticks - 1) / bar_ticks;
+            let meter = Meter {
+                tick,
+                bar,
+                beats: u64::from(nn.max(1)),
+                beat_ticks: (quarter * 4).checked_shr(u32::from(dd)).unwrap_or(0).max(1),
+            };
+            if last.tick == tick {
+                *meters.last_mut().expect("meters are never empty; qed") = meter;
+            } else {
+                meters.push(meter);
+            }
+        }
+
+        Meters { meters }
+    }
+
+    /// Returns bar, beat and tick, all zero based.
+    pub(crate) fn bbt(&self, tick: u64) -> (u64, u64, u64) {
+        let meter = self
+            .meters
+            .iter()
+            .rev()
+            .find(|meter| meter.tick <= tick)
+            .expect("the first meter is at tick 0; qed");
+        let offset = tick - meter.tick;
+        let bar_ticks = meter.beats * meter.beat_ticks;
+        let within = offset % bar_ticks;
+        (
+            meter.bar + offset / bar_ticks,
+            within / meter.beat_ticks,
+            within % meter.beat_ticks,
+        )
+    }
+
+    /// Returns tick of zero based bar, beat and tick.
+    pub(crate) fn tick(&self, bar: u64, beat: u64, tick: u64) -> u64 {
+        let meter = self
+            .meters
+            .iter()
+            .rev()
+            .find(|meter| meter.bar <= bar)
+            .expect("the first meter is at bar 0; qed");
+        meter.tick
+            + (bar - meter.bar) * meter.beats * meter.beat_ticks
+            + beat * meter.beat_ticks
+            + tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Meters;
+    use crate::test_util::{meta, smf};
+    use crate::{Format, MetaEvent};
+
+    #[test]
+    fn test_meters() {
+        let time_signature = |time, nn, dd| {
+            meta(
+                time,
+                MetaEvent::TimeSignature {
+                    nn,
+                    dd,
+                    cc: 24,
+                    bb: 8,
+                },
+            )
+        };
+        let smf = smf(
+            Format::Single,
+            96,
+            vec![vec![time_signature(0, 4, 2), time_signature(384, 6, 3)]],
+        );
+
+        let meters = Meters::new(&smf, 96);
+        assert_eq!(meters.bbt(100), (0, 1, 4));
+        assert_eq!(meters.bbt(384 + 48 * 7), (2, 1, 0));
+        assert_eq!(meters.tick(0, 1, 4), 100);
+        assert_eq!(meters.tick(2, 1, 0), 384 + 48 * 7);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{EventKind, MidiEventKind, Track};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// Note paired from note on and note off events of a [`Track`].
+///
+/// [`Track`]: struct.Track.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+    /// Absolute time of the note on event in ticks.
+    pub start: u64,
+    /// Absolute time of the note off event in ticks, or the end of the track if the note is
+    /// never released.
+    pub end: u64,
+    /// Index of the note on event in the track.
+    pub on: usize,
+    /// Index of the note off event in the track, `None` if the note is never released.
+    pub off: Option<usize>,
+}
+
+impl<'a> Track<'a> {
+    /// Pairs note on events with the following note off events of the same key and channel.
+    ///
+    /// Overlapping notes of the same key are released in the order they were started. Note on
+    /// with zero velocity is a note off. Notes are ordered by start.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn notes(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let smf = midi::Smf::read(bytes)?;
+    /// for note in smf.tracks[0].notes() {
+    ///     println!("{} {}..{}", note.key, note.start, note.end);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn notes(&self) -> Vec<Note> {
+        let mut notes = Vec::new();
+        let mut pending: Vec<VecDeque<usize>> = (0..16 * 128).map(|_| VecDeque::new()).collect();
+        let mut end = 0;
+        for (index, (tick, event)) in self.absolute_iter().enumerate() {
+            end = tick;
+            let midi_event = match event.kind {
+                EventKind::Midi(midi_event) => midi_event,
+                _ => continue,
+            };
+            let slot =
+                |key: u8| ((midi_event.channel & 0x0f) as usize) << 7 | (key & 0x7f) as usize;
+            match midi_event.kind {
+                MidiEventKind::NoteOn { key, velocity } if velocity > 0 => {
+                    pending[slot(key)].push_back(notes.len());
+                    notes.push(Note {
+                        channel: midi_event.channel,
+                        key,
+                        velocity,
+                        start: tick,
+                        end: tick,
+                        on: index,
+                        off: None,
+                    });
+                }
+                MidiEventKind::NoteOn { key, .. } | MidiEventKind::NoteOff { key, .. } => {
+                    if let Some(note) = pending[slot(key)].pop_front() {
+                        notes[note].end = tick;
+                        notes[note].off = Some(index);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for note in notes.iter_mut().filter(|note| note.off.is_none()) {
+            note.end = end;
+        }
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{end_of_track, note_on};
+    use crate::{Note, Track};
+
+    #[test]
+    fn test_notes() {
+        let track = Track {
+            events: vec![
+                note_on(0, 0, 60, 100),
+                note_on(10, 0, 60, 90),
+                note_on(10, 0, 60, 0),
+                note_on(10, 0, 60, 0),
+                note_on(0, 0, 62, 80),
+                end_of_track(5),
+            ],
+        };
+        let note = |key, velocity, start, end, on, off| Note {
+            channel: 0,
+            key,
+            velocity,
+            start,
+            end,
+            on,
+            off,
+        };
+        assert_eq!(
+            track.notes(),
+            vec![
+                note(60, 100, 0, 20, 0, Some(2)),
+                note(60, 90, 10, 30, 1, Some(3)),
+                note(62, 80, 30, 35, 4, None),
+            ]
+        );
+    }
+}
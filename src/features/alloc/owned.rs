@@ -0,0 +1,394 @@
+use crate::read::{RiffInfo, Rmid};
+use crate::{
+    Event, EventKind, Format, MetaEvent, MidiEvent, Smf, SysexEvent, Text, Timing, Track,
+    UnknownChunk,
+};
+use alloc::vec::Vec;
+
+/// Owned version of [`MetaEvent`].
+///
+/// [`MetaEvent`]: enum.MetaEvent.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OwnedMetaEvent {
+    SequenceNumber(u16),
+    Text(#[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>),
+    CopyrightNotice(
+        #[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>,
+    ),
+    Name(#[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>),
+    InstrumentName(
+        #[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>,
+    ),
+    Lyric(#[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>),
+    Marker(#[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>),
+    CuePoint(#[cfg_attr(feature = "serde", serde(with = "crate::features::serde::text"))] Vec<u8>),
+    ChannelPrefix(u8),
+    EndOfTrack,
+    SetTempo(u32),
+    #[cfg_attr(feature = "serde", serde(rename = "smtpe_offset"))]
+    SMTPEOffset {
+        hh: u8,
+        mm: u8,
+        ss: u8,
+        fr: u8,
+        ff: u8,
+    },
+    TimeSignature {
+        nn: u8,
+        dd: u8,
+        cc: u8,
+        bb: u8,
+    },
+    KeySignature {
+        sf: u8,
+        mi: u8,
+    },
+    SequencerSpecific(Vec<u8>),
+    Unknown {
+        meta_type: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl OwnedMetaEvent {
+    /// Borrows [`OwnedMetaEvent`] as [`MetaEvent`].
+    ///
+    /// [`OwnedMetaEvent`]: enum.OwnedMetaEvent.html
+    /// [`MetaEvent`]: enum.MetaEvent.html
+    pub fn as_meta_event(&self) -> MetaEvent<'_> {
+        match *self {
+            OwnedMetaEvent::SequenceNumber(number) => MetaEvent::SequenceNumber(number),
+            OwnedMetaEvent::Text(ref text) => MetaEvent::Text(Text::new(text)),
+            OwnedMetaEvent::CopyrightNotice(ref text) => {
+                MetaEvent::CopyrightNotice(Text::new(text))
+            }
+            OwnedMetaEvent::Name(ref text) => MetaEvent::Name(Text::new(text)),
+            OwnedMetaEvent::InstrumentName(ref text) => MetaEvent::InstrumentName(Text::new(text)),
+            OwnedMetaEvent::Lyric(ref text) => MetaEvent::Lyric(Text::new(text)),
+            OwnedMetaEvent::Marker(ref text) => MetaEvent::Marker(Text::new(text)),
+            OwnedMetaEvent::CuePoint(ref text) => MetaEvent::CuePoint(Text::new(text)),
+            OwnedMetaEvent::ChannelPrefix(channel) => MetaEvent::ChannelPrefix(channel),
+            OwnedMetaEvent::EndOfTrack => MetaEvent::EndOfTrack,
+            OwnedMetaEvent::SetTempo(tempo) => MetaEvent::SetTempo(tempo),
+            OwnedMetaEvent::SMTPEOffset { hh, mm, ss, fr, ff } => {
+                MetaEvent::SMTPEOffset { hh, mm, ss, fr, ff }
+            }
+            OwnedMetaEvent::TimeSignature { nn, dd, cc, bb } => {
+                MetaEvent::TimeSignature { nn, dd, cc, bb }
+            }
+            OwnedMetaEvent::KeySignature { sf, mi } => MetaEvent::KeySignature { sf, mi },
+            OwnedMetaEvent::SequencerSpecific(ref data) => MetaEvent::SequencerSpecific(data),
+            OwnedMetaEvent::Unknown {
+                meta_type,
+                ref data,
+            } => MetaEvent::Unknown { meta_type, data },
+        }
+    }
+}
+
+impl<'a> From<MetaEvent<'a>> for OwnedMetaEvent {
+    fn from(meta_event: MetaEvent<'a>) -> Self {
+        match meta_event {
+            MetaEvent::SequenceNumber(number) => OwnedMetaEvent::SequenceNumber(number),
+            MetaEvent::Text(text) => OwnedMetaEvent::Text(text.raw().to_vec()),
+            MetaEvent::CopyrightNotice(text) => {
+                OwnedMetaEvent::CopyrightNotice(text.raw().to_vec())
+            }
+            MetaEvent::Name(text) => OwnedMetaEvent::Name(text.raw().to_vec()),
+            MetaEvent::InstrumentName(text) => OwnedMetaEvent::InstrumentName(text.raw().to_vec()),
+            MetaEvent::Lyric(text) => OwnedMetaEvent::Lyric(text.raw().to_vec()),
+            MetaEvent::Marker(text) => OwnedMetaEvent::Marker(text.raw().to_vec()),
+            MetaEvent::CuePoint(text) => OwnedMetaEvent::CuePoint(text.raw().to_vec()),
+            MetaEvent::ChannelPrefix(channel) => OwnedMetaEvent::ChannelPrefix(channel),
+            MetaEvent::EndOfTrack => OwnedMetaEvent::EndOfTrack,
+            MetaEvent::SetTempo(tempo) => OwnedMetaEvent::SetTempo(tempo),
+            MetaEvent::SMTPEOffset { hh, mm, ss, fr, ff } => {
+                OwnedMetaEvent::SMTPEOffset { hh, mm, ss, fr, ff }
+            }
+            MetaEvent::TimeSignature { nn, dd, cc, bb } => {
+                OwnedMetaEvent::TimeSignature { nn, dd, cc, bb }
+            }
+            MetaEvent::KeySignature { sf, mi } => OwnedMetaEvent::KeySignature { sf, mi },
+            MetaEvent::SequencerSpecific(data) => OwnedMetaEvent::SequencerSpecific(data.to_vec()),
+            MetaEvent::Unknown { meta_type, data } => OwnedMetaEvent::Unknown {
+                meta_type,
+                data: data.to_vec(),
+            },
+        }
+    }
+}
+
+/// Owned version of [`SysexEvent`].
+///
+/// [`SysexEvent`]: enum.SysexEvent.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedSysexEvent {
+    F0(Vec<u8>),
+    F7(Vec<u8>),
+}
+
+impl OwnedSysexEvent {
+    /// Borrows [`OwnedSysexEvent`] as [`SysexEvent`].
+    ///
+    /// [`OwnedSysexEvent`]: enum.OwnedSysexEvent.html
+    /// [`SysexEvent`]: enum.SysexEvent.html
+    pub fn as_sysex_event(&self) -> SysexEvent<'_> {
+        match *self {
+            OwnedSysexEvent::F0(ref data) => SysexEvent::F0(data),
+            OwnedSysexEvent::F7(ref data) => SysexEvent::F7(data),
+        }
+    }
+}
+
+impl<'a> From<SysexEvent<'a>> for OwnedSysexEvent {
+    fn from(sysex_event: SysexEvent<'a>) -> Self {
+        match sysex_event {
+            SysexEvent::F0(data) => OwnedSysexEvent::F0(data.to_vec()),
+            SysexEvent::F7(data) => OwnedSysexEvent::F7(data.to_vec()),
+        }
+    }
+}
+
+/// Owned version of [`EventKind`].
+///
+/// [`EventKind`]: enum.EventKind.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OwnedEventKind {
+    Midi(MidiEvent),
+    Meta(OwnedMetaEvent),
+    Sysex(OwnedSysexEvent),
+}
+
+impl OwnedEventKind {
+    /// Borrows [`OwnedEventKind`] as [`EventKind`].
+    ///
+    /// [`OwnedEventKind`]: enum.OwnedEventKind.html
+    /// [`EventKind`]: enum.EventKind.html
+    pub fn as_event_kind(&self) -> EventKind<'_> {
+        match *self {
+            OwnedEventKind::Midi(midi_event) => EventKind::Midi(midi_event),
+            OwnedEventKind::Meta(ref meta_event) => EventKind::Meta(meta_event.as_meta_event()),
+            OwnedEventKind::Sysex(ref sysex_event) => {
+                EventKind::Sysex(sysex_event.as_sysex_event())
+            }
+        }
+    }
+}
+
+impl<'a> From<EventKind<'a>> for OwnedEventKind {
+    fn from(kind: EventKind<'a>) -> Self {
+        match kind {
+            EventKind::Midi(midi_event) => OwnedEventKind::Midi(midi_event),
+            EventKind::Meta(meta_event) => OwnedEventKind::Meta(meta_event.into()),
+            EventKind::Sysex(sysex_event) => OwnedEventKind::Sysex(sysex_event.into()),
+        }
+    }
+}
+
+/// Owned version of [`Event`].
+///
+/// Owned events do not borrow the `SMF` data, so they can outlive it.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Event, OwnedEvent};
+/// # fn foo(event: Event) {
+/// let owned = OwnedEvent::from(event);
+/// assert_eq!(owned.as_event(), event);
+/// # }
+/// ```
+///
+/// [`Event`]: struct.Event.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedEvent {
+    pub time: u32,
+    pub kind: OwnedEventKind,
+}
+
+impl OwnedEvent {
+    /// Borrows [`OwnedEvent`] as [`Event`].
+    ///
+    /// [`OwnedEvent`]: struct.OwnedEvent.html
+    /// [`Event`]: struct.Event.html
+    pub fn as_event(&self) -> Event<'_> {
+        Event {
+            time: self.time,
+            kind: self.kind.as_event_kind(),
+        }
+    }
+}
+
+impl<'a> From<Event<'a>> for OwnedEvent {
+    fn from(event: Event<'a>) -> Self {
+        OwnedEvent {
+            time: event.time,
+            kind: event.kind.into(),
+        }
+    }
+}
+
+/// Owned version of [`Track`].
+///
+/// [`Track`]: struct.Track.html
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedTrack {
+    pub events: Vec<OwnedEvent>,
+}
+
+impl OwnedTrack {
+    /// Borrows [`OwnedTrack`] as [`Track`].
+    ///
+    /// [`OwnedTrack`]: struct.OwnedTrack.html
+    /// [`Track`]: struct.Track.html
+    pub fn as_track(&self) -> Track<'_> {
+        Track {
+            events: self.events.iter().map(OwnedEvent::as_event).collect(),
+        }
+    }
+}
+
+impl<'a> From<Track<'a>> for OwnedTrack {
+    fn from(track: Track<'a>) -> Self {
+        OwnedTrack {
+            events: track.events.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Owned version of [`UnknownChunk`].
+///
+/// [`UnknownChunk`]: struct.UnknownChunk.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedUnknownChunk {
+    pub id: [u8; 4],
+    pub data: Vec<u8>,
+    pub position: usize,
+}
+
+impl OwnedUnknownChunk {
+    /// Borrows [`OwnedUnknownChunk`] as [`UnknownChunk`].
+    ///
+    /// [`OwnedUnknownChunk`]: struct.OwnedUnknownChunk.html
+    /// [`UnknownChunk`]: struct.UnknownChunk.html
+    pub fn as_unknown_chunk(&self) -> UnknownChunk<'_> {
+        UnknownChunk {
+            id: self.id,
+            data: &self.data,
+            position: self.position,
+        }
+    }
+}
+
+impl<'a> From<UnknownChunk<'a>> for OwnedUnknownChunk {
+    fn from(chunk: UnknownChunk<'a>) -> Self {
+        OwnedUnknownChunk {
+            id: chunk.id,
+            data: chunk.data.to_vec(),
+            position: chunk.position,
+        }
+    }
+}
+
+/// Owned version of [`Rmid`].
+///
+/// [`Rmid`]: read/struct.Rmid.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedRmid {
+    /// `INFO` list data.
+    pub info: Option<Vec<u8>>,
+    /// Embedded Downloadable Sounds collection.
+    pub dls: Option<Vec<u8>>,
+}
+
+impl OwnedRmid {
+    /// Borrows [`OwnedRmid`] as [`Rmid`].
+    ///
+    /// [`OwnedRmid`]: struct.OwnedRmid.html
+    /// [`Rmid`]: read/struct.Rmid.html
+    pub fn as_rmid(&self) -> Rmid<'_> {
+        Rmid {
+            info: self.info.as_ref().map(|info| RiffInfo::new(info)),
+            dls: self.dls.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<Rmid<'a>> for OwnedRmid {
+    fn from(rmid: Rmid<'a>) -> Self {
+        OwnedRmid {
+            info: rmid.info.map(|info| info.raw().to_vec()),
+            dls: rmid.dls.map(<[u8]>::to_vec),
+        }
+    }
+}
+
+/// Owned version of [`Smf`].
+///
+/// Unlike [`Smf`], it can be deserialized from formats which do not allow borrowing,
+/// e.g. JSON.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Error, OwnedSmf, Smf};
+/// # fn foo(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+/// let owned = OwnedSmf::from(Smf::read(bytes)?);
+/// let written = owned.as_smf().write()?;
+/// # Ok(written)
+/// # }
+/// ```
+///
+/// [`Smf`]: struct.Smf.html
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedSmf {
+    pub format: Format,
+    pub tracks: Vec<OwnedTrack>,
+    pub timing: Timing,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unknown_chunks: Vec<OwnedUnknownChunk>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rmid: Option<OwnedRmid>,
+}
+
+impl OwnedSmf {
+    /// Borrows [`OwnedSmf`] as [`Smf`].
+    ///
+    /// [`OwnedSmf`]: struct.OwnedSmf.html
+    /// [`Smf`]: struct.Smf.html
+    pub fn as_smf(&self) -> Smf<'_> {
+        Smf {
+            format: self.format,
+            tracks: self.tracks.iter().map(OwnedTrack::as_track).collect(),
+            timing: self.timing,
+            unknown_chunks: self
+                .unknown_chunks
+                .iter()
+                .map(OwnedUnknownChunk::as_unknown_chunk)
+                .collect(),
+            rmid: self.rmid.as_ref().map(OwnedRmid::as_rmid),
+        }
+    }
+}
+
+impl<'a> From<Smf<'a>> for OwnedSmf {
+    fn from(smf: Smf<'a>) -> Self {
+        OwnedSmf {
+            format: smf.format,
+            tracks: smf.tracks.into_iter().map(Into::into).collect(),
+            timing: smf.timing,
+            unknown_chunks: smf.unknown_chunks.into_iter().map(Into::into).collect(),
+            rmid: smf.rmid.map(Into::into),
+        }
+    }
+}
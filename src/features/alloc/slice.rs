@@ -0,0 +1,261 @@
+use crate::{
+    ChannelState, Error, ErrorKind, Event, EventKind, MetaEvent, Meters, MidiEvent, MidiEventKind,
+    Smf, TempoMap, Timing, Track,
+};
+use alloc::vec::Vec;
+
+/// Position in a song used by [`Smf::slice`].
+///
+/// [`Smf::slice`]: struct.Smf.html#method.slice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    /// Absolute time in ticks.
+    Ticks(u64),
+    /// Absolute time in seconds.
+    Seconds(f64),
+    /// Bar, beat and tick. Bars and beats are numbered from 1, ticks from 0. Requires
+    /// [`Timing::Metrical`].
+    ///
+    /// [`Timing::Metrical`]: enum.Timing.html#variant.Metrical
+    Bbt { bar: u64, beat: u64, tick: u64 },
+}
+
+impl Position {
+    fn tick(self, smf: &Smf) -> Result<u64, Error> {
+        match self {
+            Position::Ticks(tick) => Ok(tick),
+            Position::Seconds(seconds) => Ok(TempoMap::new(smf).tick(seconds)),
+            Position::Bbt { bar, beat, tick } => match smf.timing {
+                Timing::Metrical(ppqn) => Ok(Meters::new(smf, ppqn).tick(
+                    bar.saturating_sub(1),
+                    beat.saturating_sub(1),
+                    tick,
+                )),
+                Timing::Timecode { .. } => Err(Error {
+                    context: "Smf::slice: bars and beats require metrical timing",
+                    kind: ErrorKind::Invalid,
+                }),
+            },
+        }
+    }
+}
+
+fn midi(time: u32, channel: u8, kind: MidiEventKind) -> Event<'static> {
+    Event {
+        time,
+        kind: EventKind::Midi(MidiEvent { channel, kind }),
+    }
+}
+
+/// Slices `track` to `start..end` ticks.
+fn slice_track<'a>(track: &Track<'a>, start: u64, end: u64) -> Track<'a> {
+    let mut name = None;
+    let mut tempo = None;
+    let mut time_signature = None;
+    let mut key_signature = None;
+    let mut state = ChannelState::new();
+    for (_, event) in track.absolute_iter().take_while(|&(tick, _)| tick < start) {
+        match event.kind {
+            EventKind::Meta(meta @ MetaEvent::Name(_)) => name = Some(meta),
+            EventKind::Meta(meta @ MetaEvent::SetTempo(_)) => tempo = Some(meta),
+            EventKind::Meta(meta @ MetaEvent::TimeSignature { .. }) => time_signature = Some(meta),
+            EventKind::Meta(meta @ MetaEvent::KeySignature { .. }) => key_signature = Some(meta),
+            EventKind::Midi(midi_event) => state.update(midi_event),
+            _ => {}
+        }
+    }
+
+    let mut events: Vec<(u64, Event<'a>)> = [name, tempo, time_signature, key_signature]
+        .iter()
+        .flatten()
+        .map(|&meta| {
+            (
+                start,
+                Event {
+                    time: 0,
+                    kind: EventKind::Meta(meta),
+                },
+            )
+        })
+        .collect();
+    events.extend(
+        state
+            .events()
+            .into_iter()
+            .map(|midi_event| (start, midi(0, midi_event.channel, midi_event.kind))),
+    );
+
+    // notes crossing the start are restarted, notes crossing the end are released
+    let mut skipped = vec![false; track.events.len()];
+    let mut releases = Vec::new();
+    for note in track.notes() {
+        let included = note.start < end && (note.end > start || note.start >= start);
+        if !included {
+            skipped[note.on] = true;
+            if let Some(off) = note.off {
+                skipped[off] = true;
+            }
+            continue;
+        }
+
+        let on = MidiEventKind::NoteOn {
+            key: note.key,
+            velocity: note.velocity,
+        };
+        if note.start < start {
+            events.push((start, midi(0, note.channel, on)));
+        }
+        if note.off.is_none() || note.end >= end {
+            let off = MidiEventKind::NoteOff {
+                key: note.key,
+                velocity: 64,
+            };
+            releases.push((end, midi(0, note.channel, off)));
+        }
+    }
+
+    events.extend(
+        track
+            .absolute_iter()
+            .enumerate()
+            .filter(|&(index, (tick, event))| {
+                tick >= start
+                    && tick < end
+                    && !skipped[index]
+                    && !matches!(event.kind, EventKind::Meta(MetaEvent::EndOfTrack))
+            })
+            .map(|(_, (tick, event))| (tick, *event)),
+    );
+    events.extend(releases);
+    events.push((
+        end,
+        Event {
+            time: 0,
+            kind: EventKind::Meta(MetaEvent::EndOfTrack),
+        },
+    ));
+
+    Track::from_absolute(
+        events
+            .into_iter()
+            .map(|(tick, event)| (tick - start, event)),
+    )
+}
+
+impl<'a> Smf<'a> {
+    /// Extracts the region between `start` and `end`.
+    ///
+    /// State in effect at `start`, i.e. tempo, time and key signature, track name and
+    /// [`ChannelState`], is restored by events at the beginning of every track. Notes sounding
+    /// at `start` are restarted at the beginning and notes sounding at `end` are released at
+    /// the end. The end is limited to the end of the song.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// use midi::Position;
+    ///
+    /// # fn preview(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let smf = midi::Smf::read(bytes)?;
+    /// let preview = smf.slice(Position::Seconds(30.0), Position::Seconds(45.0))?;
+    /// let chorus = smf.slice(
+    ///     Position::Bbt { bar: 17, beat: 1, tick: 0 },
+    ///     Position::Bbt { bar: 25, beat: 1, tick: 0 },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ChannelState`]: struct.ChannelState.html
+    pub fn slice(&self, start: Position, end: Position) -> Result<Smf<'a>, Error> {
+        let song_end = self
+            .tracks
+            .iter()
+            .filter_map(|track| track.absolute_iter().last().map(|(tick, _)| tick))
+            .max()
+            .unwrap_or(0);
+        let start = start.tick(self)?;
+        let end = end.tick(self)?.min(song_end);
+        if start > end {
+            return Err(Error {
+                context: "Smf::slice: start is after the end",
+                kind: ErrorKind::Invalid,
+            });
+        }
+
+        let smf = Smf {
+            format: self.format,
+            tracks: self
+                .tracks
+                .iter()
+                .map(|track| slice_track(track, start, end))
+                .collect(),
+            timing: self.timing,
+            // unknown chunks are related to the original tracks
+            unknown_chunks: Vec::new(),
+            rmid: self.rmid,
+        };
+
+        Ok(smf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Position;
+    use crate::test_util::{end_of_track, midi, note_off, note_on, smf, tempo};
+    use crate::{Event, Format, MidiEventKind};
+
+    fn controller(time: u32, number: u8, value: u8) -> Event<'static> {
+        midi(time, 0, MidiEventKind::ControllerChange { number, value })
+    }
+
+    #[test]
+    fn test_slice() {
+        let smf = smf(
+            Format::Single,
+            96,
+            vec![vec![
+                tempo(0, 400_000),
+                controller(0, 7, 100),
+                midi(0, 0, MidiEventKind::ProgramChange(5)),
+                controller(0, 0, 1),
+                note_on(0, 0, 60, 90),
+                controller(10, 7, 80),
+                note_on(10, 0, 62, 80),
+                note_on(0, 0, 60, 0),
+                note_on(40, 0, 64, 70),
+                note_on(10, 0, 62, 0),
+                note_on(0, 0, 64, 0),
+                end_of_track(100),
+            ]],
+        );
+
+        let sliced = smf.slice(Position::Ticks(15), Position::Ticks(65)).unwrap();
+        assert_eq!(
+            sliced.tracks[0].events,
+            vec![
+                tempo(0, 400_000),
+                controller(0, 0, 1),
+                midi(0, 0, MidiEventKind::ProgramChange(5)),
+                controller(0, 7, 80),
+                note_on(0, 0, 60, 90),
+                note_on(5, 0, 62, 80),
+                note_on(0, 0, 60, 0),
+                note_on(40, 0, 64, 70),
+                note_off(5, 0, 62),
+                note_off(0, 0, 64),
+                end_of_track(0),
+            ]
+        );
+
+        let bbt = Position::Bbt {
+            bar: 1,
+            beat: 1,
+            tick: 15,
+        };
+        assert_eq!(smf.slice(bbt, Position::Ticks(65)).unwrap(), sliced);
+        assert!(smf.slice(Position::Ticks(65), Position::Ticks(15)).is_err());
+    }
+}
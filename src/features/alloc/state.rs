@@ -0,0 +1,481 @@
+use crate::{MidiEvent, MidiEventKind};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+const BANK_SELECT_MSB: u8 = 0;
+const BANK_SELECT_LSB: u8 = 32;
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const DATA_INCREMENT: u8 = 96;
+const DATA_DECREMENT: u8 = 97;
+const NRPN_LSB: u8 = 98;
+const NRPN_MSB: u8 = 99;
+const RPN_LSB: u8 = 100;
+const RPN_MSB: u8 = 101;
+
+/// Controllers reset by [`MidiEventKind::ResetAllControllers`], modulation, expression and
+/// pedals.
+///
+/// [`MidiEventKind::ResetAllControllers`]: enum.MidiEventKind.html#variant.ResetAllControllers
+const RESET_CONTROLLERS: [u8; 6] = [1, 11, 64, 65, 66, 67];
+
+/// Registered or non-registered parameter number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Parameter {
+    Registered(u16),
+    NonRegistered(u16),
+}
+
+impl Parameter {
+    fn controllers(self) -> (u8, u8, u16) {
+        match self {
+            Parameter::Registered(number) => (RPN_MSB, RPN_LSB, number),
+            Parameter::NonRegistered(number) => (NRPN_MSB, NRPN_LSB, number),
+        }
+    }
+}
+
+/// Parameter selected by the last parameter number controllers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Selection {
+    registered: bool,
+    msb: Option<u8>,
+    lsb: Option<u8>,
+}
+
+impl Selection {
+    fn parameter(&self) -> Option<Parameter> {
+        let number = u16::from(self.msb?) << 7 | u16::from(self.lsb?);
+        match self.registered {
+            true => Some(Parameter::Registered(number)),
+            false => Some(Parameter::NonRegistered(number)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Channel {
+    controllers: [Option<u8>; 128],
+    program: Option<u8>,
+    pitch_bend: Option<u16>,
+    pressure: Option<u8>,
+    selection: Option<Selection>,
+    /// Data entry msb and lsb of parameters.
+    parameters: BTreeMap<Parameter, (u8, Option<u8>)>,
+    /// Velocity of sounding notes.
+    notes: [Option<u8>; 128],
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Channel {
+            controllers: [None; 128],
+            program: None,
+            pitch_bend: None,
+            pressure: None,
+            selection: None,
+            parameters: BTreeMap::new(),
+            notes: [None; 128],
+        }
+    }
+}
+
+impl Channel {
+    fn controller(&mut self, number: u8, value: u8) {
+        match number {
+            RPN_MSB | RPN_LSB | NRPN_MSB | NRPN_LSB => {
+                let registered = matches!(number, RPN_MSB | RPN_LSB);
+                let mut selection = match self.selection {
+                    Some(selection) if selection.registered == registered => selection,
+                    _ => Selection {
+                        registered,
+                        msb: None,
+                        lsb: None,
+                    },
+                };
+                if matches!(number, RPN_MSB | NRPN_MSB) {
+                    selection.msb = Some(value);
+                } else {
+                    selection.lsb = Some(value);
+                }
+                // null function number is the same as no selection
+                self.selection = match (selection.msb, selection.lsb) {
+                    (Some(0x7f), Some(0x7f)) => None,
+                    _ => Some(selection),
+                };
+            }
+            DATA_ENTRY_MSB | DATA_ENTRY_LSB | DATA_INCREMENT | DATA_DECREMENT => {
+                let parameter = match self.selection.and_then(|selection| selection.parameter()) {
+                    Some(parameter) => parameter,
+                    None => return,
+                };
+                let entry = self.parameters.entry(parameter).or_insert((0, None));
+                match number {
+                    DATA_ENTRY_MSB => *entry = (value, None),
+                    DATA_ENTRY_LSB => entry.1 = Some(value),
+                    DATA_INCREMENT => entry.0 = entry.0.saturating_add(1).min(0x7f),
+                    _ => entry.0 = entry.0.saturating_sub(1),
+                }
+            }
+            _ => self.controllers[number as usize] = Some(value),
+        }
+    }
+
+    fn update(&mut self, kind: MidiEventKind) {
+        match kind {
+            MidiEventKind::NoteOn { key, velocity } if velocity > 0 => {
+                self.notes[(key & 0x7f) as usize] = Some(velocity)
+            }
+            MidiEventKind::NoteOn { key, .. } | MidiEventKind::NoteOff { key, .. } => {
+                self.notes[(key & 0x7f) as usize] = None
+            }
+            MidiEventKind::ControllerChange { number, value } => {
+                self.controller(number & 0x7f, value)
+            }
+            MidiEventKind::ProgramChange(program) => self.program = Some(program),
+            MidiEventKind::PitchBend { lsb, msb } => {
+                self.pitch_bend = Some(u16::from(msb) << 7 | u16::from(lsb))
+            }
+            MidiEventKind::ChannelKeyPressure(pressure) => self.pressure = Some(pressure),
+            MidiEventKind::ResetAllControllers => {
+                for &number in &RESET_CONTROLLERS {
+                    self.controllers[number as usize] = None;
+                }
+                self.pitch_bend = None;
+                self.pressure = None;
+                self.selection = None;
+            }
+            MidiEventKind::AllSoundOff
+            | MidiEventKind::AllNotesOff
+            | MidiEventKind::OmniModeOff
+            | MidiEventKind::OmniModeOn
+            | MidiEventKind::MonoModeOn(_)
+            | MidiEventKind::PolyModeOn => self.notes = [None; 128],
+            MidiEventKind::PolyphonicKeyPressure { .. } | MidiEventKind::LocalControl(_) => {}
+        }
+    }
+
+    fn events(&self, events: &mut Vec<MidiEventKind>) {
+        let controller = |number: u8| {
+            self.controllers[number as usize]
+                .map(|value| MidiEventKind::ControllerChange { number, value })
+        };
+
+        // bank select must precede program change
+        events.extend(controller(BANK_SELECT_MSB));
+        events.extend(controller(BANK_SELECT_LSB));
+        events.extend(self.program.map(MidiEventKind::ProgramChange));
+        events.extend(
+            (0..128u8)
+                .filter(|&number| !matches!(number, BANK_SELECT_MSB | BANK_SELECT_LSB))
+                .filter_map(controller),
+        );
+
+        let select = |events: &mut Vec<MidiEventKind>, msb, lsb, number: u16| {
+            events.push(MidiEventKind::ControllerChange {
+                number: msb,
+                value: (number >> 7) as u8,
+            });
+            events.push(MidiEventKind::ControllerChange {
+                number: lsb,
+                value: (number & 0x7f) as u8,
+            });
+        };
+        for (&parameter, &(msb, lsb)) in &self.parameters {
+            let (msb_controller, lsb_controller, number) = parameter.controllers();
+            select(events, msb_controller, lsb_controller, number);
+            events.push(MidiEventKind::ControllerChange {
+                number: DATA_ENTRY_MSB,
+                value: msb,
+            });
+            events.extend(lsb.map(|value| MidiEventKind::ControllerChange {
+                number: DATA_ENTRY_LSB,
+                value,
+            }));
+        }
+        // restore selection, so following data entry changes the same parameter
+        let last = self.parameters.keys().next_back().copied();
+        match self.selection {
+            Some(selection) if selection.parameter().is_none() || selection.parameter() != last => {
+                let (msb, lsb) = match selection.registered {
+                    true => (RPN_MSB, RPN_LSB),
+                    false => (NRPN_MSB, NRPN_LSB),
+                };
+                let controller = |number, value: Option<u8>| {
+                    value.map(|value| MidiEventKind::ControllerChange { number, value })
+                };
+                events.extend(controller(msb, selection.msb));
+                events.extend(controller(lsb, selection.lsb));
+            }
+            None if last.is_some() => select(events, RPN_MSB, RPN_LSB, 0x3fff),
+            _ => {}
+        }
+
+        events.extend(self.pitch_bend.map(|value| MidiEventKind::PitchBend {
+            lsb: (value & 0x7f) as u8,
+            msb: (value >> 7) as u8,
+        }));
+        events.extend(self.pressure.map(MidiEventKind::ChannelKeyPressure));
+    }
+}
+
+/// State of all 16 channels after a sequence of [`MidiEvent`]s.
+///
+/// Tracks program, bank, controllers, registered and non-registered parameters, pitch bend,
+/// channel pressure and sounding notes. Used to restore the state when seeking or slicing,
+/// also known as chase.
+///
+/// # Example
+///
+/// ```
+/// # use midi;
+/// # fn chase(bytes: &[u8]) -> Result<(), midi::Error> {
+/// let smf = midi::Smf::read(bytes)?;
+/// let mut state = midi::ChannelState::new();
+/// for (_, event) in smf.tracks[0].absolute_iter().take_while(|&(tick, _)| tick < 960) {
+///     if let midi::EventKind::Midi(midi_event) = event.kind {
+///         state.update(midi_event);
+///     }
+/// }
+/// let chased = state.events();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MidiEvent`]: struct.MidiEvent.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelState {
+    channels: Vec<Channel>,
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        ChannelState::new()
+    }
+}
+
+impl ChannelState {
+    /// Creates new [`ChannelState`] with nothing set and no sounding notes.
+    ///
+    /// [`ChannelState`]: struct.ChannelState.html
+    pub fn new() -> Self {
+        ChannelState {
+            channels: vec![Channel::default(); 16],
+        }
+    }
+
+    fn channel(&self, channel: u8) -> &Channel {
+        &self.channels[(channel & 0x0f) as usize]
+    }
+
+    /// Updates the state with `event`.
+    pub fn update(&mut self, event: MidiEvent) {
+        self.channels[(event.channel & 0x0f) as usize].update(event.kind)
+    }
+
+    /// Forgets everything.
+    pub fn reset(&mut self) {
+        *self = ChannelState::new();
+    }
+
+    /// Returns the last program of the `channel`.
+    pub fn program(&self, channel: u8) -> Option<u8> {
+        self.channel(channel).program
+    }
+
+    /// Returns the last 14 bit bank of the `channel`. Missing bank select lsb is zero.
+    pub fn bank(&self, channel: u8) -> Option<u16> {
+        let channel = self.channel(channel);
+        let msb = channel.controllers[BANK_SELECT_MSB as usize]?;
+        let lsb = channel.controllers[BANK_SELECT_LSB as usize].unwrap_or(0);
+        Some(u16::from(msb) << 7 | u16::from(lsb))
+    }
+
+    /// Returns the last value of the controller `number` of the `channel`.
+    ///
+    /// Parameter number and data entry controllers are tracked as parameters, see
+    /// [`parameter`].
+    ///
+    /// [`parameter`]: #method.parameter
+    pub fn controller(&self, channel: u8, number: u8) -> Option<u8> {
+        self.channel(channel).controllers[(number & 0x7f) as usize]
+    }
+
+    /// Returns the last 14 bit pitch bend of the `channel`, `0x2000` is the center.
+    pub fn pitch_bend(&self, channel: u8) -> Option<u16> {
+        self.channel(channel).pitch_bend
+    }
+
+    /// Returns the last channel pressure of the `channel`.
+    pub fn pressure(&self, channel: u8) -> Option<u8> {
+        self.channel(channel).pressure
+    }
+
+    /// Returns the last 14 bit value of the `parameter` of the `channel`. Missing data entry
+    /// lsb is zero.
+    pub fn parameter(&self, channel: u8, parameter: Parameter) -> Option<u16> {
+        let (msb, lsb) = *self.channel(channel).parameters.get(&parameter)?;
+        Some(u16::from(msb) << 7 | u16::from(lsb.unwrap_or(0)))
+    }
+
+    /// Returns velocity of the sounding `key` of the `channel`.
+    pub fn note(&self, channel: u8, key: u8) -> Option<u8> {
+        self.channel(channel).notes[(key & 0x7f) as usize]
+    }
+
+    /// Returns iterator over sounding notes as `(channel, key, velocity)`.
+    pub fn notes(&self) -> impl Iterator<Item = (u8, u8, u8)> + '_ {
+        self.channels
+            .iter()
+            .enumerate()
+            .flat_map(|(channel, state)| {
+                state
+                    .notes
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(key, velocity)| {
+                        Some((channel as u8, key as u8, (*velocity)?))
+                    })
+            })
+    }
+
+    /// Returns the minimal list of events reproducing the state, except sounding notes.
+    ///
+    /// Bank select precedes program change and parameter numbers precede their data entry.
+    pub fn events(&self) -> Vec<MidiEvent> {
+        let mut events = Vec::new();
+        let mut kinds = Vec::new();
+        for (channel, state) in self.channels.iter().enumerate() {
+            kinds.clear();
+            state.events(&mut kinds);
+            events.extend(kinds.iter().map(|&kind| MidiEvent {
+                channel: channel as u8,
+                kind,
+            }));
+        }
+        events
+    }
+
+    /// Returns note on events of the sounding notes.
+    pub fn note_on_events(&self) -> Vec<MidiEvent> {
+        self.notes()
+            .map(|(channel, key, velocity)| MidiEvent {
+                channel,
+                kind: MidiEventKind::NoteOn { key, velocity },
+            })
+            .collect()
+    }
+
+    /// Returns note off events of the sounding notes.
+    pub fn note_off_events(&self) -> Vec<MidiEvent> {
+        self.notes()
+            .map(|(channel, key, _)| MidiEvent {
+                channel,
+                kind: MidiEventKind::NoteOff { key, velocity: 64 },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelState, Parameter};
+    use crate::{MidiEvent, MidiEventKind};
+
+    fn controller(channel: u8, number: u8, value: u8) -> MidiEvent {
+        MidiEvent {
+            channel,
+            kind: MidiEventKind::ControllerChange { number, value },
+        }
+    }
+
+    fn midi(channel: u8, kind: MidiEventKind) -> MidiEvent {
+        MidiEvent { channel, kind }
+    }
+
+    #[test]
+    fn test_channel_state() {
+        let events = [
+            midi(0, MidiEventKind::ProgramChange(10)),
+            controller(0, 7, 100),
+            controller(0, 0, 1),
+            controller(0, 32, 2),
+            // pitch bend range
+            controller(0, 101, 0),
+            controller(0, 100, 0),
+            controller(0, 6, 12),
+            controller(0, 96, 0),
+            // nrpn without lsb selected does not change anything
+            controller(0, 99, 1),
+            controller(0, 6, 50),
+            midi(0, MidiEventKind::PitchBend { lsb: 0, msb: 0x50 }),
+            midi(
+                1,
+                MidiEventKind::NoteOn {
+                    key: 60,
+                    velocity: 90,
+                },
+            ),
+            midi(
+                1,
+                MidiEventKind::NoteOn {
+                    key: 62,
+                    velocity: 80,
+                },
+            ),
+            midi(
+                1,
+                MidiEventKind::NoteOn {
+                    key: 60,
+                    velocity: 0,
+                },
+            ),
+            controller(1, 64, 127),
+            controller(1, 101, 0),
+            controller(1, 100, 1),
+            controller(1, 6, 64),
+            midi(1, MidiEventKind::ResetAllControllers),
+        ];
+
+        let mut state = ChannelState::new();
+        for &event in &events {
+            state.update(event);
+        }
+
+        assert_eq!(state.program(0), Some(10));
+        assert_eq!(state.bank(0), Some(130));
+        assert_eq!(state.controller(0, 7), Some(100));
+        assert_eq!(state.parameter(0, Parameter::Registered(0)), Some(13 << 7));
+        assert_eq!(state.parameter(0, Parameter::NonRegistered(1 << 7)), None);
+        assert_eq!(state.pitch_bend(0), Some(0x50 << 7));
+        assert_eq!(state.controller(1, 64), None);
+        assert_eq!(state.notes().collect::<Vec<_>>(), vec![(1, 62, 80)]);
+
+        assert_eq!(
+            state.events(),
+            vec![
+                controller(0, 0, 1),
+                controller(0, 32, 2),
+                midi(0, MidiEventKind::ProgramChange(10)),
+                controller(0, 7, 100),
+                controller(0, 101, 0),
+                controller(0, 100, 0),
+                controller(0, 6, 13),
+                controller(0, 99, 1),
+                midi(0, MidiEventKind::PitchBend { lsb: 0, msb: 0x50 }),
+                controller(1, 101, 0),
+                controller(1, 100, 1),
+                controller(1, 6, 64),
+                controller(1, 101, 0x7f),
+                controller(1, 100, 0x7f),
+            ]
+        );
+
+        // replaying the events reproduces the state, except sounding notes
+        let mut replayed = ChannelState::new();
+        for event in state.events().into_iter().chain(state.note_on_events()) {
+            replayed.update(event);
+        }
+        assert_eq!(replayed, state);
+    }
+}
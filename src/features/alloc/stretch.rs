@@ -0,0 +1,219 @@
+use crate::{Error, ErrorKind, Event, EventKind, MetaEvent, Smf, Timing, Track, DEFAULT_TEMPO};
+use alloc::vec::Vec;
+
+/// How [`Smf::scale_tempo`] changes the speed.
+///
+/// [`Smf::scale_tempo`]: struct.Smf.html#method.scale_tempo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TempoScaling {
+    /// Rewrite [`MetaEvent::SetTempo`] events, ticks are not changed.
+    ///
+    /// [`MetaEvent::SetTempo`]: enum.MetaEvent.html#variant.SetTempo
+    Tempo,
+    /// Rewrite time of all events, tempo is not changed.
+    Ticks,
+}
+
+/// Maps absolute time of all events of the `track` using `f`.
+///
+/// Delta times are computed from mapped absolute times, so rounding errors do not accumulate.
+fn map_ticks<'a, F>(track: &Track<'a>, f: F) -> Track<'a>
+where
+    F: Fn(u64) -> u64,
+{
+    Track::from_absolute(
+        track
+            .absolute_iter()
+            .map(|(tick, event)| (f(tick), *event))
+            .collect::<Vec<_>>(),
+    )
+}
+
+impl<'a> Smf<'a> {
+    /// Converts `SMF` to [`Timing::Metrical`] with `ppqn` pulses per quarter note.
+    ///
+    /// Absolute time of every event is rescaled and rounded, so the rounding error of delta
+    /// times does not accumulate. Fails if the `SMF` uses [`Timing::Timecode`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn resample(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let smf = midi::Smf::read(bytes)?;
+    /// let resampled = smf.to_ppqn(480)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Timing::Metrical`]: enum.Timing.html#variant.Metrical
+    /// [`Timing::Timecode`]: enum.Timing.html#variant.Timecode
+    pub fn to_ppqn(&self, ppqn: u16) -> Result<Smf<'a>, Error> {
+        let current = match self.timing {
+            Timing::Metrical(current) if ppqn > 0 && current > 0 => u128::from(current),
+            Timing::Metrical(_) => {
+                return Err(Error {
+                    context: "Smf::to_ppqn: ppqn must be greater than zero",
+                    kind: ErrorKind::Invalid,
+                })
+            }
+            Timing::Timecode { .. } => {
+                return Err(Error {
+                    context: "Smf::to_ppqn: smf uses timecode timing",
+                    kind: ErrorKind::Invalid,
+                })
+            }
+        };
+
+        let target = u128::from(ppqn);
+        let rescale = |tick: u64| {
+            let tick = (u128::from(tick) * target + current / 2) / current;
+            tick.min(u128::from(u64::MAX)) as u64
+        };
+        let smf = Smf {
+            tracks: self
+                .tracks
+                .iter()
+                .map(|track| map_ticks(track, rescale))
+                .collect(),
+            timing: Timing::Metrical(ppqn),
+            ..self.clone()
+        };
+
+        Ok(smf)
+    }
+
+    /// Makes `SMF` play `factor` times faster.
+    ///
+    /// [`TempoScaling::Tempo`] rewrites [`MetaEvent::SetTempo`] events and adds one at the
+    /// beginning of the first track if it is missing, it fails if the `SMF` uses
+    /// [`Timing::Timecode`]. [`TempoScaling::Ticks`] rewrites time of all events instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn faster(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let smf = midi::Smf::read(bytes)?;
+    /// let faster = smf.scale_tempo(1.25, midi::TempoScaling::Tempo)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TempoScaling::Tempo`]: enum.TempoScaling.html#variant.Tempo
+    /// [`TempoScaling::Ticks`]: enum.TempoScaling.html#variant.Ticks
+    /// [`MetaEvent::SetTempo`]: enum.MetaEvent.html#variant.SetTempo
+    /// [`Timing::Timecode`]: enum.Timing.html#variant.Timecode
+    pub fn scale_tempo(&self, factor: f64, scaling: TempoScaling) -> Result<Smf<'a>, Error> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(Error {
+                context: "Smf::scale_tempo: factor must be a positive number",
+                kind: ErrorKind::Invalid,
+            });
+        }
+
+        let mut smf = self.clone();
+        match scaling {
+            TempoScaling::Tempo => {
+                if let Timing::Timecode { .. } = self.timing {
+                    return Err(Error {
+                        context: "Smf::scale_tempo: smf uses timecode timing",
+                        kind: ErrorKind::Invalid,
+                    });
+                }
+
+                let scale = |tempo: u32| {
+                    (f64::from(tempo) / factor).round().clamp(1.0, 16_777_215.0) as u32
+                };
+                let mut initial = false;
+                for track in &mut smf.tracks {
+                    let mut tick = 0u64;
+                    for event in &mut track.events {
+                        tick += u64::from(event.time);
+                        if let EventKind::Meta(MetaEvent::SetTempo(ref mut tempo)) = event.kind {
+                            initial |= tick == 0;
+                            *tempo = scale(*tempo);
+                        }
+                    }
+                }
+                if let (false, Some(track)) = (initial, smf.tracks.first_mut()) {
+                    let event = Event {
+                        time: 0,
+                        kind: EventKind::Meta(MetaEvent::SetTempo(scale(DEFAULT_TEMPO))),
+                    };
+                    track.events.insert(0, event);
+                }
+            }
+            TempoScaling::Ticks => {
+                let rescale = |tick: u64| (tick as f64 / factor).round() as u64;
+                for track in &mut smf.tracks {
+                    *track = map_ticks(track, rescale);
+                }
+            }
+        }
+
+        Ok(smf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TempoScaling;
+    use crate::test_util::{note_on, tempo};
+    use crate::{test_util, Event, Format, Fps, Smf, Timing};
+
+    fn note(time: u32) -> Event<'static> {
+        note_on(time, 0, 60, 64)
+    }
+
+    fn smf(events: Vec<Event<'static>>) -> Smf<'static> {
+        test_util::smf(Format::Single, 96, vec![events])
+    }
+
+    fn smf_with_timecode() -> Smf<'static> {
+        Smf {
+            timing: Timing::Timecode {
+                fps: Fps::Fps25,
+                subframe: 40,
+            },
+            ..smf(vec![note(10)])
+        }
+    }
+
+    #[test]
+    fn test_to_ppqn() {
+        let smf = smf(vec![note(1), note(1), note(1), note(1)]);
+        let resampled = smf.to_ppqn(48).unwrap();
+        assert_eq!(resampled.timing, Timing::Metrical(48));
+        // absolute times are rounded to 1 1 2 2, rounding delta times would give 1 1 1 1
+        assert_eq!(
+            resampled.tracks[0].events,
+            vec![note(1), note(0), note(1), note(0)]
+        );
+
+        let smf = smf_with_timecode();
+        assert!(smf.to_ppqn(96).is_err());
+    }
+
+    #[test]
+    fn test_scale_tempo() {
+        let smf = smf(vec![note(10), tempo(10, 600_000), note(20)]);
+
+        let faster = smf.scale_tempo(2.0, TempoScaling::Tempo).unwrap();
+        assert_eq!(
+            faster.tracks[0].events,
+            vec![tempo(0, 250_000), note(10), tempo(10, 300_000), note(20),]
+        );
+
+        let faster = smf.scale_tempo(2.0, TempoScaling::Ticks).unwrap();
+        assert_eq!(
+            faster.tracks[0].events,
+            vec![note(5), tempo(5, 600_000), note(10)]
+        );
+
+        assert!(smf.scale_tempo(0.0, TempoScaling::Ticks).is_err());
+        assert!(smf_with_timecode()
+            .scale_tempo(2.0, TempoScaling::Tempo)
+            .is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{EventKind, MetaEvent, MidiEventKind, Smf, TempoMap, Text};
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+/// Duration, statistics and metadata of [`Smf`], see [`Smf::summary`].
+///
+/// [`Smf`]: struct.Smf.html
+/// [`Smf::summary`]: struct.Smf.html#method.summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary<'a> {
+    /// Absolute time of the last event in ticks.
+    pub ticks: u64,
+    /// Absolute time of the last event in seconds.
+    pub seconds: f64,
+    /// Number of notes of every track.
+    pub track_notes: Vec<usize>,
+    /// Number of notes of every channel.
+    pub channel_notes: [usize; 16],
+    /// Lowest and highest key, `None` without notes.
+    pub pitch_range: Option<(u8, u8)>,
+    /// Number of notes by note on velocity.
+    pub velocities: [usize; 128],
+    /// Largest number of notes sounding at the same time.
+    pub max_polyphony: usize,
+    /// `(channel, program)` pairs of program change events, sorted.
+    pub programs: Vec<(u8, u8)>,
+    /// Lowest and highest tempo in microseconds per quarter note, including the default tempo
+    /// if the first tempo change is not at tick 0.
+    pub tempo_range: (u32, u32),
+    /// First [`MetaEvent::Name`] of every track.
+    ///
+    /// [`MetaEvent::Name`]: enum.MetaEvent.html#variant.Name
+    pub track_names: Vec<Option<Text<'a>>>,
+    /// First [`MetaEvent::CopyrightNotice`] of all tracks.
+    ///
+    /// [`MetaEvent::CopyrightNotice`]: enum.MetaEvent.html#variant.CopyrightNotice
+    pub copyright: Option<Text<'a>>,
+    /// [`MetaEvent::Marker`]s of all tracks with absolute time in ticks, ordered by time.
+    ///
+    /// [`MetaEvent::Marker`]: enum.MetaEvent.html#variant.Marker
+    pub markers: Vec<(u64, Text<'a>)>,
+}
+
+impl<'a> Smf<'a> {
+    /// Collects [`Summary`] of the file.
+    ///
+    /// Notes are paired by [`Track::notes`], notes of different tracks sounding at the same time
+    /// count towards polyphony.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// # fn index(bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let smf = midi::Smf::read(bytes)?;
+    /// let summary = smf.summary();
+    /// println!("{:.1} s, {} notes", summary.seconds, summary.channel_notes.iter().sum::<usize>());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Summary`]: struct.Summary.html
+    /// [`Track::notes`]: struct.Track.html#method.notes
+    pub fn summary(&self) -> Summary<'a> {
+        let tempo_map = TempoMap::new(self);
+        let mut summary = Summary {
+            ticks: 0,
+            seconds: 0.0,
+            track_notes: Vec::with_capacity(self.tracks.len()),
+            channel_notes: [0; 16],
+            pitch_range: None,
+            velocities: [0; 128],
+            max_polyphony: 0,
+            programs: Vec::new(),
+            tempo_range: tempo_map
+                .changes()
+                .iter()
+                .fold((u32::MAX, 0), |(min, max), change| {
+                    (min.min(change.tempo), max.max(change.tempo))
+                }),
+            track_names: Vec::with_capacity(self.tracks.len()),
+            copyright: None,
+            markers: Vec::new(),
+        };
+
+        let mut programs = BTreeSet::new();
+        // (tick, order, change) with note ends before note starts at the same tick, and ends of
+        // zero length notes after their start
+        let mut polyphony = Vec::new();
+        for track in &self.tracks {
+            let mut name = None;
+            for (tick, event) in track.absolute_iter() {
+                summary.ticks = summary.ticks.max(tick);
+                match event.kind {
+                    EventKind::Midi(midi_event) => {
+                        if let MidiEventKind::ProgramChange(program) = midi_event.kind {
+                            programs.insert((midi_event.channel, program));
+                        }
+                    }
+                    EventKind::Meta(MetaEvent::Name(text)) => {
+                        name = name.or(Some(text));
+                    }
+                    EventKind::Meta(MetaEvent::CopyrightNotice(text)) => {
+                        summary.copyright = summary.copyright.or(Some(text));
+                    }
+                    EventKind::Meta(MetaEvent::Marker(text)) => summary.markers.push((tick, text)),
+                    _ => {}
+                }
+            }
+            summary.track_names.push(name);
+
+            let notes = track.notes();
+            summary.track_notes.push(notes.len());
+            for note in &notes {
+                summary.channel_notes[usize::from(note.channel & 0x0f)] += 1;
+                summary.velocities[usize::from(note.velocity & 0x7f)] += 1;
+                summary.pitch_range = Some(match summary.pitch_range {
+                    Some((low, high)) => (low.min(note.key), high.max(note.key)),
+                    None => (note.key, note.key),
+                });
+                polyphony.push((note.start, 1, 1));
+                let order = if note.end > note.start { 0 } else { 2 };
+                polyphony.push((note.end, order, -1));
+            }
+        }
+
+        polyphony.sort_unstable_by_key(|&(tick, order, _)| (tick, order));
+        let mut sounding = 0i64;
+        for (_, _, change) in polyphony {
+            sounding += change;
+            summary.max_polyphony = summary.max_polyphony.max(sounding as usize);
+        }
+
+        summary.seconds = tempo_map.seconds(summary.ticks);
+        summary.programs = programs.into_iter().collect();
+        summary.markers.sort_by_key(|&(tick, _)| tick);
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{end_of_track, meta, midi, note_on, smf, tempo};
+    use crate::{Format, MetaEvent, MidiEventKind, Text};
+
+    #[test]
+    fn test_summary() {
+        let conductor = vec![
+            meta(0, MetaEvent::Name(Text::new(b"Song"))),
+            meta(0, MetaEvent::CopyrightNotice(Text::new(b"(c) Someone"))),
+            tempo(0, 400_000),
+            meta(96, MetaEvent::Marker(Text::new(b"Chorus"))),
+            tempo(0, 600_000),
+            end_of_track(0),
+        ];
+        let piano = vec![
+            midi(0, 0, MidiEventKind::ProgramChange(1)),
+            note_on(0, 0, 60, 100),
+            note_on(0, 0, 64, 80),
+            note_on(48, 0, 60, 0),
+            note_on(0, 0, 67, 80),
+            note_on(48, 0, 64, 0),
+            note_on(0, 0, 67, 0),
+            midi(0, 9, MidiEventKind::ProgramChange(0)),
+            note_on(0, 9, 36, 127),
+            note_on(0, 9, 36, 0),
+            end_of_track(96),
+        ];
+        let smf = smf(Format::MultiTrack, 96, vec![conductor, piano]);
+
+        let summary = smf.summary();
+        assert_eq!(summary.ticks, 192);
+        assert!((summary.seconds - 1.0).abs() < 1e-9);
+        assert_eq!(summary.track_notes, vec![0, 4]);
+        assert_eq!(summary.channel_notes[0], 3);
+        assert_eq!(summary.channel_notes[9], 1);
+        assert_eq!(summary.pitch_range, Some((36, 67)));
+        assert_eq!(summary.velocities[80], 2);
+        assert_eq!(summary.velocities[0], 0);
+        // the zero length drum note starts when the other notes end
+        assert_eq!(summary.max_polyphony, 2);
+        assert_eq!(summary.programs, vec![(0, 1), (9, 0)]);
+        assert_eq!(summary.tempo_range, (400_000, 600_000));
+        assert_eq!(summary.track_names, vec![Some(Text::new(b"Song")), None]);
+        assert_eq!(summary.copyright, Some(Text::new(b"(c) Someone")));
+        assert_eq!(summary.markers, vec![(96, Text::new(b"Chorus"))]);
+    }
+}
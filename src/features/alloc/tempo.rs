@@ -0,0 +1,200 @@
+use crate::{EventKind, MetaEvent, Smf, Timing};
+use alloc::vec::Vec;
+
+/// Tempo used when no [`MetaEvent::SetTempo`] is specified (120 bpm).
+///
+/// [`MetaEvent::SetTempo`]: enum.MetaEvent.html#variant.SetTempo
+pub const DEFAULT_TEMPO: u32 = 500_000;
+
+/// Tempo change at an absolute tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoChange {
+    /// Absolute time in ticks.
+    pub tick: u64,
+    /// Microseconds per quarter note.
+    pub tempo: u32,
+    /// Absolute time in seconds.
+    pub seconds: f64,
+}
+
+/// Conversion between ticks and seconds.
+///
+/// # Example
+///
+/// ```
+/// # use midi;
+/// # fn seconds(bytes: &[u8]) -> Result<(), midi::Error> {
+/// let smf = midi::Smf::read(bytes)?;
+/// let tempo_map = midi::TempoMap::new(&smf);
+/// let seconds = tempo_map.seconds(960);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TempoMap {
+    timing: Timing,
+    changes: Vec<TempoChange>,
+}
+
+impl TempoMap {
+    /// Creates new [`TempoMap`] from all [`MetaEvent::SetTempo`] events of the `smf`.
+    ///
+    /// [`TempoMap`]: struct.TempoMap.html
+    /// [`MetaEvent::SetTempo`]: enum.MetaEvent.html#variant.SetTempo
+    pub fn new(smf: &Smf) -> Self {
+        let tempos = smf.tracks.iter().flat_map(|track| {
+            track
+                .absolute_iter()
+                .filter_map(|(tick, event)| match event.kind {
+                    EventKind::Meta(MetaEvent::SetTempo(tempo)) => Some((tick, tempo)),
+                    _ => None,
+                })
+        });
+        Self::from_tempos(smf.timing, tempos)
+    }
+
+    /// Creates new [`TempoMap`] from `(tick, tempo)` pairs.
+    ///
+    /// Pairs do not need to be sorted. If there are several tempo changes at the same tick,
+    /// the last one wins.
+    ///
+    /// [`TempoMap`]: struct.TempoMap.html
+    pub fn from_tempos<I>(timing: Timing, tempos: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, u32)>,
+    {
+        let mut tempos = tempos.into_iter().collect::<Vec<_>>();
+        // stable sort keeps the order of events at the same tick
+        tempos.sort_by_key(|&(tick, _)| tick);
+
+        let mut changes: Vec<TempoChange> = Vec::with_capacity(tempos.len() + 1);
+        changes.push(TempoChange {
+            tick: 0,
+            tempo: DEFAULT_TEMPO,
+            seconds: 0.0,
+        });
+
+        for (tick, tempo) in tempos {
+            let last = *changes.last().expect("changes are never empty; qed");
+            if last.tick == tick {
+                changes
+                    .last_mut()
+                    .expect("changes are never empty; qed")
+                    .tempo = tempo;
+                continue;
+            }
+
+            let seconds = last.seconds + ticks_to_seconds(timing, tick - last.tick, last.tempo);
+            changes.push(TempoChange {
+                tick,
+                tempo,
+                seconds,
+            });
+        }
+
+        TempoMap { timing, changes }
+    }
+
+    /// Returns timing used for conversions.
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Returns all tempo changes ordered by time.
+    ///
+    /// The first change is always at tick 0.
+    pub fn changes(&self) -> &[TempoChange] {
+        &self.changes
+    }
+
+    /// Returns tempo in microseconds per quarter note at `tick`.
+    pub fn tempo(&self, tick: u64) -> u32 {
+        self.change_at(tick).tempo
+    }
+
+    /// Converts absolute time in ticks to seconds.
+    pub fn seconds(&self, tick: u64) -> f64 {
+        let change = self.change_at(tick);
+        change.seconds + ticks_to_seconds(self.timing, tick - change.tick, change.tempo)
+    }
+
+    /// Converts absolute time in seconds to ticks, rounding down.
+    pub fn tick(&self, seconds: f64) -> u64 {
+        if seconds <= 0.0 {
+            return 0;
+        }
+
+        let index = self
+            .changes
+            .iter()
+            .rposition(|change| change.seconds <= seconds)
+            .unwrap_or(0);
+        let change = self.changes[index];
+        let ticks_per_second = 1.0 / ticks_to_seconds(self.timing, 1, change.tempo);
+        // guard against rounding errors pushing us behind the next change
+        let offset = ((seconds - change.seconds) * ticks_per_second + 1e-6).floor() as u64;
+        match self.changes.get(index + 1) {
+            Some(next) => (change.tick + offset).min(next.tick),
+            None => change.tick + offset,
+        }
+    }
+
+    fn change_at(&self, tick: u64) -> &TempoChange {
+        let index = self
+            .changes
+            .iter()
+            .rposition(|change| change.tick <= tick)
+            .unwrap_or(0);
+        &self.changes[index]
+    }
+}
+
+fn ticks_to_seconds(timing: Timing, ticks: u64, tempo: u32) -> f64 {
+    match timing {
+        Timing::Metrical(ppqn) => {
+            ticks as f64 * f64::from(tempo) / (1_000_000.0 * f64::from(ppqn.max(1)))
+        }
+        Timing::Timecode { fps, subframe } => {
+            ticks as f64 / (fps.as_f64() * f64::from(subframe.max(1)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TempoMap, DEFAULT_TEMPO};
+    use crate::{Fps, Timing};
+
+    #[test]
+    fn test_default_tempo() {
+        let tempo_map = TempoMap::from_tempos(Timing::Metrical(96), None);
+        assert_eq!(tempo_map.tempo(1000), DEFAULT_TEMPO);
+        assert_eq!(tempo_map.seconds(96), 0.5);
+        assert_eq!(tempo_map.tick(0.5), 96);
+    }
+
+    #[test]
+    fn test_tempo_changes() {
+        let tempos = vec![(192, 1_000_000), (0, 250_000), (192, 2_000_000)];
+        let tempo_map = TempoMap::from_tempos(Timing::Metrical(96), tempos);
+        assert_eq!(tempo_map.changes().len(), 2);
+        assert_eq!(tempo_map.seconds(96), 0.25);
+        assert_eq!(tempo_map.seconds(192), 0.5);
+        assert_eq!(tempo_map.seconds(288), 2.5);
+        assert_eq!(tempo_map.tempo(191), 250_000);
+        assert_eq!(tempo_map.tempo(192), 2_000_000);
+        assert_eq!(tempo_map.tick(0.5), 192);
+        assert_eq!(tempo_map.tick(2.5), 288);
+    }
+
+    #[test]
+    fn test_timecode() {
+        let timing = Timing::Timecode {
+            fps: Fps::Fps25,
+            subframe: 40,
+        };
+        let tempo_map = TempoMap::from_tempos(timing, vec![(0, 1_000_000)]);
+        assert_eq!(tempo_map.seconds(1000), 1.0);
+        assert_eq!(tempo_map.tick(1.0), 1000);
+    }
+}
@@ -0,0 +1,454 @@
+//! Crate options behind `std` feature.
+
+use crate::{
+    read::{is_rmid, read_event, read_header_chunk, HeaderChunk},
+    Error, ErrorKind, OwnedEvent,
+};
+use std::{io, vec::Vec};
+
+fn io_context(context: &'static str) -> impl FnOnce(io::Error) -> Error {
+    move |_| Error {
+        context,
+        kind: ErrorKind::Fatal,
+    }
+}
+
+/// Streaming `SMF` reader.
+///
+/// Unlike [`SmfReader`], it does not require the entire file to be loaded into memory. Chunks are
+/// pulled from any [`io::Read`] as they are needed. Reading byte by byte from unbuffered sources
+/// is slow, so consider wrapping them in [`io::BufReader`].
+///
+/// # Example
+///
+/// ```
+/// # use midi;
+/// # fn stream_read(path: &str) -> Result<(), midi::Error> {
+/// # let file = std::fs::File::open(path).unwrap();
+/// let mut reader = midi::StreamReader::new(std::io::BufReader::new(file))?;
+/// while let Some(track) = reader.next_track() {
+///     for event in track? {
+///         let event: midi::OwnedEvent = event?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`SmfReader`]: read/struct.SmfReader.html
+/// [`io::Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
+/// [`io::BufReader`]: https://doc.rust-lang.org/std/io/struct.BufReader.html
+pub struct StreamReader<R> {
+    reader: R,
+    header: HeaderChunk,
+    // number of track chunks left to read
+    tracks: usize,
+    // unread bytes of the current track chunk
+    remaining: u64,
+}
+
+impl<R: io::Read> StreamReader<R> {
+    /// Creates new [`StreamReader`] and reads [`HeaderChunk`].
+    ///
+    /// `SMF` wrapped in RIFF `RMID` container is unwrapped. Container chunks preceding the `SMF`
+    /// data are skipped.
+    ///
+    /// [`StreamReader`]: struct.StreamReader.html
+    /// [`HeaderChunk`]: read/struct.HeaderChunk.html
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut bytes = [0u8; 14];
+        reader
+            .read_exact(&mut bytes[..12])
+            .map_err(io_context("StreamReader::new: failed to read header chunk"))?;
+
+        if is_rmid(&bytes) {
+            // find the 'data' chunk
+            loop {
+                let mut chunk_header = [0u8; 8];
+                reader.read_exact(&mut chunk_header).map_err(io_context(
+                    "StreamReader::new: container must contain 'data' chunk",
+                ))?;
+                if &chunk_header[..4] == b"data" {
+                    break;
+                }
+
+                let len = u32::from_le_bytes([
+                    chunk_header[4],
+                    chunk_header[5],
+                    chunk_header[6],
+                    chunk_header[7],
+                ]);
+                // chunks are padded to even size
+                let len = u64::from(len) + u64::from(len % 2);
+                let skipped = io::copy(&mut io::Read::take(&mut reader, len), &mut io::sink())
+                    .map_err(io_context(
+                        "StreamReader::new: failed to skip container chunk",
+                    ))?;
+                if skipped != len {
+                    return Err(Error {
+                        context: "StreamReader::new: container must contain 'data' chunk",
+                        kind: ErrorKind::Fatal,
+                    });
+                }
+            }
+            reader
+                .read_exact(&mut bytes[..12])
+                .map_err(io_context("StreamReader::new: failed to read header chunk"))?;
+        }
+
+        reader
+            .read_exact(&mut bytes[12..])
+            .map_err(io_context("StreamReader::new: failed to read header chunk"))?;
+        let header = read_header_chunk(&mut &bytes[..])?;
+
+        let stream_reader = StreamReader {
+            reader,
+            header,
+            tracks: header.tracks as usize,
+            remaining: 0,
+        };
+
+        Ok(stream_reader)
+    }
+
+    /// Returns [`HeaderChunk`].
+    ///
+    /// [`HeaderChunk`]: read/struct.HeaderChunk.html
+    pub fn header_chunk(&self) -> HeaderChunk {
+        self.header
+    }
+
+    /// Returns underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Starts reading next track chunk.
+    ///
+    /// Unread events of the previous track chunk are discarded. If a chunk type is not `MTrk`,
+    /// it is ignored and skipped over.
+    pub fn next_track(&mut self) -> Option<Result<TrackReader<'_, R>, Error>> {
+        let result = self
+            .start_track(|reader, len| {
+                io::copy(&mut io::Read::take(reader, len), &mut io::sink()).and_then(|copied| {
+                    if copied == len {
+                        Ok(())
+                    } else {
+                        Err(io::ErrorKind::UnexpectedEof.into())
+                    }
+                })
+            })?
+            .map(move |_| TrackReader {
+                stream: self,
+                running_status: None,
+                buffer: Vec::new(),
+            });
+
+        Some(result)
+    }
+
+    /// Discards unread bytes of the current track chunk using `skip` and reads the length of the
+    /// next `MTrk` chunk.
+    fn start_track<F>(&mut self, mut skip: F) -> Option<Result<(), Error>>
+    where
+        F: FnMut(&mut R, u64) -> io::Result<()>,
+    {
+        if self.remaining != 0 {
+            let remaining = self.remaining;
+            self.remaining = 0;
+            if let Err(err) = skip(&mut self.reader, remaining) {
+                self.tracks = 0;
+                return Some(Err(io_context(
+                    "StreamReader::next_track: failed to skip track data",
+                )(err)));
+            }
+        }
+
+        if self.tracks == 0 {
+            return None;
+        }
+        self.tracks -= 1;
+
+        loop {
+            let mut bytes = [0u8; 8];
+            if let Err(err) = self.reader.read_exact(&mut bytes) {
+                self.tracks = 0;
+                return Some(Err(io_context(
+                    "StreamReader::next_track: chunk must specify type and len",
+                )(err)));
+            }
+
+            let len = u64::from(u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]));
+            // The midi specification requires that software be able to handle unexpected
+            // chunk-types by ignoring the entire chunk
+            if &bytes[..4] == b"MTrk" {
+                self.remaining = len;
+                return Some(Ok(()));
+            }
+
+            if let Err(err) = skip(&mut self.reader, len) {
+                self.tracks = 0;
+                return Some(Err(io_context(
+                    "StreamReader::next_track: failed to skip chunk",
+                )(err)));
+            }
+        }
+    }
+}
+
+impl<R: io::Read + io::Seek> StreamReader<R> {
+    /// Skips next track chunk without reading its events.
+    ///
+    /// Unread events of the previous track chunk are skipped as well.
+    pub fn skip_track(&mut self) -> Option<Result<(), Error>> {
+        let seek =
+            |reader: &mut R, len: u64| reader.seek(io::SeekFrom::Current(len as i64)).map(|_| ());
+
+        match self.start_track(seek)? {
+            Ok(()) => {
+                let remaining = self.remaining;
+                self.remaining = 0;
+                let result = seek(&mut self.reader, remaining)
+                    .map_err(io_context("StreamReader::skip_track: failed to skip track"));
+                Some(result)
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Iterator over [`OwnedEvent`]s of a single track chunk.
+///
+/// Created using [`StreamReader::next_track`] method.
+///
+/// [`OwnedEvent`]: struct.OwnedEvent.html
+/// [`StreamReader::next_track`]: struct.StreamReader.html#method.next_track
+pub struct TrackReader<'a, R> {
+    stream: &'a mut StreamReader<R>,
+    running_status: Option<u8>,
+    // raw bytes of the event being read
+    buffer: Vec<u8>,
+}
+
+impl<'a, R: io::Read> TrackReader<'a, R> {
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        if self.stream.remaining == 0 {
+            return Err(Error {
+                context: "TrackReader::next: event must fit into the track chunk",
+                kind: ErrorKind::Invalid,
+            });
+        }
+
+        let mut byte = [0u8];
+        self.stream
+            .reader
+            .read_exact(&mut byte)
+            .map_err(io_context("TrackReader::next: failed to read event"))?;
+        self.stream.remaining -= 1;
+        Ok(byte[0])
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<(), Error> {
+        if self.stream.remaining < len as u64 {
+            return Err(Error {
+                context: "TrackReader::next: event must fit into the track chunk",
+                kind: ErrorKind::Invalid,
+            });
+        }
+
+        let start = self.buffer.len();
+        self.buffer.resize(start + len, 0);
+        self.stream
+            .reader
+            .read_exact(&mut self.buffer[start..])
+            .map_err(io_context("TrackReader::next: failed to read event"))?;
+        self.stream.remaining -= len as u64;
+        Ok(())
+    }
+
+    fn read_vlq(&mut self) -> Result<u32, Error> {
+        let mut result = 0u32;
+        for _ in 0..4 {
+            let byte = self.read_u8()?;
+            self.buffer.push(byte);
+            result = (result << 7) | u32::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+
+        Err(Error {
+            context: "TrackReader::next: vlq must fit into 32 bit integer",
+            kind: ErrorKind::Invalid,
+        })
+    }
+
+    fn read_data(&mut self) -> Result<(), Error> {
+        let len = self.read_vlq()?;
+        self.read_bytes(len as usize)
+    }
+
+    fn read_event(&mut self) -> Result<OwnedEvent, Error> {
+        self.buffer.clear();
+        self.read_vlq()?;
+
+        let byte = self.read_u8()?;
+        let (status, data_bytes) = if byte & 0x80 != 0 {
+            (byte, 0)
+        } else {
+            // running status, the byte is already a data byte
+            let status = self.running_status.ok_or(Error {
+                context: "TrackReader::next: data byte without running status",
+                kind: ErrorKind::Invalid,
+            })?;
+            (status, 1)
+        };
+
+        self.buffer.push(status);
+        // midi events set running status, sysex and meta events do not affect it
+        match status {
+            0xff => {
+                let meta_type = self.read_u8()?;
+                self.buffer.push(meta_type);
+                self.read_data()?;
+            }
+            0xf0 | 0xf7 => {
+                self.read_data()?;
+            }
+            0x80..=0xef => {
+                self.running_status = Some(status);
+                if data_bytes == 1 {
+                    self.buffer.push(byte);
+                }
+                let len = match status & 0xf0 {
+                    0xc0 | 0xd0 => 1,
+                    _ => 2,
+                };
+                self.read_bytes(len - data_bytes)?;
+            }
+            _ => {
+                return Err(Error {
+                    context: "TrackReader::next: unsupported event type",
+                    kind: ErrorKind::Invalid,
+                })
+            }
+        }
+
+        let event = read_event(&mut &self.buffer[..])?;
+        Ok(event.into())
+    }
+}
+
+impl<'a, R: io::Read> Iterator for TrackReader<'a, R> {
+    type Item = Result<OwnedEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stream.remaining == 0 {
+            return None;
+        }
+
+        let result = self.read_event();
+        if result.is_err() {
+            // the position within the track is unknown, stop reading the file
+            self.stream.remaining = 0;
+            self.stream.tracks = 0;
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamReader;
+    use crate::{EventKind, MidiEvent, MidiEventKind};
+    use std::io;
+
+    #[rustfmt::skip]
+    const SMF: &[u8] = &[
+        // header
+        0x4d, 0x54, 0x68, 0x64, 0, 0, 0, 6, 0, 1, 0, 2, 0, 0x60,
+        // unknown chunk
+        0x58, 0x46, 0x49, 0x48, 0, 0, 0, 2, 0xaa, 0xbb,
+        // track with running status
+        0x4d, 0x54, 0x72, 0x6b, 0, 0, 0, 11,
+        0x00, 0x90, 0x3c, 0x40,
+        0x60, 0x3c, 0x00,
+        0x00, 0xff, 0x2f, 0x00,
+        // track with end of track only
+        0x4d, 0x54, 0x72, 0x6b, 0, 0, 0, 4,
+        0x00, 0xff, 0x2f, 0x00,
+    ];
+
+    #[test]
+    fn test_stream_reader() {
+        let mut reader = StreamReader::new(SMF).unwrap();
+        assert_eq!(reader.header_chunk().tracks, 2);
+
+        let events = reader
+            .next_track()
+            .unwrap()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[1].time, 0x60);
+        assert_eq!(
+            events[1].as_event().kind,
+            EventKind::Midi(MidiEvent {
+                channel: 0,
+                kind: MidiEventKind::NoteOn {
+                    key: 0x3c,
+                    velocity: 0
+                },
+            })
+        );
+
+        assert_eq!(reader.next_track().unwrap().unwrap().count(), 1);
+        assert!(reader.next_track().is_none());
+    }
+
+    #[test]
+    fn test_stream_reader_running_status() {
+        #[rustfmt::skip]
+        let smf: &[u8] = &[
+            // header
+            0x4d, 0x54, 0x68, 0x64, 0, 0, 0, 6, 0, 0, 0, 1, 0, 0x60,
+            // track with running status across meta and sysex events
+            0x4d, 0x54, 0x72, 0x6b, 0, 0, 0, 20,
+            0x00, 0x90, 0x3c, 0x40,
+            0x00, 0xff, 0x01, 0x01, 0x41,
+            0x00, 0xf0, 0x01, 0xf7,
+            0x60, 0x3c, 0x00,
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let mut reader = StreamReader::new(smf).unwrap();
+        let events = reader
+            .next_track()
+            .unwrap()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            events[3].as_event().kind,
+            EventKind::Midi(MidiEvent {
+                channel: 0,
+                kind: MidiEventKind::NoteOn {
+                    key: 0x3c,
+                    velocity: 0
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_stream_reader_skip() {
+        let mut reader = StreamReader::new(io::Cursor::new(SMF)).unwrap();
+        // skip partially read track
+        let mut track = reader.next_track().unwrap().unwrap();
+        track.next().unwrap().unwrap();
+        reader.skip_track().unwrap().unwrap();
+        assert!(reader.skip_track().is_none());
+        assert_eq!(reader.into_inner().position(), SMF.len() as u64);
+    }
+}
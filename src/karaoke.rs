@@ -0,0 +1,362 @@
+//! Timed lyrics extraction.
+//!
+//! Supports both `.kar` files (Soft Karaoke convention) and standard [`MetaEvent::Lyric`] events.
+//!
+//! Karaoke files store lyrics as [`MetaEvent::Text`] events. Text starting with `@` is a tag:
+//!
+//! - `@K` - file type, e.g. `@KMIDI KARAOKE FILE`
+//! - `@V` - version
+//! - `@I` - additional information
+//! - `@L` - language
+//! - `@T` - title, author and copyright, one per event
+//!
+//! Any other text is a syllable. A syllable starting with `/` begins a new line and a syllable
+//! starting with `\` begins a new paragraph. Standard lyrics use carriage return to end the line
+//! and line feed to end the paragraph instead.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! # fn print_lyrics(bytes: &[u8]) -> Result<(), midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let lyrics = midi::karaoke::Lyrics::read(&smf);
+//! for line in lyrics.lines {
+//!     for syllable in line.syllables {
+//!         let _at = syllable.seconds;
+//!         let _text = syllable.text.as_utf8();
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`MetaEvent::Lyric`]: ../enum.MetaEvent.html#variant.Lyric
+//! [`MetaEvent::Text`]: ../enum.MetaEvent.html#variant.Text
+
+use crate::{EventKind, MetaEvent, Smf, TempoMap, Text};
+use alloc::vec::Vec;
+
+/// Kind of events lyrics were read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// [`MetaEvent::Text`] events of a `.kar` file.
+    ///
+    /// [`MetaEvent::Text`]: ../enum.MetaEvent.html#variant.Text
+    Kar,
+    /// [`MetaEvent::Lyric`] events.
+    ///
+    /// [`MetaEvent::Lyric`]: ../enum.MetaEvent.html#variant.Lyric
+    Lyric,
+}
+
+/// Single timed piece of lyrics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Syllable<'a> {
+    /// Syllable text stripped of line and paragraph markers.
+    pub text: Text<'a>,
+    /// Absolute time in ticks.
+    pub tick: u64,
+    /// Absolute time in seconds.
+    pub seconds: f64,
+}
+
+/// Line of lyrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line<'a> {
+    /// True if the line begins a new paragraph.
+    pub paragraph: bool,
+    /// Non-empty list of line syllables.
+    pub syllables: Vec<Syllable<'a>>,
+}
+
+impl<'a> Line<'a> {
+    /// Returns time in seconds of the first syllable.
+    pub fn start(&self) -> f64 {
+        self.syllables.first().map_or(0.0, |s| s.seconds)
+    }
+}
+
+/// Lyrics of the song.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lyrics<'a> {
+    /// Kind of events lyrics were read from. `None` if the song has no lyrics.
+    pub source: Option<Source>,
+    /// `@V` tag.
+    pub version: Option<Text<'a>>,
+    /// `@L` tag.
+    pub language: Option<Text<'a>>,
+    /// `@T` tags.
+    pub title: Vec<Text<'a>>,
+    /// `@I` tags.
+    pub info: Vec<Text<'a>>,
+    /// Lyrics lines ordered by time.
+    pub lines: Vec<Line<'a>>,
+}
+
+impl<'a> Lyrics<'a> {
+    /// Reads lyrics of the `smf`.
+    ///
+    /// If the song is a karaoke file, lyrics are read from the track with the most text events.
+    /// Otherwise they are read from the track with the most [`MetaEvent::Lyric`] events.
+    ///
+    /// [`MetaEvent::Lyric`]: ../enum.MetaEvent.html#variant.Lyric
+    pub fn read(smf: &Smf<'a>) -> Self {
+        Self::read_with_tempo_map(smf, &TempoMap::new(smf))
+    }
+
+    /// Reads lyrics of the `smf` using already built [`TempoMap`].
+    ///
+    /// [`TempoMap`]: ../struct.TempoMap.html
+    pub fn read_with_tempo_map(smf: &Smf<'a>, tempo_map: &TempoMap) -> Self {
+        let mut lyrics = Lyrics::default();
+
+        let is_kar =
+            smf.tracks
+                .iter()
+                .flat_map(|track| track.events.iter())
+                .any(|event| match event.kind {
+                    EventKind::Meta(MetaEvent::Text(text)) => text.raw().starts_with(b"@K"),
+                    _ => false,
+                });
+
+        if is_kar {
+            for track in &smf.tracks {
+                for event in &track.events {
+                    if let EventKind::Meta(MetaEvent::Text(text)) = event.kind {
+                        lyrics.read_tag(text.raw());
+                    }
+                }
+            }
+
+            let syllables = lyrics_track(smf, |kind| match *kind {
+                EventKind::Meta(MetaEvent::Text(text)) if !text.raw().starts_with(b"@") => {
+                    Some(text)
+                }
+                _ => None,
+            });
+
+            if !syllables.is_empty() {
+                lyrics.source = Some(Source::Kar);
+                lyrics.lines = lines(syllables, tempo_map);
+                return lyrics;
+            }
+        }
+
+        let syllables = lyrics_track(smf, |kind| match *kind {
+            EventKind::Meta(MetaEvent::Lyric(text)) => Some(text),
+            _ => None,
+        });
+
+        if !syllables.is_empty() {
+            lyrics.source = Some(Source::Lyric);
+            lyrics.lines = lines(syllables, tempo_map);
+        }
+
+        lyrics
+    }
+
+    fn read_tag(&mut self, raw: &'a [u8]) {
+        if raw.len() < 2 || raw[0] != b'@' {
+            return;
+        }
+
+        let value = Text::new(&raw[2..]);
+        match raw[1] {
+            b'V' => self.version = Some(value),
+            b'L' => self.language = Some(value),
+            b'T' => self.title.push(value),
+            b'I' => self.info.push(value),
+            _ => {}
+        }
+    }
+}
+
+/// Returns `(tick, text)` pairs of the track with the most texts matched by `f`.
+fn lyrics_track<'a, F>(smf: &Smf<'a>, f: F) -> Vec<(u64, Text<'a>)>
+where
+    F: Fn(&EventKind<'a>) -> Option<Text<'a>>,
+{
+    smf.tracks
+        .iter()
+        .map(|track| {
+            track
+                .absolute_iter()
+                .filter_map(|(tick, event)| f(&event.kind).map(|text| (tick, text)))
+                .collect::<Vec<_>>()
+        })
+        .fold(Vec::new(), |best, texts| {
+            if texts.len() > best.len() {
+                texts
+            } else {
+                best
+            }
+        })
+}
+
+fn lines<'a>(syllables: Vec<(u64, Text<'a>)>, tempo_map: &TempoMap) -> Vec<Line<'a>> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut new_line = true;
+    let mut new_paragraph = true;
+
+    for (tick, text) in syllables {
+        let mut raw = text.raw();
+
+        // karaoke markers
+        match raw.first() {
+            Some(b'\\') => {
+                new_line = true;
+                new_paragraph = true;
+                raw = &raw[1..];
+            }
+            Some(b'/') => {
+                new_line = true;
+                raw = &raw[1..];
+            }
+            _ => {}
+        }
+
+        // standard lyrics markers may precede the syllable as well
+        while let Some(&byte) = raw.first() {
+            if !apply_break(byte, &mut new_line, &mut new_paragraph) {
+                break;
+            }
+            raw = &raw[1..];
+        }
+
+        let mut end = raw.len();
+        while end > 0 && matches!(raw[end - 1], b'\r' | b'\n') {
+            end -= 1;
+        }
+        let (raw, trailing) = raw.split_at(end);
+
+        if !raw.is_empty() {
+            if new_line || lines.is_empty() {
+                lines.push(Line {
+                    paragraph: new_paragraph,
+                    syllables: Vec::new(),
+                });
+                new_line = false;
+                new_paragraph = false;
+            }
+
+            let line = lines.last_mut().expect("line has just been pushed; qed");
+            line.syllables.push(Syllable {
+                text: Text::new(raw),
+                tick,
+                seconds: tempo_map.seconds(tick),
+            });
+        }
+
+        for &byte in trailing {
+            apply_break(byte, &mut new_line, &mut new_paragraph);
+        }
+    }
+
+    lines
+}
+
+fn apply_break(byte: u8, new_line: &mut bool, new_paragraph: &mut bool) -> bool {
+    match byte {
+        b'\r' => *new_line = true,
+        b'\n' => {
+            *new_line = true;
+            *new_paragraph = true;
+        }
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lyrics, Source};
+    use crate::test_util::meta;
+    use crate::{test_util, Event, Format, MetaEvent, Smf, Text};
+
+    fn text(time: u32, data: &[u8]) -> Event<'_> {
+        meta(time, MetaEvent::Text(Text::new(data)))
+    }
+
+    fn lyric(time: u32, data: &[u8]) -> Event<'_> {
+        meta(time, MetaEvent::Lyric(Text::new(data)))
+    }
+
+    fn smf(tracks: Vec<Vec<Event>>) -> Smf {
+        test_util::smf(Format::MultiTrack, 96, tracks)
+    }
+
+    fn line_texts<'a>(lyrics: &Lyrics<'a>) -> Vec<(bool, Vec<&'a [u8]>)> {
+        lyrics
+            .lines
+            .iter()
+            .map(|line| {
+                let texts = line.syllables.iter().map(|s| s.text.raw()).collect();
+                (line.paragraph, texts)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_kar_lyrics() {
+        let smf = smf(vec![
+            vec![text(0, b"@KMIDI KARAOKE FILE"), text(0, b"@V0100")],
+            vec![
+                text(0, b"@LENGL"),
+                text(0, b"@TPirates"),
+                text(0, b"@TKlaus Badelt"),
+                text(96, b"\\Yo "),
+                text(48, b"ho"),
+                text(48, b"/Yo "),
+                text(96, b"ho"),
+            ],
+        ]);
+
+        let lyrics = Lyrics::read(&smf);
+        assert_eq!(lyrics.source, Some(Source::Kar));
+        assert_eq!(lyrics.version, Some(Text::new(b"0100")));
+        assert_eq!(lyrics.language, Some(Text::new(b"ENGL")));
+        assert_eq!(
+            lyrics.title,
+            vec![Text::new(b"Pirates"), Text::new(b"Klaus Badelt")]
+        );
+        assert_eq!(
+            line_texts(&lyrics),
+            vec![
+                (true, vec![b"Yo " as &[u8], b"ho"]),
+                (false, vec![b"Yo " as &[u8], b"ho"]),
+            ]
+        );
+        assert_eq!(lyrics.lines[0].start(), 0.5);
+        assert_eq!(lyrics.lines[1].syllables[1].tick, 288);
+        assert_eq!(lyrics.lines[1].syllables[1].seconds, 1.5);
+    }
+
+    #[test]
+    fn test_standard_lyrics() {
+        let smf = smf(vec![vec![
+            text(0, b"not lyrics"),
+            lyric(0, b"Hel"),
+            lyric(10, b"lo\r"),
+            lyric(10, b"world\n"),
+            lyric(10, b"again"),
+        ]]);
+
+        let lyrics = Lyrics::read(&smf);
+        assert_eq!(lyrics.source, Some(Source::Lyric));
+        assert_eq!(
+            line_texts(&lyrics),
+            vec![
+                (true, vec![b"Hel" as &[u8], b"lo"]),
+                (false, vec![b"world" as &[u8]]),
+                (true, vec![b"again" as &[u8]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_lyrics() {
+        let smf = smf(vec![vec![text(0, b"just a comment")]]);
+        assert_eq!(Lyrics::read(&smf), Lyrics::default());
+    }
+}
@@ -35,10 +35,37 @@
 
 #![cfg_attr(not(feature = "alloc"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+pub mod csv;
+#[cfg(feature = "alloc")]
+pub mod dump;
+mod encoding;
 mod features;
+#[cfg(feature = "alloc")]
+pub mod karaoke;
+pub mod mtc;
+#[cfg(feature = "alloc")]
+pub mod player;
+#[cfg(feature = "alloc")]
+pub mod quantize;
 pub mod read;
+#[cfg(feature = "alloc")]
+pub mod sync;
+#[cfg(feature = "synth")]
+pub mod synth;
+#[cfg(all(test, feature = "alloc"))]
+mod test_util;
+#[cfg(feature = "alloc")]
+pub mod transform;
+pub mod ump;
+#[cfg(feature = "alloc")]
+pub mod write;
 
 use core::str;
+pub use encoding::*;
 pub use features::*;
 
 /// `SMF` reader error.
@@ -63,6 +90,8 @@ pub enum ErrorKind {
 
 /// `SMF` format specified in `MThd` chunk.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Format {
     Single,
     MultiTrack,
@@ -73,6 +102,8 @@ pub enum Format {
 ///
 /// [`Timing`]: enum.Timing.html
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Fps {
     Fps24,
     Fps25,
@@ -80,6 +111,20 @@ pub enum Fps {
     Fps30NonDrop,
 }
 
+impl Fps {
+    /// Returns number of frames per second.
+    ///
+    /// `Fps30Drop` runs at 29.97 frames per second.
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Fps::Fps24 => 24.0,
+            Fps::Fps25 => 25.0,
+            Fps::Fps30Drop => 30_000.0 / 1001.0,
+            Fps::Fps30NonDrop => 30.0,
+        }
+    }
+}
+
 /// `SMF` timing specified in `MThd` chunk.
 ///
 /// With metrical timing, the timing interval is tempo related, whereas with timecode the timing
@@ -88,6 +133,8 @@ pub enum Fps {
 /// A timing resolution of 1 ms can be achieved by specifying 25 fps and 40 sub-frames, which would
 /// be encoded in hex as  E7 28.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Timing {
     /// Specifies number of sub-visions of a querter note (aka pulses per quarter note, ppqn)
     Metrical(u16),
@@ -102,7 +149,8 @@ pub enum Timing {
 /// [`Event`] variant.
 ///
 /// [`Event`]: struct.Event.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MidiEvent {
     pub channel: u8,
     pub kind: MidiEventKind,
@@ -112,7 +160,9 @@ pub struct MidiEvent {
 ///
 /// [`MidiEventKind::LocalControl`]:
 /// enum.MidiEventKind.html#variant.LocalControl
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Action {
     Disconnect,
     Reconnect,
@@ -121,7 +171,9 @@ pub enum Action {
 /// [`MidiEvent`] variants.
 ///
 /// [`MidiEvent`]: struct.MidiEvent.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum MidiEventKind {
     NoteOff { key: u8, velocity: u8 },
     NoteOn { key: u8, velocity: u8 },
@@ -141,10 +193,68 @@ pub enum MidiEventKind {
     PolyModeOn,
 }
 
+/// System common and system real-time message.
+///
+/// These messages are not part of `SMF` files, they are used by devices and [`ump`] packets.
+///
+/// [`ump`]: ump/index.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SystemEvent {
+    /// `MTC` quarter frame, message type in the upper and value in the lower nibble.
+    TimeCodeQuarterFrame(u8),
+    /// Song position in sixteenth notes.
+    SongPosition(u16),
+    SongSelect(u8),
+    TuneRequest,
+    TimingClock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    Reset,
+}
+
+impl SystemEvent {
+    /// Returns status byte.
+    pub fn status(&self) -> u8 {
+        match self {
+            SystemEvent::TimeCodeQuarterFrame(_) => 0xf1,
+            SystemEvent::SongPosition(_) => 0xf2,
+            SystemEvent::SongSelect(_) => 0xf3,
+            SystemEvent::TuneRequest => 0xf6,
+            SystemEvent::TimingClock => 0xf8,
+            SystemEvent::Start => 0xfa,
+            SystemEvent::Continue => 0xfb,
+            SystemEvent::Stop => 0xfc,
+            SystemEvent::ActiveSensing => 0xfe,
+            SystemEvent::Reset => 0xff,
+        }
+    }
+
+    /// Returns the status and data bytes and their count. Data bytes are limited to 7 bits.
+    pub fn to_bytes(&self) -> ([u8; 3], usize) {
+        let status = self.status();
+        match *self {
+            SystemEvent::TimeCodeQuarterFrame(value) | SystemEvent::SongSelect(value) => {
+                ([status, value & 0x7f, 0], 2)
+            }
+            SystemEvent::SongPosition(position) => (
+                [status, position as u8 & 0x7f, (position >> 7) as u8 & 0x7f],
+                3,
+            ),
+            _ => ([status, 0, 0], 1),
+        }
+    }
+}
+
 /// [`Event`] variant.
 ///
 /// [`Event`]: struct.Event.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum MetaEvent<'a> {
     SequenceNumber(u16),
     Text(Text<'a>),
@@ -157,6 +267,7 @@ pub enum MetaEvent<'a> {
     ChannelPrefix(u8),
     EndOfTrack,
     SetTempo(u32),
+    #[cfg_attr(feature = "serde", serde(rename = "smtpe_offset"))]
     SMTPEOffset {
         hh: u8,
         mm: u8,
@@ -181,10 +292,27 @@ pub enum MetaEvent<'a> {
     },
 }
 
+impl<'a> MetaEvent<'a> {
+    /// Returns text of the text meta events.
+    pub fn text(&self) -> Option<Text<'a>> {
+        match *self {
+            MetaEvent::Text(text)
+            | MetaEvent::CopyrightNotice(text)
+            | MetaEvent::Name(text)
+            | MetaEvent::InstrumentName(text)
+            | MetaEvent::Lyric(text)
+            | MetaEvent::Marker(text)
+            | MetaEvent::CuePoint(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
 /// [`Event`] variant.
 ///
 /// [`Event`]: struct.Event.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SysexEvent<'a> {
     F0(&'a [u8]),
     F7(&'a [u8]),
@@ -193,7 +321,9 @@ pub enum SysexEvent<'a> {
 /// [`Event`] variants.
 ///
 /// [`Event`]: struct.Event.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum EventKind<'a> {
     Midi(MidiEvent),
     Meta(MetaEvent<'a>),
@@ -201,7 +331,8 @@ pub enum EventKind<'a> {
 }
 
 /// `MTrk` event.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Event<'a> {
     pub time: u32,
     pub kind: EventKind<'a>,
@@ -210,7 +341,7 @@ pub struct Event<'a> {
 /// [`MetaEvent`] text
 ///
 /// [`MetaEvent`]: enum.MetaEvent.html
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Text<'a> {
     data: &'a [u8],
 }
@@ -224,6 +355,10 @@ impl<'a> Text<'a> {
     }
 
     /// Try to decode text as utf8.
+    ///
+    /// See [`decode`] for other encodings.
+    ///
+    /// [`decode`]: #method.decode
     pub fn as_utf8(&self) -> Result<&'a str, str::Utf8Error> {
         str::from_utf8(self.data)
     }
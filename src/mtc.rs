@@ -0,0 +1,521 @@
+//! MIDI Time Code (`MTC`) and `SMPTE` timecode.
+//!
+//! [`Timecode`] converts between seconds, frame counts, `hh:mm:ss:ff.sf` text and the `MTC`
+//! messages: eight quarter frame messages spanning two frames and the full frame system exclusive
+//! message. [`Decoder`] follows incoming messages and keeps a running timecode.
+//!
+//! `Fps30Drop` timecode counts 30 frames per second, but skips frame numbers 0 and 1 at the start
+//! of every minute except every tenth minute, so that it runs at 29.97 frames per second.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::mtc::{Decoder, Timecode};
+//! use midi::Fps;
+//!
+//! # fn foo() -> Result<(), midi::Error> {
+//! let start = Timecode::parse(Fps::Fps25, "01:00:00:00.00")?;
+//! let mut decoder = Decoder::new();
+//! for event in &start.quarter_frames() {
+//!     decoder.system_event(event);
+//! }
+//! assert!(decoder.timecode().unwrap().as_seconds() > start.as_seconds());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Timecode`]: struct.Timecode.html
+//! [`Decoder`]: struct.Decoder.html
+
+use crate::{Error, ErrorKind, Fps, MetaEvent, SystemEvent, Timing};
+use core::fmt;
+
+/// Frames per minute and per ten minutes of `Fps30Drop` timecode.
+const DROP_MINUTE: u32 = 30 * 60 - 2;
+const DROP_TEN_MINUTES: u32 = 30 * 600 - 18;
+
+/// Hours wrap at a day.
+const HOURS: u32 = 24;
+
+/// Nominal number of frame numbers per second.
+fn frame_rate(fps: Fps) -> u32 {
+    match fps {
+        Fps::Fps24 => 24,
+        Fps::Fps25 => 25,
+        Fps::Fps30Drop | Fps::Fps30NonDrop => 30,
+    }
+}
+
+/// Number of frames per day.
+fn frames_per_day(fps: Fps) -> u32 {
+    match fps {
+        Fps::Fps30Drop => DROP_TEN_MINUTES * 6 * HOURS,
+        fps => frame_rate(fps) * 3600 * HOURS,
+    }
+}
+
+/// Rate code used by `MTC` messages and `SMPTE` offset meta event.
+fn rate_code(fps: Fps) -> u8 {
+    match fps {
+        Fps::Fps24 => 0,
+        Fps::Fps25 => 1,
+        Fps::Fps30Drop => 2,
+        Fps::Fps30NonDrop => 3,
+    }
+}
+
+fn rate_fps(code: u8) -> Fps {
+    match code & 0x3 {
+        0 => Fps::Fps24,
+        1 => Fps::Fps25,
+        2 => Fps::Fps30Drop,
+        _ => Fps::Fps30NonDrop,
+    }
+}
+
+fn invalid(context: &'static str) -> Error {
+    Error {
+        context,
+        kind: ErrorKind::Invalid,
+    }
+}
+
+/// `SMPTE` timecode `hh:mm:ss:ff.sf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timecode {
+    pub fps: Fps,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    /// Fractional frame in 1/100 frame, as in `SMPTE` offset meta event.
+    pub subframes: u8,
+}
+
+impl Timecode {
+    /// Creates timecode from number of frames since `00:00:00:00`, wrapping at 24 hours.
+    pub fn from_frames(fps: Fps, frames: u32, subframes: u8) -> Self {
+        let mut frames = frames % frames_per_day(fps);
+        if fps == Fps::Fps30Drop {
+            // add dropped frame numbers
+            let tens = frames / DROP_TEN_MINUTES;
+            let rest = frames % DROP_TEN_MINUTES;
+            frames += 18 * tens;
+            if rest > 1 {
+                frames += 2 * ((rest - 2) / DROP_MINUTE);
+            }
+        }
+
+        let rate = frame_rate(fps);
+        Timecode {
+            fps,
+            hours: (frames / (rate * 3600)) as u8,
+            minutes: (frames / (rate * 60) % 60) as u8,
+            seconds: (frames / rate % 60) as u8,
+            frames: (frames % rate) as u8,
+            subframes: subframes.min(99),
+        }
+    }
+
+    /// Returns number of frames since `00:00:00:00`, ignoring subframes.
+    pub fn total_frames(self) -> u32 {
+        let minutes = u32::from(self.hours) * 60 + u32::from(self.minutes);
+        let frames = (minutes * 60 + u32::from(self.seconds)) * frame_rate(self.fps)
+            + u32::from(self.frames);
+        match self.fps {
+            Fps::Fps30Drop => frames.saturating_sub(2 * (minutes - minutes / 10)),
+            _ => frames,
+        }
+    }
+
+    /// Creates timecode from `seconds` since `00:00:00:00`, rounded to subframes. Negative
+    /// `seconds` are clamped to zero.
+    pub fn from_seconds(fps: Fps, seconds: f64) -> Self {
+        let subframes = (seconds * fps.as_f64() * 100.0 + 0.5) as u64;
+        let frames = (subframes / 100 % u64::from(frames_per_day(fps))) as u32;
+        Timecode::from_frames(fps, frames, (subframes % 100) as u8)
+    }
+
+    /// Returns number of seconds since `00:00:00:00`.
+    pub fn as_seconds(self) -> f64 {
+        let frames = f64::from(self.total_frames()) + f64::from(self.subframes) / 100.0;
+        frames / self.fps.as_f64()
+    }
+
+    /// Creates timecode from absolute time in ticks of [`Timing::Timecode`]. Returns `None` for
+    /// metrical timing.
+    ///
+    /// [`Timing::Timecode`]: ../enum.Timing.html#variant.Timecode
+    pub fn from_ticks(timing: Timing, ticks: u64) -> Option<Self> {
+        match timing {
+            Timing::Timecode { fps, subframe } if subframe > 0 => {
+                let subframe = u64::from(subframe);
+                let frames = (ticks / subframe % u64::from(frames_per_day(fps))) as u32;
+                let subframes = (ticks % subframe * 100 / subframe) as u8;
+                Some(Timecode::from_frames(fps, frames, subframes))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses `hh:mm:ss:ff` with optional `.sf` subframes. Frames may also be separated by `;`,
+    /// which is common for drop-frame timecode.
+    pub fn parse(fps: Fps, text: &str) -> Result<Self, Error> {
+        let (text, subframes) = match text.split_once('.') {
+            Some((text, subframes)) => (text, Some(subframes)),
+            None => (text, None),
+        };
+        let mut fields = [0u8; 4];
+        let mut parts = text.split([':', ';']);
+        for field in &mut fields {
+            let part = parts
+                .next()
+                .ok_or_else(|| invalid("Timecode::parse: expected hh:mm:ss:ff"))?;
+            *field = part
+                .trim()
+                .parse()
+                .map_err(|_| invalid("Timecode::parse: invalid number"))?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid("Timecode::parse: expected hh:mm:ss:ff"));
+        }
+        let subframes = match subframes {
+            Some(subframes) => subframes
+                .trim()
+                .parse()
+                .map_err(|_| invalid("Timecode::parse: invalid subframes"))?,
+            None => 0,
+        };
+
+        let [hours, minutes, seconds, frames] = fields;
+        let timecode = Timecode {
+            fps,
+            hours,
+            minutes,
+            seconds,
+            frames,
+            subframes,
+        };
+        if timecode.is_valid() {
+            Ok(timecode)
+        } else {
+            Err(invalid("Timecode::parse: timecode out of range"))
+        }
+    }
+
+    /// Returns `true` if all fields are in range, including frame numbers skipped by
+    /// `Fps30Drop`.
+    // `u8::is_multiple_of` requires Rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn is_valid(&self) -> bool {
+        let dropped = self.fps == Fps::Fps30Drop
+            && self.seconds == 0
+            && self.frames < 2
+            && self.minutes % 10 != 0;
+        u32::from(self.hours) < HOURS
+            && self.minutes < 60
+            && self.seconds < 60
+            && u32::from(self.frames) < frame_rate(self.fps)
+            && self.subframes < 100
+            && !dropped
+    }
+
+    /// Creates timecode from [`MetaEvent::SMTPEOffset`], with frame rate in bits 5 and 6 of the
+    /// hour. Returns `None` for other events.
+    ///
+    /// [`MetaEvent::SMTPEOffset`]: ../enum.MetaEvent.html#variant.SMTPEOffset
+    pub fn from_smtpe_offset(event: &MetaEvent) -> Option<Self> {
+        match *event {
+            MetaEvent::SMTPEOffset { hh, mm, ss, fr, ff } => Some(Timecode {
+                fps: rate_fps(hh >> 5),
+                hours: hh & 0x1f,
+                minutes: mm,
+                seconds: ss,
+                frames: fr,
+                subframes: ff,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns [`MetaEvent::SMTPEOffset`] for the timecode.
+    ///
+    /// [`MetaEvent::SMTPEOffset`]: ../enum.MetaEvent.html#variant.SMTPEOffset
+    pub fn smtpe_offset(self) -> MetaEvent<'static> {
+        MetaEvent::SMTPEOffset {
+            hh: rate_code(self.fps) << 5 | self.hours & 0x1f,
+            mm: self.minutes,
+            ss: self.seconds,
+            fr: self.frames,
+            ff: self.subframes,
+        }
+    }
+
+    /// Returns the eight quarter frame messages for the timecode, sent over two frames starting
+    /// at the timecode. Subframes are ignored.
+    pub fn quarter_frames(self) -> [SystemEvent; 8] {
+        let hours = rate_code(self.fps) << 5 | self.hours & 0x1f;
+        let values = [self.frames, self.seconds, self.minutes, hours];
+        let mut events = [SystemEvent::TimeCodeQuarterFrame(0); 8];
+        for (piece, event) in events.iter_mut().enumerate() {
+            let value = values[piece / 2];
+            let nibble = if piece % 2 == 0 {
+                value & 0xf
+            } else {
+                value >> 4
+            };
+            *event = SystemEvent::TimeCodeQuarterFrame((piece as u8) << 4 | nibble & 0xf);
+        }
+        events
+    }
+
+    /// Returns full frame system exclusive message, starting with `0xf0` and ending with `0xf7`.
+    /// Subframes are ignored.
+    pub fn full_frame(self) -> [u8; 10] {
+        let hours = rate_code(self.fps) << 5 | self.hours & 0x1f;
+        [
+            0xf0,
+            0x7f,
+            0x7f,
+            0x01,
+            0x01,
+            hours,
+            self.minutes,
+            self.seconds,
+            self.frames,
+            0xf7,
+        ]
+    }
+
+    /// Advances timecode by `subframes`, wrapping at 24 hours.
+    fn advance(self, subframes: u32) -> Self {
+        let subframes =
+            u64::from(self.total_frames()) * 100 + u64::from(self.subframes) + u64::from(subframes);
+        let frames = (subframes / 100 % u64::from(frames_per_day(self.fps))) as u32;
+        Timecode::from_frames(self.fps, frames, (subframes % 100) as u8)
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}:{:02}.{:02}",
+            self.hours, self.minutes, self.seconds, self.frames, self.subframes
+        )
+    }
+}
+
+/// Decodes incoming `MTC` messages into a running timecode.
+///
+/// A complete sequence of eight quarter frame messages, or a full frame message, sets the
+/// timecode. Every following quarter frame message advances it by a quarter frame.
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    pieces: [u8; 8],
+    /// Number of consecutive pieces received, starting from piece 0.
+    received: u8,
+    last: Option<u8>,
+    timecode: Option<Timecode>,
+}
+
+impl Decoder {
+    /// Creates decoder without timecode.
+    pub fn new() -> Self {
+        Decoder::default()
+    }
+
+    /// Returns current timecode, if known.
+    pub fn timecode(&self) -> Option<Timecode> {
+        self.timecode
+    }
+
+    /// Handles quarter frame message `value` and returns current timecode.
+    pub fn quarter_frame(&mut self, value: u8) -> Option<Timecode> {
+        let piece = value >> 4 & 0x7;
+        self.pieces[usize::from(piece)] = value & 0xf;
+
+        let follows = self.last == Some(piece.wrapping_sub(1) & 0x7);
+        if piece == 0 {
+            self.received = 1;
+        } else if follows && self.received == piece {
+            self.received += 1;
+        } else {
+            self.received = 0;
+        }
+        self.last = Some(piece);
+
+        if self.received == 8 {
+            let [frames, seconds, minutes, hours] =
+                [0, 2, 4, 6].map(|piece| self.pieces[piece] | self.pieces[piece + 1] << 4);
+            let timecode = Timecode {
+                fps: rate_fps(hours >> 5),
+                hours: hours & 0x1f,
+                minutes: minutes & 0x3f,
+                seconds: seconds & 0x3f,
+                frames: frames & 0x1f,
+                subframes: 0,
+            };
+            // the last piece arrives 7 quarter frames after the encoded timecode
+            self.timecode = Some(timecode.advance(175));
+            self.received = 0;
+        } else if follows {
+            self.timecode = self.timecode.map(|timecode| timecode.advance(25));
+        }
+        self.timecode
+    }
+
+    /// Handles [`SystemEvent::TimeCodeQuarterFrame`] and ignores other events. Returns current
+    /// timecode.
+    ///
+    /// [`SystemEvent::TimeCodeQuarterFrame`]: ../enum.SystemEvent.html#variant.TimeCodeQuarterFrame
+    pub fn system_event(&mut self, event: &SystemEvent) -> Option<Timecode> {
+        if let SystemEvent::TimeCodeQuarterFrame(value) = *event {
+            self.quarter_frame(value)
+        } else {
+            self.timecode
+        }
+    }
+
+    /// Handles full frame system exclusive message `data`, with or without leading `0xf0`, and
+    /// ignores other messages. Returns current timecode.
+    pub fn sysex(&mut self, data: &[u8]) -> Option<Timecode> {
+        let data = data.strip_prefix(&[0xf0]).unwrap_or(data);
+        if let [0x7f, _, 0x01, 0x01, hours, minutes, seconds, frames, ..] = *data {
+            self.timecode = Some(Timecode {
+                fps: rate_fps(hours >> 5),
+                hours: hours & 0x1f,
+                minutes,
+                seconds,
+                frames,
+                subframes: 0,
+            });
+            self.received = 0;
+            self.last = None;
+        }
+        self.timecode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, Timecode};
+    use crate::{Fps, MetaEvent, SystemEvent, Timing};
+
+    fn timecode(fps: Fps, hours: u8, minutes: u8, seconds: u8, frames: u8) -> Timecode {
+        Timecode {
+            fps,
+            hours,
+            minutes,
+            seconds,
+            frames,
+            subframes: 0,
+        }
+    }
+
+    #[test]
+    fn test_drop_frame() {
+        let fps = Fps::Fps30Drop;
+        assert_eq!(
+            Timecode::from_frames(fps, 1799, 0),
+            timecode(fps, 0, 0, 59, 29)
+        );
+        assert_eq!(
+            Timecode::from_frames(fps, 1800, 0),
+            timecode(fps, 0, 1, 0, 2)
+        );
+        assert_eq!(
+            Timecode::from_frames(fps, 17982, 0),
+            timecode(fps, 0, 10, 0, 0)
+        );
+        assert_eq!(
+            Timecode::from_frames(fps, 17981, 0),
+            timecode(fps, 0, 9, 59, 29)
+        );
+
+        for frames in (0..200_000).step_by(7) {
+            let timecode = Timecode::from_frames(fps, frames, 0);
+            assert!(timecode.is_valid(), "{}", timecode);
+            assert_eq!(timecode.total_frames(), frames);
+        }
+
+        // one hour of drop-frame timecode is one hour of real time
+        let hour = timecode(fps, 1, 0, 0, 0);
+        assert!((hour.as_seconds() - 3600.0).abs() < 0.01);
+        assert!(!timecode(fps, 0, 1, 0, 1).is_valid());
+        assert!(timecode(fps, 0, 10, 0, 1).is_valid());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let timecode = Timecode::from_seconds(Fps::Fps25, 3661.5);
+        assert_eq!(
+            Timecode::parse(Fps::Fps25, "01:01:01:12.50").unwrap(),
+            timecode
+        );
+        assert_eq!(timecode.as_seconds(), 3661.5);
+        assert!(Timecode::parse(Fps::Fps25, "01:01:01:25").is_err());
+        assert!(Timecode::parse(Fps::Fps25, "01:01:01").is_err());
+        assert_eq!(
+            Timecode::parse(Fps::Fps30Drop, "00:10:00;00")
+                .unwrap()
+                .total_frames(),
+            17982
+        );
+
+        let offset = MetaEvent::SMTPEOffset {
+            hh: 0x41,
+            mm: 2,
+            ss: 3,
+            fr: 4,
+            ff: 5,
+        };
+        let timecode = Timecode::from_smtpe_offset(&offset).unwrap();
+        assert_eq!(timecode.fps, Fps::Fps30Drop);
+        assert_eq!(timecode.hours, 1);
+        assert_eq!(timecode.smtpe_offset(), offset);
+
+        let timing = Timing::Timecode {
+            fps: Fps::Fps25,
+            subframe: 40,
+        };
+        assert_eq!(
+            Timecode::from_ticks(timing, 25 * 40 + 20),
+            Timecode::parse(Fps::Fps25, "00:00:01:00.50").ok()
+        );
+        assert_eq!(Timecode::from_ticks(Timing::Metrical(96), 0), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_display() {
+        let timecode = Timecode::from_seconds(Fps::Fps25, 3661.5);
+        assert_eq!(timecode.to_string(), "01:01:01:12.50");
+    }
+
+    #[test]
+    fn test_decoder() {
+        let start = timecode(Fps::Fps30NonDrop, 23, 59, 59, 28);
+        let events = start.quarter_frames();
+        assert_eq!(events[6], SystemEvent::TimeCodeQuarterFrame(0x67));
+        assert_eq!(events[7], SystemEvent::TimeCodeQuarterFrame(0x77));
+
+        let mut decoder = Decoder::new();
+        for event in &events[..7] {
+            assert_eq!(decoder.system_event(event), None);
+        }
+        let parse = |text| Timecode::parse(Fps::Fps30NonDrop, text).ok();
+        assert_eq!(decoder.system_event(&events[7]), parse("23:59:59:29.75"));
+        let current = decoder.quarter_frame(0x00).unwrap();
+        assert_eq!(Some(current), parse("00:00:00:00.00"));
+
+        // out of sequence pieces do not advance the timecode
+        assert_eq!(decoder.quarter_frame(0x50), Some(current));
+
+        let full_frame = timecode(Fps::Fps25, 10, 20, 30, 12).full_frame();
+        let current = decoder.sysex(&full_frame[1..]).unwrap();
+        assert_eq!(current, timecode(Fps::Fps25, 10, 20, 30, 12));
+    }
+}
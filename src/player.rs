@@ -0,0 +1,613 @@
+//! Real-time playback of `SMF`.
+//!
+//! [`Player`] sends [`MidiEvent`]s of all tracks to an [`Output`] when their time comes
+//! according to a [`Clock`]. It does not block nor spawn threads, the application calls
+//! [`Player::update`] and sleeps for the returned duration. With `std` feature, [`SystemClock`]
+//! can be used as the clock.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::player::{Clock, Player};
+//!
+//! # fn play(bytes: &[u8], clock: impl Clock) -> Result<(), midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let output = |event: midi::MidiEvent| println!("{:?}", event);
+//! let mut player = Player::new(&smf, clock, output);
+//! player.play();
+//! while let Some(wait) = player.update() {
+//!     std::thread::sleep(std::time::Duration::from_secs_f64(wait));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Player`]: struct.Player.html
+//! [`Player::update`]: struct.Player.html#method.update
+//! [`MidiEvent`]: ../struct.MidiEvent.html
+//! [`Output`]: trait.Output.html
+//! [`Clock`]: trait.Clock.html
+//! [`SystemClock`]: struct.SystemClock.html
+
+use crate::{ChannelState, EventKind, MidiEvent, MidiEventKind, Smf, TempoMap};
+use alloc::vec::Vec;
+
+/// Source of monotonic time.
+pub trait Clock {
+    /// Returns current time in seconds.
+    fn now(&self) -> f64;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> f64 {
+        (**self).now()
+    }
+}
+
+/// [`Clock`] measuring time elapsed since its creation.
+///
+/// [`Clock`]: trait.Clock.html
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    /// Creates new [`SystemClock`] starting now.
+    ///
+    /// [`SystemClock`]: struct.SystemClock.html
+    pub fn new() -> Self {
+        SystemClock {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+/// Destination of played events, e.g. a midi port or a synthesizer.
+pub trait Output {
+    /// Sends `event` now.
+    fn send(&mut self, event: MidiEvent);
+}
+
+impl Output for Vec<MidiEvent> {
+    fn send(&mut self, event: MidiEvent) {
+        self.push(event)
+    }
+}
+
+impl<F> Output for F
+where
+    F: FnMut(MidiEvent),
+{
+    fn send(&mut self, event: MidiEvent) {
+        self(event)
+    }
+}
+
+/// Event of the merged event stream.
+#[derive(Debug, Clone, Copy)]
+struct Scheduled {
+    tick: u64,
+    seconds: f64,
+    track: usize,
+    event: MidiEvent,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Paused { seconds: f64 },
+    Playing { clock: f64, seconds: f64 },
+}
+
+/// Player of [`Smf`].
+///
+/// Position, loop region and seek target are in song time, which runs [`speed`] times faster
+/// than the [`Clock`]. When position reaches the end of the loop region, sounding notes are
+/// released and playback continues from the start of the region. Muted tracks do not play notes,
+/// but their other events are still sent, so the tracks sound right when they are unmuted.
+///
+/// [`Smf`]: ../struct.Smf.html
+/// [`Clock`]: trait.Clock.html
+/// [`speed`]: #method.set_speed
+#[derive(Debug)]
+pub struct Player<C, O> {
+    events: Vec<Scheduled>,
+    tempo_map: TempoMap,
+    duration: f64,
+    clock: C,
+    output: O,
+    transport: Transport,
+    /// Index of the next event to send.
+    next: usize,
+    speed: f64,
+    loop_region: Option<(u64, u64)>,
+    muted: Vec<bool>,
+    solo: Vec<bool>,
+    /// Notes sent and not released yet, as `(track, channel, key)`.
+    sounding: Vec<(usize, u8, u8)>,
+    /// Bit mask of channels used by the song.
+    channels: u16,
+}
+
+impl<C: Clock, O: Output> Player<C, O> {
+    /// Creates new paused [`Player`] of the `smf` at the beginning.
+    ///
+    /// [`Player`]: struct.Player.html
+    pub fn new(smf: &Smf, clock: C, output: O) -> Self {
+        let tempo_map = TempoMap::new(smf);
+        let mut events = Vec::new();
+        let mut end = 0;
+        for (track, events_of_track) in smf.tracks.iter().enumerate() {
+            for (tick, event) in events_of_track.absolute_iter() {
+                end = end.max(tick);
+                if let EventKind::Midi(event) = event.kind {
+                    events.push(Scheduled {
+                        tick,
+                        seconds: tempo_map.seconds(tick),
+                        track,
+                        event,
+                    });
+                }
+            }
+        }
+        // stable sort keeps the order of tracks
+        events.sort_by_key(|scheduled| scheduled.tick);
+        let channels = events.iter().fold(0, |channels, scheduled| {
+            channels | 1 << (scheduled.event.channel & 0x0f)
+        });
+
+        Player {
+            events,
+            duration: tempo_map.seconds(end),
+            tempo_map,
+            clock,
+            output,
+            transport: Transport::Paused { seconds: 0.0 },
+            next: 0,
+            speed: 1.0,
+            loop_region: None,
+            muted: vec![false; smf.tracks.len()],
+            solo: vec![false; smf.tracks.len()],
+            sounding: Vec::new(),
+            channels,
+        }
+    }
+
+    /// Returns the clock.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns the output.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Returns mutable output.
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    /// Returns length of the song in seconds.
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Returns current position in seconds.
+    pub fn position(&self) -> f64 {
+        match self.transport {
+            Transport::Paused { seconds } => seconds,
+            Transport::Playing { clock, seconds } => {
+                seconds + (self.clock.now() - clock) * self.speed
+            }
+        }
+    }
+
+    /// Returns current position in ticks.
+    pub fn tick(&self) -> u64 {
+        self.tempo_map.tick(self.position())
+    }
+
+    /// Returns `true` if the player is playing.
+    pub fn is_playing(&self) -> bool {
+        matches!(self.transport, Transport::Playing { .. })
+    }
+
+    /// Starts or resumes playback from the current position.
+    pub fn play(&mut self) {
+        if let Transport::Paused { seconds } = self.transport {
+            self.transport = Transport::Playing {
+                clock: self.clock.now(),
+                seconds,
+            };
+        }
+    }
+
+    /// Pauses playback and releases sounding notes.
+    pub fn pause(&mut self) {
+        self.transport = Transport::Paused {
+            seconds: self.position(),
+        };
+        self.release(|_| true);
+    }
+
+    /// Moves to `seconds`, releases sounding notes and sends events restoring the
+    /// [`ChannelState`] at the new position.
+    ///
+    /// Every channel used by the song is reset first with [`MidiEventKind::ResetAllControllers`]
+    /// and centered pitch bend, so values set later in the song do not stay in effect.
+    ///
+    /// [`ChannelState`]: ../struct.ChannelState.html
+    /// [`MidiEventKind::ResetAllControllers`]: ../enum.MidiEventKind.html#variant.ResetAllControllers
+    pub fn seek(&mut self, seconds: f64) {
+        let seconds = seconds.max(0.0);
+        self.release(|_| true);
+        self.next = self.events.partition_point(|event| event.seconds < seconds);
+
+        let channels = self.channels;
+        for channel in (0..16).filter(|channel| channels & 1 << channel != 0) {
+            let resets = [
+                MidiEventKind::ResetAllControllers,
+                MidiEventKind::PitchBend { lsb: 0, msb: 0x40 },
+            ];
+            for kind in resets {
+                self.output.send(MidiEvent { channel, kind });
+            }
+        }
+
+        let mut state = ChannelState::new();
+        for scheduled in &self.events[..self.next] {
+            state.update(scheduled.event);
+        }
+        for event in state.events() {
+            self.output.send(event);
+        }
+
+        self.transport = match self.transport {
+            Transport::Paused { .. } => Transport::Paused { seconds },
+            Transport::Playing { .. } => Transport::Playing {
+                clock: self.clock.now(),
+                seconds,
+            },
+        };
+    }
+
+    /// Moves to `tick`, see [`seek`].
+    ///
+    /// [`seek`]: #method.seek
+    pub fn seek_tick(&mut self, tick: u64) {
+        self.seek(self.tempo_map.seconds(tick))
+    }
+
+    /// Plays the song `speed` times faster, e.g. `0.5` is half speed.
+    pub fn set_speed(&mut self, speed: f64) {
+        if let Transport::Playing { .. } = self.transport {
+            self.transport = Transport::Playing {
+                clock: self.clock.now(),
+                seconds: self.position(),
+            };
+        }
+        if speed.is_finite() && speed > 0.0 {
+            self.speed = speed;
+        }
+    }
+
+    /// Sets region between `start` and `end` ticks which is played repeatedly, or disables
+    /// looping.
+    pub fn set_loop(&mut self, region: Option<(u64, u64)>) {
+        self.loop_region = region.filter(|(start, end)| start < end);
+    }
+
+    /// Mutes or unmutes `track`. Notes of the muted track are released.
+    pub fn set_mute(&mut self, track: usize, mute: bool) {
+        if let Some(muted) = self.muted.get_mut(track) {
+            *muted = mute;
+            self.release_inaudible();
+        }
+    }
+
+    /// Solos or unsolos `track`. When any track is soloed, only soloed tracks play notes.
+    pub fn set_solo(&mut self, track: usize, solo: bool) {
+        if let Some(soloed) = self.solo.get_mut(track) {
+            *soloed = solo;
+            self.release_inaudible();
+        }
+    }
+
+    fn is_audible(&self, track: usize) -> bool {
+        !self.muted[track] && (self.solo[track] || !self.solo.contains(&true))
+    }
+
+    fn release_inaudible(&mut self) {
+        let audible = (0..self.muted.len())
+            .map(|track| self.is_audible(track))
+            .collect::<Vec<_>>();
+        self.release(|track| !audible[track]);
+    }
+
+    /// Releases sounding notes of tracks matching `filter`.
+    fn release<F: Fn(usize) -> bool>(&mut self, filter: F) {
+        let output = &mut self.output;
+        self.sounding.retain(|&(track, channel, key)| {
+            if !filter(track) {
+                return true;
+            }
+            output.send(MidiEvent {
+                channel,
+                kind: MidiEventKind::NoteOff { key, velocity: 64 },
+            });
+            false
+        });
+    }
+
+    fn send(&mut self, scheduled: Scheduled) {
+        let MidiEvent { channel, kind } = scheduled.event;
+        match kind {
+            MidiEventKind::NoteOn { key, velocity } if velocity > 0 => {
+                if !self.is_audible(scheduled.track) {
+                    return;
+                }
+                self.sounding.push((scheduled.track, channel, key));
+            }
+            MidiEventKind::NoteOn { key, .. } | MidiEventKind::NoteOff { key, .. } => {
+                let note = (scheduled.track, channel, key);
+                match self.sounding.iter().position(|&sounding| sounding == note) {
+                    Some(index) => {
+                        self.sounding.remove(index);
+                    }
+                    // note of muted track
+                    None => return,
+                }
+            }
+            _ => {}
+        }
+        self.output.send(scheduled.event);
+    }
+
+    /// Sends all events due at the current position.
+    ///
+    /// Returns time in seconds of the [`Clock`] until the next event is due, or `None` if the
+    /// player is paused or reached the end of the song.
+    ///
+    /// [`Clock`]: trait.Clock.html
+    pub fn update(&mut self) -> Option<f64> {
+        if !self.is_playing() {
+            return None;
+        }
+
+        loop {
+            let position = self.position();
+            let loop_region = self
+                .loop_region
+                .map(|(start, end)| (self.tempo_map.seconds(start), end));
+            let end = loop_region.map(|(_, end)| end);
+
+            while let Some(&scheduled) = self.events.get(self.next) {
+                let past_end = matches!(end, Some(end) if scheduled.tick >= end);
+                if scheduled.seconds > position || past_end {
+                    break;
+                }
+                self.next += 1;
+                self.send(scheduled);
+            }
+
+            let (start, end) = match loop_region {
+                Some((start, end)) => (start, self.tempo_map.seconds(end)),
+                None => break,
+            };
+            if position < end {
+                break;
+            }
+            // events between the loop start and the overshoot are sent late rather than skipped
+            let overshoot = (position - end) % (end - start);
+            self.seek(start);
+            self.transport = Transport::Playing {
+                clock: self.clock.now(),
+                seconds: start + overshoot,
+            };
+        }
+
+        let position = self.position();
+        let loop_end = self.loop_region.map(|(_, end)| self.tempo_map.seconds(end));
+        let next = match (self.events.get(self.next), loop_end) {
+            (Some(scheduled), Some(end)) => scheduled.seconds.min(end),
+            (Some(scheduled), None) => scheduled.seconds,
+            (None, Some(end)) => end,
+            (None, None) if position < self.duration => self.duration,
+            (None, None) => {
+                self.pause();
+                return None;
+            }
+        };
+        Some(((next - position) / self.speed).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, Player};
+    use crate::test_util::{end_of_track, midi, smf as test_smf};
+    use crate::{Format, MidiEvent, MidiEventKind, Smf};
+    use core::cell::Cell;
+
+    #[derive(Default)]
+    struct MockClock(Cell<f64>);
+
+    impl Clock for MockClock {
+        fn now(&self) -> f64 {
+            self.0.get()
+        }
+    }
+
+    fn note_on(key: u8) -> MidiEventKind {
+        MidiEventKind::NoteOn { key, velocity: 64 }
+    }
+
+    fn note_off(key: u8) -> MidiEventKind {
+        MidiEventKind::NoteOn { key, velocity: 0 }
+    }
+
+    fn released(key: u8) -> MidiEventKind {
+        MidiEventKind::NoteOff { key, velocity: 64 }
+    }
+
+    /// Two tracks at 120 bpm and 96 ppqn, so 96 ticks is half a second.
+    fn smf() -> Smf<'static> {
+        test_smf(
+            Format::MultiTrack,
+            96,
+            vec![
+                vec![
+                    midi(0, 0, MidiEventKind::ProgramChange(1)),
+                    midi(0, 0, note_on(60)),
+                    midi(96, 0, note_off(60)),
+                    midi(0, 0, note_on(62)),
+                    midi(96, 0, note_off(62)),
+                    end_of_track(0),
+                ],
+                vec![
+                    midi(48, 1, note_on(36)),
+                    midi(48, 1, note_off(36)),
+                    end_of_track(0),
+                ],
+            ],
+        )
+    }
+
+    /// Events resetting channels 0 and 1 used by [`smf`].
+    fn resets() -> Vec<(u8, MidiEventKind)> {
+        (0..2)
+            .flat_map(|channel| {
+                vec![
+                    (channel, MidiEventKind::ResetAllControllers),
+                    (channel, MidiEventKind::PitchBend { lsb: 0, msb: 0x40 }),
+                ]
+            })
+            .collect()
+    }
+
+    fn sent(player: &mut Player<&MockClock, Vec<MidiEvent>>) -> Vec<(u8, MidiEventKind)> {
+        player
+            .output_mut()
+            .drain(..)
+            .map(|event| (event.channel, event.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_play_pause_and_seek() {
+        let clock = MockClock::default();
+        let smf = smf();
+        let mut player = Player::new(&smf, &clock, Vec::new());
+        assert_eq!(player.duration(), 1.0);
+        assert_eq!(player.update(), None);
+
+        player.play();
+        assert_eq!(player.update(), Some(0.25));
+        assert_eq!(
+            sent(&mut player),
+            vec![(0, MidiEventKind::ProgramChange(1)), (0, note_on(60))]
+        );
+
+        clock.0.set(0.3);
+        assert_eq!(player.update(), Some(0.2));
+        assert_eq!(sent(&mut player), vec![(1, note_on(36))]);
+
+        player.pause();
+        assert_eq!(
+            sent(&mut player),
+            vec![(0, released(60)), (1, released(36))]
+        );
+        clock.0.set(10.0);
+        player.play();
+        assert_eq!(player.position(), 0.3);
+
+        player.seek(0.75);
+        let mut expected = resets();
+        expected.push((0, MidiEventKind::ProgramChange(1)));
+        assert_eq!(sent(&mut player), expected);
+        clock.0.set(10.25);
+        assert_eq!(player.update(), None);
+        assert_eq!(sent(&mut player), vec![]);
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn test_seek_backwards() {
+        let clock = MockClock::default();
+        let bend = MidiEventKind::PitchBend { lsb: 0, msb: 0x60 };
+        let smf = test_smf(
+            Format::MultiTrack,
+            96,
+            vec![vec![
+                midi(0, 3, MidiEventKind::ProgramChange(5)),
+                midi(96, 3, bend),
+                midi(96, 3, note_on(60)),
+            ]],
+        );
+        let mut player = Player::new(&smf, &clock, Vec::new());
+        player.play();
+        clock.0.set(0.75);
+        player.update();
+        assert_eq!(sent(&mut player).last(), Some(&(3, bend)));
+
+        // the pitch bend set later in the song is reset
+        player.seek(0.25);
+        assert_eq!(
+            sent(&mut player),
+            vec![
+                (3, MidiEventKind::ResetAllControllers),
+                (3, MidiEventKind::PitchBend { lsb: 0, msb: 0x40 }),
+                (3, MidiEventKind::ProgramChange(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_speed_loop_and_mute() {
+        let clock = MockClock::default();
+        let smf = smf();
+        let mut player = Player::new(&smf, &clock, Vec::new());
+        player.set_speed(2.0);
+        player.set_loop(Some((0, 96)));
+        player.set_mute(1, true);
+        player.play();
+
+        clock.0.set(0.125);
+        assert_eq!(player.update(), Some(0.125));
+        assert_eq!(
+            sent(&mut player),
+            vec![(0, MidiEventKind::ProgramChange(1)), (0, note_on(60))]
+        );
+
+        // loop end at 0.5 seconds of song time
+        clock.0.set(0.3);
+        player.update();
+        let mut expected = vec![(0, released(60))];
+        expected.extend(resets());
+        expected.extend(vec![(0, MidiEventKind::ProgramChange(1)), (0, note_on(60))]);
+        assert_eq!(sent(&mut player), expected);
+        assert!((player.position() - 0.1).abs() < 1e-9);
+
+        player.set_solo(1, true);
+        player.set_mute(1, false);
+        assert_eq!(sent(&mut player), vec![(0, released(60))]);
+    }
+}
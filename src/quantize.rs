@@ -0,0 +1,359 @@
+//! Quantization of note timing.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::quantize::{Grid, Quantize};
+//!
+//! # fn quantize(bytes: &[u8]) -> Result<(), midi::Error> {
+//! let mut smf = midi::Smf::read(bytes)?;
+//! Quantize::new(Grid::new(16))
+//!     .strength(0.8)
+//!     .swing(0.2)
+//!     .apply(&mut smf)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Error, ErrorKind, EventKind, MetaEvent, MidiEvent, MidiEventKind, Smf, Timing, Track};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// Grid of note positions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    division: u16,
+    triplet: bool,
+}
+
+impl Grid {
+    /// Creates grid of `1/division` notes, e.g. `Grid::new(16)` for sixteenth notes.
+    pub fn new(division: u16) -> Self {
+        Grid {
+            division: division.max(1),
+            triplet: false,
+        }
+    }
+
+    /// Creates grid of `1/division` triplet notes, e.g. `Grid::triplet(8)` for eighth note
+    /// triplets.
+    pub fn triplet(division: u16) -> Self {
+        Grid {
+            division: division.max(1),
+            triplet: true,
+        }
+    }
+
+    /// Returns the length of a grid step in ticks for the given pulses per quarter note.
+    pub fn ticks(&self, ppqn: u16) -> f64 {
+        let ticks = f64::from(ppqn) * 4.0 / f64::from(self.division);
+        if self.triplet {
+            ticks * 2.0 / 3.0
+        } else {
+            ticks
+        }
+    }
+}
+
+/// Quantization of note starts and optionally note ends to a [`Grid`].
+///
+/// Notes are paired using [`Track::notes`]. Note off events move together with note on
+/// events, so the note length is kept, unless note ends are quantized as well. Other events
+/// keep their time and their relative order.
+///
+/// Note starts are not moved before earlier non-note events of their channel, so that e.g. a
+/// program change still applies to the following notes. A note ending after the quantized start
+/// of the next note with the same key and channel is shortened to end there.
+///
+/// [`Grid`]: struct.Grid.html
+/// [`Track::notes`]: ../struct.Track.html#method.notes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantize {
+    grid: Grid,
+    strength: f64,
+    swing: f64,
+    window: f64,
+    ends: bool,
+}
+
+impl Quantize {
+    /// Creates new [`Quantize`] moving all note starts exactly to the `grid`.
+    ///
+    /// [`Quantize`]: struct.Quantize.html
+    pub fn new(grid: Grid) -> Self {
+        Quantize {
+            grid,
+            strength: 1.0,
+            swing: 0.0,
+            window: 1.0,
+            ends: false,
+        }
+    }
+
+    /// Sets how far notes are moved towards the grid, from `0.0` (not at all) to `1.0`
+    /// (exactly to the grid).
+    pub fn strength(mut self, strength: f64) -> Self {
+        self.strength = strength.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delays every other grid position by `swing` times the grid step, from `0.0` (straight)
+    /// to `1.0`. Swing of `1/3` of eighth notes grid gives triplet feel.
+    pub fn swing(mut self, swing: f64) -> Self {
+        self.swing = swing.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Only notes closer to the grid than `window` times the grid step are moved.
+    pub fn window(mut self, window: f64) -> Self {
+        self.window = window.max(0.0);
+        self
+    }
+
+    /// Quantizes note ends as well.
+    pub fn ends(mut self, ends: bool) -> Self {
+        self.ends = ends;
+        self
+    }
+
+    /// Returns quantized absolute `tick`.
+    pub fn tick(&self, tick: u64, ppqn: u16) -> u64 {
+        let step = self.grid.ticks(ppqn);
+        if step <= 0.0 {
+            return tick;
+        }
+
+        let time = tick as f64;
+        let position = |index: f64| {
+            let swing = if index % 2.0 == 1.0 { self.swing } else { 0.0 };
+            (index + swing) * step
+        };
+        let index = (time / step).floor();
+        let target = [index - 1.0, index, index + 1.0]
+            .iter()
+            .filter(|&&index| index >= 0.0)
+            .map(|&index| position(index))
+            .fold(f64::NAN, |nearest, position| {
+                if nearest.is_nan() || (position - time).abs() < (nearest - time).abs() {
+                    position
+                } else {
+                    nearest
+                }
+            });
+
+        let distance = target - time;
+        if distance.abs() > self.window * step {
+            return tick;
+        }
+        (time + distance * self.strength).round().max(0.0) as u64
+    }
+
+    /// Quantizes notes of the `track` using `ppqn` pulses per quarter note.
+    pub fn apply_track(&self, track: &mut Track, ppqn: u16) {
+        let mut ticks = Vec::with_capacity(track.events.len());
+        // the earliest tick every event may move to, the last non-note event of its channel
+        let mut floors = Vec::with_capacity(track.events.len());
+        let mut channel_floors = [0; 16];
+        for (tick, event) in track.absolute_iter() {
+            ticks.push(tick);
+            floors.push(match event.kind {
+                EventKind::Midi(MidiEvent { channel, kind }) => {
+                    let floor = &mut channel_floors[usize::from(channel & 0x0f)];
+                    match kind {
+                        MidiEventKind::NoteOn { .. } | MidiEventKind::NoteOff { .. } => {}
+                        _ => *floor = tick,
+                    }
+                    *floor
+                }
+                _ => 0,
+            });
+        }
+
+        let mut notes = track.notes();
+        for note in &notes {
+            let start = self.tick(note.start, ppqn).max(floors[note.on]);
+            ticks[note.on] = start;
+            if let Some(off) = note.off {
+                let length = note.end - note.start;
+                let end = if self.ends {
+                    self.tick(note.end, ppqn)
+                } else {
+                    start + length
+                };
+                // keep the length of notes which would be shortened to nothing
+                ticks[off] = if end > start { end } else { start + length };
+            }
+        }
+
+        // end a note before the next note of the same key starts
+        notes.sort_by_key(|note| note.on);
+        let mut previous = BTreeMap::new();
+        for note in &notes {
+            if let Some((on, Some(off))) =
+                previous.insert((note.channel & 0x0f, note.key), (note.on, note.off))
+            {
+                if off < note.on && ticks[off] > ticks[note.on] {
+                    ticks[off] = ticks[note.on].max(ticks[on]);
+                }
+            }
+        }
+
+        let end = ticks.iter().copied().max().unwrap_or(0);
+        let mut events: Vec<_> = ticks
+            .into_iter()
+            .zip(track.events.iter().copied())
+            .map(|(tick, event)| match event.kind {
+                EventKind::Meta(MetaEvent::EndOfTrack) => (end, event),
+                _ => (tick, event),
+            })
+            .collect();
+        // stable sort keeps the order of events at the same time
+        events.sort_by_key(|&(tick, _)| tick);
+        *track = Track::from_absolute(events);
+    }
+
+    /// Quantizes notes of all tracks of the `smf`.
+    ///
+    /// Fails if the `smf` does not use [`Timing::Metrical`].
+    ///
+    /// [`Timing::Metrical`]: ../enum.Timing.html#variant.Metrical
+    pub fn apply(&self, smf: &mut Smf) -> Result<(), Error> {
+        let ppqn = match smf.timing {
+            Timing::Metrical(ppqn) => ppqn,
+            Timing::Timecode { .. } => {
+                return Err(Error {
+                    context: "Quantize::apply: quantization requires metrical timing",
+                    kind: ErrorKind::Invalid,
+                })
+            }
+        };
+
+        for track in &mut smf.tracks {
+            self.apply_track(track, ppqn);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Grid, Quantize};
+    use crate::test_util::{end_of_track, midi, note_on, tempo};
+    use crate::{MidiEventKind, Track};
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(Grid::new(4).ticks(96), 96.0);
+        assert_eq!(Grid::new(16).ticks(96), 24.0);
+        assert_eq!(Grid::triplet(8).ticks(96), 32.0);
+    }
+
+    #[test]
+    fn test_tick() {
+        let quantize = Quantize::new(Grid::new(8));
+        assert_eq!(quantize.tick(50, 96), 48);
+        assert_eq!(quantize.tick(70, 96), 48);
+        assert_eq!(quantize.tick(73, 96), 96);
+        assert_eq!(quantize.strength(0.5).tick(40, 96), 44);
+        assert_eq!(quantize.window(0.1).tick(40, 96), 40);
+        assert_eq!(quantize.window(0.1).tick(45, 96), 48);
+        assert_eq!(quantize.swing(0.5).tick(70, 96), 72);
+        assert_eq!(quantize.swing(0.5).tick(90, 96), 96);
+        assert_eq!(Quantize::new(Grid::triplet(8)).tick(30, 96), 32);
+    }
+
+    #[test]
+    fn test_apply_track() {
+        let mut track = Track {
+            events: vec![
+                note_on(3, 0, 60, 100),
+                tempo(40, 400_000),
+                note_on(5, 0, 60, 0),
+                note_on(12, 0, 62, 100),
+                note_on(110, 0, 62, 0),
+                end_of_track(0),
+            ],
+        };
+        let quantize = Quantize::new(Grid::new(4));
+
+        let mut quantized = track.clone();
+        quantize.apply_track(&mut quantized, 96);
+        assert_eq!(
+            quantized.events,
+            vec![
+                note_on(0, 0, 60, 100),
+                tempo(43, 400_000),
+                note_on(2, 0, 60, 0),
+                note_on(51, 0, 62, 100),
+                note_on(110, 0, 62, 0),
+                end_of_track(0),
+            ]
+        );
+
+        quantize.ends(true).apply_track(&mut track, 96);
+        assert_eq!(
+            track.events,
+            vec![
+                note_on(0, 0, 60, 100),
+                tempo(43, 400_000),
+                note_on(2, 0, 60, 0),
+                note_on(51, 0, 62, 100),
+                note_on(96, 0, 62, 0),
+                end_of_track(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_track_program_change() {
+        let mut track = Track {
+            events: vec![
+                midi(50, 0, MidiEventKind::ProgramChange(1)),
+                note_on(2, 0, 60, 100),
+                note_on(0, 1, 62, 100),
+                note_on(8, 0, 60, 0),
+                note_on(0, 1, 62, 0),
+                end_of_track(0),
+            ],
+        };
+        Quantize::new(Grid::new(8)).apply_track(&mut track, 96);
+        // the note of channel 0 starts at the program change, the note of channel 1 moves
+        assert_eq!(
+            track.events,
+            vec![
+                note_on(48, 1, 62, 100),
+                midi(2, 0, MidiEventKind::ProgramChange(1)),
+                note_on(0, 0, 60, 100),
+                note_on(6, 1, 62, 0),
+                note_on(2, 0, 60, 0),
+                end_of_track(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_track_same_key() {
+        let mut track = Track {
+            events: vec![
+                note_on(0, 0, 60, 100),
+                note_on(50, 0, 60, 0),
+                note_on(2, 0, 60, 100),
+                note_on(20, 0, 60, 0),
+                end_of_track(0),
+            ],
+        };
+        Quantize::new(Grid::new(8)).apply_track(&mut track, 96);
+        // the first note ends where the second one starts
+        assert_eq!(
+            track.events,
+            vec![
+                note_on(0, 0, 60, 100),
+                note_on(48, 0, 60, 0),
+                note_on(0, 0, 60, 100),
+                note_on(20, 0, 60, 0),
+                end_of_track(4),
+            ]
+        );
+    }
+}
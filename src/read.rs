@@ -1,17 +1,20 @@
 //! Low-level `SMF` reading interface.
 
+use crate::ump::{read_packet, Packet};
 use crate::{
     Action, Error, ErrorKind, Event, EventKind, Format, Fps, MetaEvent, MidiEvent, MidiEventKind,
-    SysexEvent, Text, Timing,
+    SysexEvent, SystemEvent, Text, Timing,
 };
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use core::convert::TryInto;
 use core::str;
 
-fn context(context: &'static str) -> impl FnOnce(ErrorKind) -> Error {
+pub(crate) fn context(context: &'static str) -> impl FnOnce(ErrorKind) -> Error {
     move |kind| Error { context, kind }
 }
 
-fn read_bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], ErrorKind> {
+pub(crate) fn read_bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], ErrorKind> {
     if data.len() < len {
         return Err(ErrorKind::Fatal);
     }
@@ -43,10 +46,9 @@ fn read_u24(data: &mut &[u8]) -> Result<u32, ErrorKind> {
     read_bytes(data, 3)
         .map(|b| {
             let mut bytes = [0u8; 4];
-            bytes[1..].copy_from_slice(&b);
+            bytes[1..].copy_from_slice(b);
             bytes
         })
-        .map(|b| b.try_into().unwrap())
         .map(u32::from_be_bytes)
 }
 
@@ -56,6 +58,12 @@ fn read_u32(data: &mut &[u8]) -> Result<u32, ErrorKind> {
         .map(u32::from_be_bytes)
 }
 
+pub(crate) fn read_u32_le(data: &mut &[u8]) -> Result<u32, ErrorKind> {
+    read_bytes(data, 4)
+        .map(|b| b.try_into().unwrap())
+        .map(u32::from_le_bytes)
+}
+
 fn read_format(data: &mut &[u8]) -> Result<Format, ErrorKind> {
     let value = read_u16(data)?;
     let format = match value {
@@ -67,7 +75,7 @@ fn read_format(data: &mut &[u8]) -> Result<Format, ErrorKind> {
     Ok(format)
 }
 
-fn expect_bytes(data: &mut &[u8], expected: &[u8]) -> Result<(), ErrorKind> {
+pub(crate) fn expect_bytes(data: &mut &[u8], expected: &[u8]) -> Result<(), ErrorKind> {
     if read_bytes(data, expected.len())? != expected {
         return Err(ErrorKind::Invalid);
     }
@@ -108,7 +116,7 @@ fn read_vlq(data: &mut &[u8]) -> Result<u32, ErrorKind> {
     Ok(result)
 }
 
-fn read_timing(data: &mut &[u8]) -> Result<Timing, ErrorKind> {
+pub(crate) fn read_timing(data: &mut &[u8]) -> Result<Timing, ErrorKind> {
     let timing = read_u16(data)?;
     let mask = 0x8000u16;
     if (timing & mask) == 0 {
@@ -212,7 +220,7 @@ fn read_meta_event<'a>(bytes: &mut &'a [u8]) -> Result<MetaEvent<'a>, ErrorKind>
     Ok(meta_event)
 }
 
-fn read_midi_event(bytes: &mut &[u8], status_byte: u8) -> Result<MidiEvent, ErrorKind> {
+pub(crate) fn read_midi_event(bytes: &mut &[u8], status_byte: u8) -> Result<MidiEvent, ErrorKind> {
     let channel = status_byte & 0x0f;
     let status = status_byte & 0xf0;
     let kind = match status {
@@ -255,9 +263,7 @@ fn read_midi_event(bytes: &mut &[u8], status_byte: u8) -> Result<MidiEvent, Erro
             let msb = read_u7(bytes)?;
             MidiEventKind::PitchBend { lsb, msb }
         }
-        _ => {
-            unimplemented!();
-        }
+        _ => return Err(ErrorKind::Invalid),
     };
 
     let midi_event = MidiEvent { channel, kind };
@@ -265,6 +271,51 @@ fn read_midi_event(bytes: &mut &[u8], status_byte: u8) -> Result<MidiEvent, Erro
     Ok(midi_event)
 }
 
+/// Low-level [`SystemEvent`] reader.
+///
+/// Reads the status byte followed by data bytes of system common or system real-time message.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Error, SystemEvent, read::read_system_event};
+/// # fn foo() -> Result<(), Error> {
+/// let event = read_system_event(&mut &[0xf2, 0x10, 0x01][..])?;
+/// assert_eq!(event, SystemEvent::SongPosition(0x90));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`SystemEvent`]: ../enum.SystemEvent.html
+pub fn read_system_event(bytes: &mut &[u8]) -> Result<SystemEvent, Error> {
+    const DATA: &str = "read_system_event: message must contain 7 bit data bytes";
+    let status = read_u8(bytes).map_err(context("read_system_event: status byte is missing"))?;
+    let event = match status {
+        0xf1 => SystemEvent::TimeCodeQuarterFrame(read_u7(bytes).map_err(context(DATA))?),
+        0xf2 => {
+            let lsb = read_u7(bytes).map_err(context(DATA))?;
+            let msb = read_u7(bytes).map_err(context(DATA))?;
+            SystemEvent::SongPosition(u16::from(msb) << 7 | u16::from(lsb))
+        }
+        0xf3 => SystemEvent::SongSelect(read_u7(bytes).map_err(context(DATA))?),
+        0xf6 => SystemEvent::TuneRequest,
+        0xf8 => SystemEvent::TimingClock,
+        0xfa => SystemEvent::Start,
+        0xfb => SystemEvent::Continue,
+        0xfc => SystemEvent::Stop,
+        0xfe => SystemEvent::ActiveSensing,
+        0xff => SystemEvent::Reset,
+        _ => {
+            return Err(Error {
+                context: "read_system_event: unknown status byte",
+                kind: ErrorKind::Invalid,
+            })
+        }
+    };
+
+    Ok(event)
+}
+
 /// Low-level [`HeaderChunk`] reader.
 ///
 /// Reads [`HeaderChunk`] and moves the cursor the beginning of the first
@@ -300,7 +351,7 @@ pub fn read_header_chunk(cursor: &mut &[u8]) -> Result<HeaderChunk, Error> {
     if let Format::Single = format {
         if tracks != 1 {
             return Err(Error {
-                context: "read_header_chunk: header type 0, can contain on 1 track",
+                context: "read_header_chunk: format 0 header must specify exactly 1 track",
                 kind: ErrorKind::Invalid,
             });
         }
@@ -363,11 +414,58 @@ pub fn read_track_chunk<'a>(bytes: &mut &'a [u8]) -> Result<TrackChunk<'a>, Erro
         }
     };
 
-    let track_chunk = TrackChunk { data };
+    let track_chunk = TrackChunk::new(data);
 
     Ok(track_chunk)
 }
 
+/// Low-level [`Chunk`] reader.
+///
+/// Reads [`Chunk`] of any type and moves the cursor the beginning of the next chunk.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{
+/// #   Error,
+/// #   read::{read_chunk, read_header_chunk, Chunk}
+/// # };
+/// # fn foo(mut bytes: &[u8]) -> Result<(), Error> {
+/// let cursor: &mut &[u8] = &mut bytes;
+/// let header_chunk = read_header_chunk(cursor)?;
+/// while !cursor.is_empty() {
+///     match read_chunk(cursor)? {
+///         Chunk::Track(track_chunk) => {}
+///         Chunk::Unknown { id, data } => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Chunk`]: enum.Chunk.html
+pub fn read_chunk<'a>(bytes: &mut &'a [u8]) -> Result<Chunk<'a>, Error> {
+    // read chunk type
+    let id = read_bytes(bytes, 4)
+        .map(|b| b.try_into().unwrap())
+        .map_err(context("read_chunk: chunk must specify type"))?;
+
+    // read chunk len
+    let len = read_u32(bytes).map_err(context("read_chunk: chunk must specify len"))?;
+
+    // read chunk data
+    let data = read_bytes(bytes, len as usize)
+        .map_err(context("read_chunk: chunk must contain data bytes"))?;
+
+    let chunk = if &id == b"MTrk" {
+        Chunk::Track(TrackChunk::new(data))
+    } else {
+        Chunk::Unknown { id, data }
+    };
+
+    Ok(chunk)
+}
+
 /// Low-level [`Event`] reader.
 ///
 /// Reads [`Event`] and moves the cursor the beginning of the next
@@ -386,13 +484,55 @@ pub fn read_track_chunk<'a>(bytes: &mut &'a [u8]) -> Result<TrackChunk<'a>, Erro
 /// # }
 /// ```
 ///
+/// Running status is not supported, use [`read_track_event`] to read events of a track.
+///
 /// [`Event`]: ../struct.Event.html
+/// [`read_track_event`]: fn.read_track_event.html
 pub fn read_event<'a>(bytes: &mut &'a [u8]) -> Result<Event<'a>, Error> {
+    read_track_event(bytes, &mut None)
+}
+
+/// Low-level [`Event`] reader with running status support.
+///
+/// Reads [`Event`] and moves the cursor the beginning of the next
+/// [`Event`]. If the event does not start with a status byte, the
+/// `running_status` of the previous midi event is used.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Error, read::read_track_event};
+/// # fn foo(mut bytes: &[u8]) -> Result<(), Error> {
+/// let cursor: &mut &[u8] = &mut bytes;
+/// let mut running_status = None;
+/// while !cursor.is_empty() {
+///     let event = read_track_event(cursor, &mut running_status)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Event`]: ../struct.Event.html
+pub fn read_track_event<'a>(
+    bytes: &mut &'a [u8],
+    running_status: &mut Option<u8>,
+) -> Result<Event<'a>, Error> {
     // read time
     let time = read_vlq(bytes).map_err(context("read_event: event must have valid time"))?;
 
     // read event type
-    let event_type = read_u8(bytes).map_err(context("read_event: event must have type"))?;
+    let event_type = match bytes.first() {
+        Some(&byte) if byte < 0x80 => running_status.ok_or(Error {
+            context: "read_event: data byte without running status",
+            kind: ErrorKind::Invalid,
+        })?,
+        _ => read_u8(bytes).map_err(context("read_event: event must have type"))?,
+    };
+
+    // midi events set running status, sysex and meta events do not affect it
+    if let 0x80..=0xef = event_type {
+        *running_status = Some(event_type);
+    }
 
     // read event data
     let kind = match event_type {
@@ -417,6 +557,143 @@ pub fn read_event<'a>(bytes: &mut &'a [u8]) -> Result<Event<'a>, Error> {
     Ok(event)
 }
 
+/// Returns true if the `data` starts with RIFF `RMID` container header.
+pub fn is_rmid(data: &[u8]) -> bool {
+    data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"RMID"
+}
+
+/// Reads RIFF chunk header and data, including the pad byte of odd sized chunks.
+pub(crate) fn read_riff_chunk<'a>(bytes: &mut &'a [u8]) -> Result<([u8; 4], &'a [u8]), ErrorKind> {
+    let id = read_bytes(bytes, 4)?.try_into().unwrap();
+    let len = read_u32_le(bytes)? as usize;
+    let data = read_bytes(bytes, len)?;
+    if len % 2 == 1 && !bytes.is_empty() {
+        read_bytes(bytes, 1)?;
+    }
+    Ok((id, data))
+}
+
+/// Low-level RIFF `RMID` container reader.
+///
+/// Returns [`Rmid`] container and the wrapped `SMF` data. Files with the `.rmi` extension use
+/// this container. [`SmfReader::new`] unwraps it automatically.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Error, read::{is_rmid, read_rmid, SmfReader}};
+/// # fn foo(bytes: &[u8]) -> Result<(), Error> {
+/// if is_rmid(bytes) {
+///     let (rmid, smf) = read_rmid(bytes)?;
+///     let name = rmid.info.and_then(|info| info.get(b"INAM"));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Rmid`]: struct.Rmid.html
+/// [`SmfReader::new`]: struct.SmfReader.html#method.new
+pub fn read_rmid<'a>(data: &'a [u8]) -> Result<(Rmid<'a>, &'a [u8]), Error> {
+    let mut cursor = data;
+    let bytes = &mut cursor;
+    expect_bytes(bytes, b"RIFF").map_err(context("read_rmid: container type must be 'RIFF'"))?;
+    let len = read_u32_le(bytes).map_err(context("read_rmid: container must specify len"))?;
+    // be forgiving about invalid container length, it is often wrong
+    let mut body = read_bytes(bytes, len as usize).unwrap_or(*bytes);
+    let body = &mut body;
+    expect_bytes(body, b"RMID").map_err(context("read_rmid: form type must be 'RMID'"))?;
+
+    let mut rmid = Rmid {
+        info: None,
+        dls: None,
+    };
+    let mut smf = None;
+    while !body.is_empty() {
+        let (id, data) =
+            read_riff_chunk(body).map_err(context("read_rmid: chunk must contain data bytes"))?;
+        match &id {
+            b"data" => smf = Some(data),
+            b"LIST" if data.starts_with(b"INFO") => rmid.info = Some(RiffInfo::new(&data[4..])),
+            b"RIFF" if data.starts_with(b"DLS ") => rmid.dls = Some(data),
+            _ => {}
+        }
+    }
+
+    match smf {
+        Some(smf) => Ok((rmid, smf)),
+        None => Err(Error {
+            context: "read_rmid: container must contain 'data' chunk",
+            kind: ErrorKind::Invalid,
+        }),
+    }
+}
+
+/// RIFF `RMID` container metadata.
+///
+/// Created using [`read_rmid`] function.
+///
+/// [`read_rmid`]: fn.read_rmid.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rmid<'a> {
+    /// `INFO` list.
+    pub info: Option<RiffInfo<'a>>,
+    /// Embedded Downloadable Sounds collection, starting with `DLS ` form type.
+    pub dls: Option<&'a [u8]>,
+}
+
+/// RIFF `INFO` list, e.g. `INAM` (name), `ICOP` (copyright), `IART` (artist)
+/// or `ICMT` (comments).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct RiffInfo<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RiffInfo<'a> {
+    /// Creates new [`RiffInfo`] from the list data following the `INFO` list type.
+    ///
+    /// [`RiffInfo`]: struct.RiffInfo.html
+    pub fn new(data: &'a [u8]) -> Self {
+        RiffInfo { data }
+    }
+
+    /// Returns raw list data.
+    pub fn raw(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Creates iterator over `(id, text)` entries. Text does not contain the trailing zero byte.
+    pub fn iter(&self) -> impl Iterator<Item = Result<([u8; 4], Text<'a>), Error>> {
+        let mut data = self.data;
+        core::iter::from_fn(move || {
+            if data.is_empty() {
+                return None;
+            }
+
+            let result = read_riff_chunk(&mut data)
+                .map(|(id, text)| {
+                    let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+                    (id, Text::new(&text[..end]))
+                })
+                .map_err(context("RiffInfo::iter: entry must contain data bytes"));
+            if result.is_err() {
+                data = &[];
+            }
+            Some(result)
+        })
+    }
+
+    /// Returns text of the first valid entry with given `id`.
+    pub fn get(&self, id: &[u8; 4]) -> Option<Text<'a>> {
+        self.iter()
+            .filter_map(Result::ok)
+            .find(|entry| &entry.0 == id)
+            .map(|entry| entry.1)
+    }
+}
+
 /// Specifies some basic information about the data in `SMF`.
 #[derive(Debug, Clone, Copy)]
 pub struct HeaderChunk {
@@ -426,8 +703,20 @@ pub struct HeaderChunk {
 }
 
 /// Lazy `SMF` reader.
+///
+/// Tracks are the `MTrk` chunks actually present in the data. The number of tracks specified by
+/// the header chunk is informational only: [`track_chunk_iter`], [`track`], [`chunk_index`] and
+/// [`Smf::read`] all return every `MTrk` chunk, even if there are more or fewer of them.
+///
+/// [`track_chunk_iter`]: #method.track_chunk_iter
+/// [`track`]: #method.track
+/// [`chunk_index`]: #method.chunk_index
+/// [`Smf::read`]: ../struct.Smf.html#method.read
 pub struct SmfReader<'a> {
     header: HeaderChunk,
+    rmid: Option<Rmid<'a>>,
+    // entire smf data
+    smf: &'a [u8],
     // tracks chunks data
     data: &'a [u8],
 }
@@ -435,6 +724,8 @@ pub struct SmfReader<'a> {
 impl<'a> SmfReader<'a> {
     /// Creates new [`SmfReader`].
     ///
+    /// `SMF` wrapped in RIFF `RMID` container is unwrapped.
+    ///
     /// # Example
     ///
     /// ```
@@ -446,16 +737,31 @@ impl<'a> SmfReader<'a> {
     /// ```
     ///
     /// [`SmfReader`]: struct.SmfReader.html
-    pub fn new(mut data: &'a [u8]) -> Result<Self, Error> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let (rmid, smf) = if is_rmid(data) {
+            let (rmid, smf) = read_rmid(data)?;
+            (Some(rmid), smf)
+        } else {
+            (None, data)
+        };
+
+        let mut data = smf;
         let cursor = &mut data;
         let header = read_header_chunk(cursor)?;
         let reader = Self {
             header,
+            rmid,
+            smf,
             data: *cursor,
         };
         Ok(reader)
     }
 
+    /// Returns RIFF `RMID` container metadata, if `SMF` was wrapped in one.
+    pub fn rmid(&self) -> Option<Rmid<'a>> {
+        self.rmid
+    }
+
     /// Reads [`HeaderChunk`].
     ///
     /// # Example
@@ -476,6 +782,8 @@ impl<'a> SmfReader<'a> {
 
     /// Creates iterator over [`TrackChunk`]s.
     ///
+    /// Chunks of unknown types are skipped.
+    ///
     /// # Example
     ///
     /// ```
@@ -490,40 +798,266 @@ impl<'a> SmfReader<'a> {
     /// [`TrackChunk`]: struct.TrackChunk.html
     pub fn track_chunk_iter(&self) -> impl Iterator<Item = Result<TrackChunk<'a>, Error>> {
         TrackChunkIter {
+            chunks: ChunkIter { data: self.data },
+        }
+    }
+
+    /// Creates iterator over all [`Chunk`]s following the header chunk.
+    ///
+    /// Unlike [`track_chunk_iter`], chunks of unknown types are not skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi::{Error, read::{Chunk, SmfReader}};
+    /// # fn foo(data: &[u8]) -> Result<(), Error> {
+    /// # let smf_reader = SmfReader::new(data)?;
+    /// for chunk in smf_reader.chunk_iter() {
+    ///     if let Chunk::Unknown { id, data } = chunk? {
+    ///         // proprietary data
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Chunk`]: enum.Chunk.html
+    /// [`track_chunk_iter`]: #method.track_chunk_iter
+    pub fn chunk_iter(&self) -> impl Iterator<Item = Result<Chunk<'a>, Error>> {
+        ChunkIter { data: self.data }
+    }
+
+    /// Creates iterator over [`ChunkEntry`]s of all chunks following the header chunk.
+    ///
+    /// Chunk data is not read, so this is much faster than decoding the events.
+    ///
+    /// [`ChunkEntry`]: struct.ChunkEntry.html
+    pub fn chunk_entry_iter(&self) -> impl Iterator<Item = Result<ChunkEntry, Error>> + 'a {
+        ChunkEntryIter {
             data: self.data,
-            tracks: self.header.tracks as usize,
+            offset: self.smf.len() - self.data.len(),
+        }
+    }
+
+    /// Builds [`ChunkIndex`] of all chunks following the header chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi::{Error, read::SmfReader};
+    /// # fn foo(data: &[u8]) -> Result<(), Error> {
+    /// # let smf_reader = SmfReader::new(data)?;
+    /// let chunk_index = smf_reader.chunk_index()?;
+    /// if let Some(entry) = chunk_index.track(37) {
+    ///     let track_chunk = smf_reader.track_chunk_at(entry)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ChunkIndex`]: struct.ChunkIndex.html
+    #[cfg(feature = "alloc")]
+    pub fn chunk_index(&self) -> Result<ChunkIndex, Error> {
+        let chunks = self.chunk_entry_iter().collect::<Result<Vec<_>, _>>()?;
+        let tracks = chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_track())
+            .map(|(index, _)| index)
+            .collect();
+        Ok(ChunkIndex { chunks, tracks })
+    }
+
+    /// Returns [`TrackChunk`] with given `index`, which must be less than the number of `MTrk`
+    /// chunks.
+    ///
+    /// Only chunk headers of the preceding chunks are read. Use [`chunk_index`] to access
+    /// several tracks of the same file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi::{Error, read::SmfReader};
+    /// # fn foo(data: &[u8]) -> Result<(), Error> {
+    /// # let smf_reader = SmfReader::new(data)?;
+    /// let track_chunk = smf_reader.track(2)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TrackChunk`]: struct.TrackChunk.html
+    /// [`chunk_index`]: #method.chunk_index
+    pub fn track(&self, index: usize) -> Result<TrackChunk<'a>, Error> {
+        let mut tracks = self
+            .chunk_entry_iter()
+            .filter(|entry| entry.as_ref().map_or(true, ChunkEntry::is_track));
+        match tracks.nth(index) {
+            Some(entry) => self.track_chunk_at(&entry?),
+            None => Err(Error {
+                context: "SmfReader::track: track index out of bounds",
+                kind: ErrorKind::Invalid,
+            }),
+        }
+    }
+
+    /// Returns [`TrackChunk`] located by `entry`.
+    ///
+    /// [`TrackChunk`]: struct.TrackChunk.html
+    pub fn track_chunk_at(&self, entry: &ChunkEntry) -> Result<TrackChunk<'a>, Error> {
+        if !entry.is_track() {
+            return Err(Error {
+                context: "SmfReader::track_chunk_at: chunk type must be 'MTrk'",
+                kind: ErrorKind::Invalid,
+            });
+        }
+
+        let end = entry.offset.checked_add(entry.len as usize);
+        match end.and_then(|end| self.smf.get(entry.offset..end)) {
+            Some(data) => Ok(TrackChunk::new(data)),
+            None => Err(Error {
+                context: "SmfReader::track_chunk_at: chunk must be within smf data",
+                kind: ErrorKind::Fatal,
+            }),
         }
     }
 }
 
-struct TrackChunkIter<'a> {
+/// Chunk following the header chunk.
+#[derive(Debug, Clone)]
+pub enum Chunk<'a> {
+    /// `MTrk` chunk.
+    Track(TrackChunk<'a>),
+    /// Chunk of any other type, e.g. proprietary sequencer data.
+    Unknown { id: [u8; 4], data: &'a [u8] },
+}
+
+struct ChunkIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for ChunkIter<'a> {
+    type Item = Result<Chunk<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let result = read_chunk(&mut self.data);
+        if result.is_err() {
+            // the rest of the data cannot be interpreted
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// Location of a chunk within `SMF`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkEntry {
+    /// Chunk type.
+    pub id: [u8; 4],
+    /// Offset of the chunk data from the beginning of `SMF`.
+    pub offset: usize,
+    /// Length of the chunk data.
+    pub len: u32,
+}
+
+impl ChunkEntry {
+    /// Returns true if the chunk is an `MTrk` chunk.
+    pub fn is_track(&self) -> bool {
+        &self.id == b"MTrk"
+    }
+}
+
+struct ChunkEntryIter<'a> {
     data: &'a [u8],
-    tracks: usize,
+    offset: usize,
+}
+
+impl<'a> Iterator for ChunkEntryIter<'a> {
+    type Item = Result<ChunkEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let result = read_chunk_entry(&mut self.data, self.offset);
+        match result {
+            Ok(entry) => self.offset = entry.offset + entry.len as usize,
+            // the rest of the data cannot be interpreted
+            Err(_) => self.data = &[],
+        }
+        Some(result)
+    }
+}
+
+fn read_chunk_entry(bytes: &mut &[u8], offset: usize) -> Result<ChunkEntry, Error> {
+    let id = read_bytes(bytes, 4)
+        .map(|b| b.try_into().unwrap())
+        .map_err(context("read_chunk_entry: chunk must specify type"))?;
+    let len = read_u32(bytes).map_err(context("read_chunk_entry: chunk must specify len"))?;
+    read_bytes(bytes, len as usize)
+        .map_err(context("read_chunk_entry: chunk must contain data bytes"))?;
+
+    let entry = ChunkEntry {
+        id,
+        offset: offset + 8,
+        len,
+    };
+
+    Ok(entry)
+}
+
+/// Prebuilt table of chunk locations.
+///
+/// Created using [`SmfReader::chunk_index`] method.
+///
+/// [`SmfReader::chunk_index`]: struct.SmfReader.html#method.chunk_index
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkIndex {
+    chunks: Vec<ChunkEntry>,
+    // indices of `MTrk` chunks
+    tracks: Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl ChunkIndex {
+    /// Returns all chunks in the order they appear in `SMF`.
+    pub fn chunks(&self) -> &[ChunkEntry] {
+        &self.chunks
+    }
+
+    /// Returns number of `MTrk` chunks.
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Returns `MTrk` chunk with given `index`.
+    pub fn track(&self, index: usize) -> Option<&ChunkEntry> {
+        self.tracks.get(index).map(|&index| &self.chunks[index])
+    }
+}
+
+struct TrackChunkIter<'a> {
+    chunks: ChunkIter<'a>,
 }
 
 impl<'a> Iterator for TrackChunkIter<'a> {
     type Item = Result<TrackChunk<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.tracks == 0 {
-            if self.data.is_empty() {
-                return None;
+        loop {
+            match self.chunks.next()? {
+                Ok(Chunk::Track(track_chunk)) => return Some(Ok(track_chunk)),
+                // The midi specification requires that software be able to handle unexpected
+                // chunk-types by ignoring the entire chunk
+                Ok(Chunk::Unknown { .. }) => continue,
+                Err(err) => return Some(Err(err)),
             }
-            return Some(Err(Error {
-                context: "TrackChunkIter::next: undread data left",
-                kind: ErrorKind::Invalid,
-            }));
         }
-
-        self.tracks -= 1;
-        let cursor = &mut self.data;
-        let track_chunk = match read_track_chunk(cursor) {
-            Ok(track_data) => track_data,
-            Err(err) => return Some(Err(err)),
-        };
-        self.data = *cursor;
-
-        Some(Ok(track_chunk))
     }
 }
 
@@ -534,25 +1068,232 @@ impl<'a> Iterator for TrackChunkIter<'a> {
 /// [`Event`]: ../struct.Event.html
 /// [`SmfReader::track_chunk_iter`]:
 /// struct.SmfReader.html#method.track_chunk_iter
+#[derive(Debug, Clone)]
 pub struct TrackChunk<'a> {
     data: &'a [u8],
+    running_status: Option<u8>,
 }
 
-impl<'a> Iterator for TrackChunk<'a> {
-    type Item = Result<Event<'a>, Error>;
+impl<'a> TrackChunk<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        TrackChunk {
+            data,
+            running_status: None,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Returns raw bytes of the events which have not been read yet.
+    pub fn raw(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Reads next [`Event`] together with the bytes it was read from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi::{Error, read::SmfReader};
+    /// # fn foo(data: &[u8]) -> Result<(), Error> {
+    /// # let smf_reader = SmfReader::new(data)?;
+    /// let mut track_chunk = smf_reader.track(0)?;
+    /// while let Some(raw_event) = track_chunk.next_raw() {
+    ///     let raw_event = raw_event?;
+    ///     let uses_running_status = raw_event.uses_running_status();
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Event`]: ../struct.Event.html
+    pub fn next_raw(&mut self) -> Option<Result<RawEvent<'a>, Error>> {
         if self.data.is_empty() {
             return None;
         }
 
-        let cursor = &mut self.data;
-        let event = match read_event(cursor) {
+        let mut cursor = self.data;
+        let event = match read_track_event(&mut cursor, &mut self.running_status) {
             Ok(event) => event,
             Err(err) => return Some(Err(err)),
         };
-        self.data = *cursor;
-        Some(Ok(event))
+        let raw = &self.data[..self.data.len() - cursor.len()];
+        self.data = cursor;
+        Some(Ok(RawEvent { event, raw }))
+    }
+}
+
+impl<'a> Iterator for TrackChunk<'a> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_raw()
+            .map(|result| result.map(|raw_event| raw_event.event))
+    }
+}
+
+/// [`Event`] together with the bytes it was read from.
+///
+/// Created using [`TrackChunk::next_raw`] method.
+///
+/// [`Event`]: ../struct.Event.html
+/// [`TrackChunk::next_raw`]: struct.TrackChunk.html#method.next_raw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawEvent<'a> {
+    pub event: Event<'a>,
+    /// Event bytes, including delta time.
+    pub raw: &'a [u8],
+}
+
+impl<'a> RawEvent<'a> {
+    /// Returns true if the status byte of the event was omitted.
+    pub fn uses_running_status(&self) -> bool {
+        let data_byte = self
+            .raw
+            .iter()
+            .position(|byte| byte & 0x80 == 0)
+            .and_then(|time_end| self.raw.get(time_end + 1));
+        matches!(data_byte, Some(&byte) if byte < 0x80)
+    }
+}
+
+/// Returns true if the `data` starts with MIDI Clip File signature `SMF2CLIP`.
+pub fn is_clip(data: &[u8]) -> bool {
+    data.starts_with(CLIP_SIGNATURE)
+}
+
+pub(crate) const CLIP_SIGNATURE: &[u8] = b"SMF2CLIP";
+pub(crate) const START_OF_CLIP: u32 = 0xf020_0000;
+pub(crate) const END_OF_CLIP: u32 = 0xf021_0000;
+
+/// Lazy MIDI Clip File (`SMF2CLIP`) reader.
+///
+/// The file contains Universal MIDI Packets preceded by delta clockstamps. The configuration
+/// header ends with start of clip message, the clip sequence data ends with end of clip message.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Error, read::ClipReader, ump::Ump};
+/// # fn foo(data: &[u8]) -> Result<(), Error> {
+/// let reader = ClipReader::new(data)?;
+/// let ticks_per_quarter_note = reader.ticks_per_quarter_note();
+/// for entry in reader.packet_iter() {
+///     let (delta, packet) = entry?;
+///     let ump = Ump::from_packet(&packet)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ClipReader<'a> {
+    ticks_per_quarter_note: u16,
+    // configuration header packets
+    header: &'a [u8],
+    // clip sequence data packets
+    data: &'a [u8],
+}
+
+impl<'a> ClipReader<'a> {
+    /// Creates new [`ClipReader`].
+    ///
+    /// Reads the configuration header, which must contain delta clockstamp ticks per quarter note
+    /// and must be followed by start of clip message.
+    ///
+    /// [`ClipReader`]: struct.ClipReader.html
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let mut cursor = data;
+        let bytes = &mut cursor;
+        expect_bytes(bytes, CLIP_SIGNATURE)
+            .map_err(context("ClipReader::new: file must start with 'SMF2CLIP'"))?;
+        let header = *bytes;
+        let mut ticks_per_quarter_note = None;
+        let data = loop {
+            let packet = read_packet(bytes)
+                .map_err(|_| invalid("ClipReader::new: header must end with start of clip"))?;
+            match packet.words()[0] {
+                START_OF_CLIP => break *bytes,
+                word if word >> 20 == 0x003 => ticks_per_quarter_note = Some(word as u16),
+                _ => {}
+            }
+        };
+        let header = &header[..header.len() - data.len() - 16];
+
+        match ticks_per_quarter_note {
+            Some(ticks_per_quarter_note) if ticks_per_quarter_note > 0 => Ok(ClipReader {
+                ticks_per_quarter_note,
+                header,
+                data,
+            }),
+            _ => Err(invalid(
+                "ClipReader::new: header must specify ticks per quarter note",
+            )),
+        }
+    }
+
+    /// Returns delta clockstamp ticks per quarter note.
+    pub fn ticks_per_quarter_note(&self) -> u16 {
+        self.ticks_per_quarter_note
+    }
+
+    /// Creates iterator over configuration header packets, excluding delta clockstamps and
+    /// ticks per quarter note.
+    pub fn header_iter(&self) -> impl Iterator<Item = Result<Packet, Error>> + 'a {
+        ClipPacketIter {
+            data: self.header,
+            delta: 0,
+        }
+        .filter(|result| !matches!(result, Ok((_, packet)) if packet.words()[0] >> 20 == 0x003))
+        .map(|result| result.map(|(_, packet)| packet))
+    }
+
+    /// Creates iterator over `(delta, packet)` pairs of the clip sequence data, where `delta`
+    /// is the sum of preceding delta clockstamps. The last packet is end of clip.
+    pub fn packet_iter(&self) -> impl Iterator<Item = Result<(u32, Packet), Error>> + 'a {
+        let mut end = false;
+        ClipPacketIter {
+            data: self.data,
+            delta: 0,
+        }
+        .take_while(move |result| {
+            let take = !end;
+            end = !matches!(result, Ok((_, packet)) if packet.words()[0] != END_OF_CLIP);
+            take
+        })
+    }
+}
+
+fn invalid(context: &'static str) -> Error {
+    Error {
+        context,
+        kind: ErrorKind::Invalid,
+    }
+}
+
+struct ClipPacketIter<'a> {
+    data: &'a [u8],
+    delta: u32,
+}
+
+impl<'a> Iterator for ClipPacketIter<'a> {
+    type Item = Result<(u32, Packet), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.data.is_empty() {
+            let packet = match read_packet(&mut self.data) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    self.data = &[];
+                    return Some(Err(err));
+                }
+            };
+            let word = packet.words()[0];
+            if word >> 20 == 0x004 {
+                self.delta = self.delta.saturating_add(word & 0xf_ffff);
+                continue;
+            }
+            let delta = core::mem::take(&mut self.delta);
+            return Some(Ok((delta, packet)));
+        }
+        None
     }
 }
 
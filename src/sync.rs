@@ -0,0 +1,395 @@
+//! MIDI clock synchronization.
+//!
+//! [`ClockSender`] generates timing clock messages at 24 clocks per quarter note from the tempo
+//! map of an `SMF`, together with start, stop, continue and song position pointer messages, so
+//! external sequencers can follow playback. [`ClockReceiver`] does the opposite and follows
+//! incoming messages, deriving tempo from timing clock with jitter smoothing.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::player::{Clock, Player};
+//! use midi::sync::ClockSender;
+//!
+//! # fn play(bytes: &[u8], clock: impl Clock) -> Result<(), midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let mut sync = ClockSender::new(&smf);
+//! let send = |event: midi::SystemEvent| println!("{:?}", event);
+//! let mut player = Player::new(&smf, clock, |event: midi::MidiEvent| println!("{:?}", event));
+//! send(sync.start());
+//! player.play();
+//! while let Some(wait) = player.update() {
+//!     while let Some(event) = sync.poll(player.position()) {
+//!         send(event);
+//!     }
+//!     let next_clock = sync.next_clock().unwrap_or(f64::INFINITY) - player.position();
+//!     std::thread::sleep(std::time::Duration::from_secs_f64(wait.min(next_clock).max(0.0)));
+//! }
+//! send(sync.stop());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ClockSender`]: struct.ClockSender.html
+//! [`ClockReceiver`]: struct.ClockReceiver.html
+
+use crate::{Smf, SystemEvent, TempoMap};
+use alloc::vec::Vec;
+
+/// Number of timing clock messages per quarter note.
+pub const CLOCKS_PER_QUARTER_NOTE: u32 = 24;
+
+/// Song position pointer counts sixteenth notes of 6 clocks.
+const CLOCKS_PER_SIXTEENTH: u64 = 6;
+
+/// Largest song position pointer value.
+const MAX_SONG_POSITION: u64 = 0x3fff;
+
+/// Consecutive intervals over four times the average after which [`ClockReceiver`] follows them.
+///
+/// [`ClockReceiver`]: struct.ClockReceiver.html
+const OUTLIERS: u32 = 3;
+
+/// Tempo change positioned in quarter notes.
+#[derive(Debug, Clone, Copy)]
+struct Beat {
+    quarter_notes: f64,
+    seconds: f64,
+    /// Seconds per quarter note.
+    tempo: f64,
+}
+
+/// Generates MIDI clock of `SMF` playback.
+///
+/// Song time in seconds is supplied by the application, e.g. from [`Player::position`].
+///
+/// [`Player::position`]: ../player/struct.Player.html#method.position
+#[derive(Debug, Clone)]
+pub struct ClockSender {
+    tempo_map: TempoMap,
+    beats: Vec<Beat>,
+    /// Index of the next timing clock since start of song.
+    next: u64,
+    running: bool,
+}
+
+impl ClockSender {
+    /// Creates [`ClockSender`] following tempo changes of `smf`.
+    ///
+    /// [`ClockSender`]: struct.ClockSender.html
+    pub fn new(smf: &Smf) -> Self {
+        Self::from_tempo_map(TempoMap::new(smf))
+    }
+
+    /// Creates [`ClockSender`] following `tempo_map`.
+    ///
+    /// With timecode timing, quarter notes are still defined by tempo changes.
+    ///
+    /// [`ClockSender`]: struct.ClockSender.html
+    pub fn from_tempo_map(tempo_map: TempoMap) -> Self {
+        let mut beats: Vec<Beat> = Vec::with_capacity(tempo_map.changes().len());
+        for change in tempo_map.changes() {
+            let quarter_notes = match beats.last() {
+                Some(beat) => beat.quarter_notes + (change.seconds - beat.seconds) / beat.tempo,
+                None => 0.0,
+            };
+            beats.push(Beat {
+                quarter_notes,
+                seconds: change.seconds,
+                tempo: f64::from(change.tempo.max(1)) / 1_000_000.0,
+            });
+        }
+
+        ClockSender {
+            tempo_map,
+            beats,
+            next: 0,
+            running: false,
+        }
+    }
+
+    /// Returns `true` between start or continue and stop.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Rewinds to start of song and returns start message.
+    pub fn start(&mut self) -> SystemEvent {
+        self.next = 0;
+        self.running = true;
+        SystemEvent::Start
+    }
+
+    /// Stops sending timing clock and returns stop message.
+    pub fn stop(&mut self) -> SystemEvent {
+        self.running = false;
+        SystemEvent::Stop
+    }
+
+    /// Resumes sending timing clock from current position and returns continue message.
+    pub fn resume(&mut self) -> SystemEvent {
+        self.running = true;
+        SystemEvent::Continue
+    }
+
+    /// Moves to the first sixteenth note at or after `seconds` and returns song position pointer
+    /// message. Should be sent while stopped, followed by [`resume`].
+    ///
+    /// [`resume`]: #method.resume
+    pub fn locate(&mut self, seconds: f64) -> SystemEvent {
+        let clock = self.quarter_notes(seconds) * f64::from(CLOCKS_PER_QUARTER_NOTE);
+        let sixteenths = (clock / CLOCKS_PER_SIXTEENTH as f64 - 1e-6).ceil().max(0.0) as u64;
+        let sixteenths = sixteenths.min(MAX_SONG_POSITION);
+        self.next = sixteenths * CLOCKS_PER_SIXTEENTH;
+        SystemEvent::SongPosition(sixteenths as u16)
+    }
+
+    /// Moves to the first sixteenth note at or after absolute time `tick`, see [`locate`].
+    ///
+    /// [`locate`]: #method.locate
+    pub fn locate_tick(&mut self, tick: u64) -> SystemEvent {
+        self.locate(self.tempo_map.seconds(tick))
+    }
+
+    /// Returns song time in seconds of the next timing clock, `None` when stopped.
+    pub fn next_clock(&self) -> Option<f64> {
+        if self.running {
+            Some(self.seconds(self.next as f64 / f64::from(CLOCKS_PER_QUARTER_NOTE)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns timing clock message if it is due at song time `seconds`. Call repeatedly until
+    /// `None` is returned.
+    pub fn poll(&mut self, seconds: f64) -> Option<SystemEvent> {
+        match self.next_clock() {
+            Some(next) if next <= seconds => {
+                self.next += 1;
+                Some(SystemEvent::TimingClock)
+            }
+            _ => None,
+        }
+    }
+
+    fn quarter_notes(&self, seconds: f64) -> f64 {
+        let index = self
+            .beats
+            .iter()
+            .rposition(|beat| beat.seconds <= seconds)
+            .unwrap_or(0);
+        let beat = self.beats[index];
+        beat.quarter_notes + (seconds - beat.seconds) / beat.tempo
+    }
+
+    fn seconds(&self, quarter_notes: f64) -> f64 {
+        let index = self
+            .beats
+            .iter()
+            .rposition(|beat| beat.quarter_notes <= quarter_notes)
+            .unwrap_or(0);
+        let beat = self.beats[index];
+        beat.seconds + (quarter_notes - beat.quarter_notes) * beat.tempo
+    }
+}
+
+/// Follows incoming MIDI clock.
+///
+/// Tempo is the exponential moving average of intervals between timing clock messages. The
+/// interval spanning start, continue or stop is not measured. A single interval over four times
+/// the average, e.g. a late clock, is skipped, but after three of them in a row the average restarts
+/// from the latest interval so that large tempo changes are followed.
+#[derive(Debug, Clone)]
+pub struct ClockReceiver {
+    smoothing: f64,
+    last: Option<f64>,
+    /// Average seconds per timing clock.
+    interval: Option<f64>,
+    /// Consecutive intervals over four times the average.
+    outliers: u32,
+    clocks: u64,
+    running: bool,
+}
+
+impl Default for ClockReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClockReceiver {
+    /// Creates [`ClockReceiver`] with smoothing of 0.1.
+    ///
+    /// [`ClockReceiver`]: struct.ClockReceiver.html
+    pub fn new() -> Self {
+        ClockReceiver {
+            smoothing: 0.1,
+            last: None,
+            interval: None,
+            outliers: 0,
+            clocks: 0,
+            running: false,
+        }
+    }
+
+    /// Sets weight of each new interval in the average, from 0 exclusive to 1 for no smoothing.
+    pub fn smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    /// Handles `event` received at `seconds`. Events other than timing clock, start, continue,
+    /// stop and song position pointer are ignored.
+    pub fn receive(&mut self, event: &SystemEvent, seconds: f64) {
+        match *event {
+            SystemEvent::TimingClock => {
+                if let Some(last) = self.last {
+                    let interval = seconds - last;
+                    self.outliers = match self.interval {
+                        Some(average) if interval > 4.0 * average => self.outliers + 1,
+                        _ => 0,
+                    };
+                    self.interval = match self.interval {
+                        Some(average) if self.outliers == 0 => {
+                            Some(average + (interval - average) * self.smoothing)
+                        }
+                        Some(average) if self.outliers < OUTLIERS => Some(average),
+                        _ => {
+                            self.outliers = 0;
+                            Some(interval)
+                        }
+                    };
+                }
+                self.last = Some(seconds);
+                if self.running {
+                    self.clocks += 1;
+                }
+            }
+            SystemEvent::Start => {
+                self.clocks = 0;
+                self.last = None;
+                self.running = true;
+            }
+            SystemEvent::Continue => {
+                self.last = None;
+                self.running = true;
+            }
+            SystemEvent::Stop => {
+                self.last = None;
+                self.running = false;
+            }
+            SystemEvent::SongPosition(sixteenths) => {
+                self.clocks = u64::from(sixteenths) * CLOCKS_PER_SIXTEENTH;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns `true` between start or continue and stop.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Returns song position in timing clocks, 24 per quarter note.
+    pub fn position(&self) -> u64 {
+        self.clocks
+    }
+
+    /// Returns tempo in microseconds per quarter note, `None` before two timing clocks.
+    pub fn tempo(&self) -> Option<u32> {
+        self.interval.map(|interval| {
+            (interval * f64::from(CLOCKS_PER_QUARTER_NOTE) * 1_000_000.0).round() as u32
+        })
+    }
+
+    /// Returns tempo in beats per minute, `None` before two timing clocks.
+    pub fn bpm(&self) -> Option<f64> {
+        self.interval
+            .filter(|&interval| interval > 0.0)
+            .map(|interval| 60.0 / (interval * f64::from(CLOCKS_PER_QUARTER_NOTE)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClockReceiver, ClockSender};
+    use crate::{SystemEvent, TempoMap, Timing};
+
+    #[test]
+    fn test_sender() {
+        // 120 bpm for one quarter note, then 60 bpm
+        let tempo_map =
+            TempoMap::from_tempos(Timing::Metrical(96), vec![(0, 500_000), (96, 1_000_000)]);
+        let mut sender = ClockSender::from_tempo_map(tempo_map);
+        assert_eq!(sender.poll(10.0), None);
+
+        assert_eq!(sender.start(), SystemEvent::Start);
+        let mut times = Vec::new();
+        while let Some(next) = sender.next_clock().filter(|&next| next < 1.5) {
+            assert_eq!(sender.poll(next), Some(SystemEvent::TimingClock));
+            assert_eq!(sender.poll(next), None);
+            times.push(next);
+        }
+        assert_eq!(times.len(), 48);
+        assert!((times[1] - 0.5 / 24.0).abs() < 1e-9);
+        assert!((times[25] - (0.5 + 1.0 / 24.0)).abs() < 1e-9);
+
+        assert_eq!(sender.stop(), SystemEvent::Stop);
+        assert_eq!(sender.next_clock(), None);
+        // 0.5 s is the second quarter note, song position is in sixteenth notes
+        assert_eq!(sender.locate(0.5), SystemEvent::SongPosition(4));
+        assert_eq!(sender.locate(0.51), SystemEvent::SongPosition(5));
+        assert_eq!(sender.locate_tick(96 + 48), SystemEvent::SongPosition(6));
+        assert_eq!(sender.resume(), SystemEvent::Continue);
+        assert!((sender.next_clock().unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_receiver() {
+        let mut receiver = ClockReceiver::new();
+        assert_eq!(receiver.tempo(), None);
+
+        receiver.receive(&SystemEvent::Start, 0.0);
+        // 120 bpm with jitter of +-1 ms
+        for clock in 0..96 {
+            let jitter = if clock % 2 == 0 { 0.001 } else { -0.001 };
+            let seconds = f64::from(clock) * 0.5 / 24.0 + jitter;
+            receiver.receive(&SystemEvent::TimingClock, seconds);
+        }
+        let bpm = receiver.bpm().unwrap();
+        assert!((bpm - 120.0).abs() < 1.0, "{}", bpm);
+        assert_eq!(receiver.position(), 96);
+
+        // pause does not change the tempo
+        receiver.receive(&SystemEvent::Stop, 2.0);
+        receiver.receive(&SystemEvent::SongPosition(8), 2.0);
+        receiver.receive(&SystemEvent::Continue, 10.0);
+        receiver.receive(&SystemEvent::TimingClock, 10.0);
+        assert!((receiver.bpm().unwrap() - bpm).abs() < 1e-9);
+        assert_eq!(receiver.position(), 49);
+        assert!(receiver.is_running());
+
+        // single late clock is skipped
+        receiver.receive(&SystemEvent::TimingClock, 10.5);
+        assert!((receiver.bpm().unwrap() - bpm).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_receiver_tempo_drop() {
+        let mut receiver = ClockReceiver::new();
+        receiver.receive(&SystemEvent::Start, 0.0);
+        // 200 bpm, then 45 bpm
+        let mut seconds = 0.0;
+        for _ in 0..48 {
+            receiver.receive(&SystemEvent::TimingClock, seconds);
+            seconds += 60.0 / 200.0 / 24.0;
+        }
+        assert!((receiver.bpm().unwrap() - 200.0).abs() < 1e-6);
+        for _ in 0..48 {
+            seconds += 60.0 / 45.0 / 24.0;
+            receiver.receive(&SystemEvent::TimingClock, seconds);
+        }
+        assert!((receiver.bpm().unwrap() - 45.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,741 @@
+//! Simple software synthesizer behind `synth` feature.
+//!
+//! [`Synth`] plays every channel with a basic oscillator shaped by an [`Envelope`], channel 10
+//! plays noise bursts. It follows volume, expression, pan, pitch bend, pitch bend range and
+//! sustain pedal. It is meant for quick audio previews, not for faithful reproduction.
+//!
+//! With a [`SoundFont`] set, channels play samples of the preset selected by bank select and
+//! program change, channel 10 uses bank 128. Modulators are not applied.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::synth::{write_wav_i16, Synth, to_i16};
+//!
+//! # fn render(bytes: &[u8]) -> Result<(), midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let mut synth = Synth::new(44_100);
+//! let samples = synth.render_smf(&smf);
+//! let wav = write_wav_i16(&to_i16(&samples), 2, 44_100);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Synth`]: struct.Synth.html
+//! [`Envelope`]: struct.Envelope.html
+//! [`SoundFont`]: sf2/struct.SoundFont.html
+
+pub mod sf2;
+
+use self::sf2::{Preset, Region, SoundFont};
+use crate::player::Output;
+use crate::transform::DRUM_CHANNEL;
+use crate::{ChannelState, EventKind, MidiEvent, MidiEventKind, Parameter, Smf, TempoMap};
+use std::f32::consts::PI;
+use std::vec::Vec;
+
+/// Number of output channels, samples are interleaved left and right.
+pub const CHANNELS: u16 = 2;
+
+/// Longest time rendered after the end of the song, for released notes to fade out.
+const MAX_TAIL: f64 = 5.0;
+
+/// Shape of the oscillator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+    Noise,
+}
+
+/// Attack, decay, sustain and release envelope.
+///
+/// Times are in seconds, sustain is a level between `0.0` and `1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            attack: 0.01,
+            decay: 0.2,
+            sustain: 0.6,
+            release: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    Oscillator(Waveform),
+    /// Position of the end and the loop in sample data and sample modes of the region.
+    Sample {
+        end: f64,
+        loop_start: f64,
+        loop_end: f64,
+        modes: u16,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Voice {
+    channel: u8,
+    key: u8,
+    source: Source,
+    /// Played pitch in semitones, `root` pitch plays `rate` steps per second.
+    pitch: f32,
+    root: f32,
+    rate: f32,
+    gain: f32,
+    /// Pan offset added to the channel pan.
+    pan: f32,
+    exclusive_class: u16,
+    envelope: Envelope,
+    stage: Stage,
+    level: f32,
+    release_step: f32,
+    /// Phase of the oscillator or position in sample data.
+    phase: f64,
+    /// Note off arrived while sustain pedal was pressed.
+    sustained: bool,
+    /// Order of note on events, used to steal the oldest voice.
+    age: u64,
+}
+
+impl Voice {
+    fn release(&mut self, sample_rate: f32) {
+        if matches!(self.stage, Stage::Release | Stage::Finished) {
+            return;
+        }
+        self.stage = Stage::Release;
+        self.release_step = self.level / (self.envelope.release * sample_rate).max(1.0);
+    }
+
+    fn next_level(&mut self, sample_rate: f32) -> f32 {
+        let envelope = self.envelope;
+        match self.stage {
+            Stage::Attack => {
+                self.level += 1.0 / (envelope.attack * sample_rate).max(1.0);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -= (1.0 - envelope.sustain) / (envelope.decay * sample_rate).max(1.0);
+                if self.level <= envelope.sustain {
+                    self.level = envelope.sustain;
+                    self.stage = match envelope.sustain > 0.0 {
+                        true => Stage::Sustain,
+                        false => Stage::Finished,
+                    };
+                }
+            }
+            Stage::Sustain => {}
+            Stage::Release => {
+                self.level -= self.release_step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = Stage::Finished;
+                }
+            }
+            Stage::Finished => self.level = 0.0,
+        }
+        self.level
+    }
+}
+
+/// Mixing parameters of a channel.
+#[derive(Debug, Clone, Copy)]
+struct Mix {
+    gain: f32,
+    pan: f32,
+    /// Frequency multiplier of pitch bend.
+    bend: f32,
+}
+
+impl Mix {
+    fn new(state: &ChannelState, channel: u8) -> Self {
+        let controller = |number, default| {
+            f32::from(state.controller(channel, number).unwrap_or(default)) / 127.0
+        };
+        let volume = controller(7, 100);
+        let expression = controller(11, 127);
+        let pan = controller(10, 64);
+        let range = state
+            .parameter(channel, Parameter::Registered(0))
+            .map_or(2.0, |range| {
+                f32::from((range >> 7) as u8) + f32::from((range & 0x7f) as u8) / 100.0
+            });
+        let bend = state
+            .pitch_bend(channel)
+            .map_or(0.0, |bend| (f32::from(bend) - 8192.0) / 8192.0 * range);
+
+        Mix {
+            gain: volume * volume * expression,
+            pan,
+            bend: 2f32.powf(bend / 12.0),
+        }
+    }
+}
+
+/// Polyphonic synthesizer driven by [`MidiEvent`]s.
+///
+/// It implements [`Output`], so it can be played by [`Player`] in real time.
+///
+/// [`MidiEvent`]: ../struct.MidiEvent.html
+/// [`Output`]: ../player/trait.Output.html
+/// [`Player`]: ../player/struct.Player.html
+#[derive(Debug, Clone)]
+pub struct Synth<'a> {
+    sample_rate: u32,
+    sound_font: Option<&'a SoundFont<'a>>,
+    waveform: Waveform,
+    envelope: Envelope,
+    drum_envelope: Envelope,
+    polyphony: usize,
+    gain: f32,
+    state: ChannelState,
+    voices: Vec<Voice>,
+    age: u64,
+    noise: u32,
+}
+
+impl<'a> Synth<'a> {
+    /// Creates new [`Synth`] rendering at `sample_rate` with sawtooth waveform and default
+    /// [`Envelope`].
+    ///
+    /// [`Synth`]: struct.Synth.html
+    /// [`Envelope`]: struct.Envelope.html
+    pub fn new(sample_rate: u32) -> Self {
+        Synth {
+            sample_rate: sample_rate.max(1),
+            sound_font: None,
+            waveform: Waveform::Sawtooth,
+            envelope: Envelope::default(),
+            drum_envelope: Envelope {
+                attack: 0.001,
+                decay: 0.15,
+                sustain: 0.0,
+                release: 0.05,
+            },
+            polyphony: 64,
+            gain: 0.1,
+            state: ChannelState::new(),
+            voices: Vec::new(),
+            age: 0,
+            noise: 0x1234_5678,
+        }
+    }
+
+    /// Sets the waveform of melodic channels.
+    pub fn waveform(mut self, waveform: Waveform) -> Self {
+        self.waveform = waveform;
+        self
+    }
+
+    /// Sets the envelope of melodic channels.
+    pub fn envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
+    }
+
+    /// Sets the [`SoundFont`] used instead of oscillators. Channels fall back to oscillators
+    /// when the sound font does not contain their preset.
+    ///
+    /// [`SoundFont`]: sf2/struct.SoundFont.html
+    pub fn sound_font(mut self, sound_font: &'a SoundFont<'a>) -> Self {
+        self.sound_font = Some(sound_font);
+        self
+    }
+
+    /// Sets the maximum number of sounding notes. The oldest note is stopped when exceeded.
+    pub fn polyphony(mut self, polyphony: usize) -> Self {
+        self.polyphony = polyphony.max(1);
+        self
+    }
+
+    /// Sets the gain of a single note at full velocity and volume.
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Returns the sample rate.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns the number of sounding notes, including released notes fading out.
+    pub fn active_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Handles `event`.
+    pub fn send(&mut self, event: MidiEvent) {
+        self.state.update(event);
+        let sample_rate = self.sample_rate as f32;
+        let channel = event.channel & 0x0f;
+        let sustain = self.state.controller(channel, 64).unwrap_or(0) >= 64;
+        let voices = self
+            .voices
+            .iter_mut()
+            .filter(|voice| voice.channel == channel);
+        match event.kind {
+            MidiEventKind::NoteOn { key, velocity } if velocity > 0 => {
+                let regions = match self.sound_font {
+                    Some(font) => self
+                        .preset(font, channel)
+                        .map(|preset| font.regions(preset, key, velocity)),
+                    None => None,
+                };
+                match regions {
+                    Some(regions) if !regions.is_empty() => {
+                        for region in regions {
+                            self.start_sample(channel, key, velocity, region);
+                        }
+                    }
+                    _ => self.start_oscillator(channel, key, velocity),
+                }
+            }
+            MidiEventKind::NoteOn { key, .. } | MidiEventKind::NoteOff { key, .. } => {
+                for voice in voices.filter(|voice| voice.key == key) {
+                    match sustain {
+                        true => voice.sustained = true,
+                        false => voice.release(sample_rate),
+                    }
+                }
+            }
+            MidiEventKind::ControllerChange { number: 64, .. }
+            | MidiEventKind::ResetAllControllers
+                if !sustain =>
+            {
+                for voice in voices.filter(|voice| voice.sustained) {
+                    voice.release(sample_rate);
+                }
+            }
+            MidiEventKind::AllNotesOff
+            | MidiEventKind::OmniModeOff
+            | MidiEventKind::OmniModeOn
+            | MidiEventKind::MonoModeOn(_)
+            | MidiEventKind::PolyModeOn => {
+                for voice in voices {
+                    voice.release(sample_rate);
+                }
+            }
+            MidiEventKind::AllSoundOff => self.voices.retain(|voice| voice.channel != channel),
+            _ => {}
+        }
+    }
+
+    /// Returns the preset of the `channel`, falling back to the first bank or the first drum
+    /// kit.
+    fn preset(&self, font: &'a SoundFont<'a>, channel: u8) -> Option<&'a Preset<'a>> {
+        let program = u16::from(self.state.program(channel).unwrap_or(0));
+        if channel == DRUM_CHANNEL {
+            return font.preset(128, program).or_else(|| font.preset(128, 0));
+        }
+        let bank = self.state.bank(channel).map_or(0, |bank| bank >> 7);
+        font.preset(bank, program)
+            .or_else(|| font.preset(0, program))
+    }
+
+    fn start(&mut self, voice: Voice) {
+        if self.voices.len() >= self.polyphony {
+            if let Some(oldest) = (0..self.voices.len()).min_by_key(|&i| self.voices[i].age) {
+                self.voices.remove(oldest);
+            }
+        }
+        self.age += 1;
+        self.voices.push(Voice {
+            age: self.age,
+            ..voice
+        });
+    }
+
+    fn start_oscillator(&mut self, channel: u8, key: u8, velocity: u8) {
+        let (waveform, envelope) = match channel == DRUM_CHANNEL {
+            true => (Waveform::Noise, self.drum_envelope),
+            false => (self.waveform, self.envelope),
+        };
+        self.start(Voice {
+            channel,
+            key,
+            source: Source::Oscillator(waveform),
+            pitch: f32::from(key),
+            root: 69.0,
+            rate: 440.0,
+            gain: f32::from(velocity) / 127.0,
+            pan: 0.0,
+            exclusive_class: 0,
+            envelope,
+            stage: Stage::Attack,
+            level: 0.0,
+            release_step: 0.0,
+            phase: 0.0,
+            sustained: false,
+            age: 0,
+        });
+    }
+
+    fn start_sample(&mut self, channel: u8, key: u8, velocity: u8, region: Region) {
+        let sample_rate = self.sample_rate as f32;
+        if region.exclusive_class != 0 {
+            for voice in &mut self.voices {
+                if voice.channel == channel && voice.exclusive_class == region.exclusive_class {
+                    voice.release(sample_rate);
+                }
+            }
+        }
+        self.start(Voice {
+            channel,
+            key,
+            source: Source::Sample {
+                end: region.end as f64,
+                loop_start: region.loop_start as f64,
+                loop_end: region.loop_end as f64,
+                modes: region.sample_modes,
+            },
+            pitch: region.pitch,
+            root: region.root,
+            rate: region.sample_rate,
+            gain: f32::from(velocity) / 127.0 * region.attenuation,
+            pan: region.pan,
+            exclusive_class: region.exclusive_class,
+            envelope: Envelope {
+                attack: region.attack,
+                decay: region.decay,
+                sustain: region.sustain,
+                release: region.release,
+            },
+            stage: Stage::Attack,
+            level: 0.0,
+            release_step: 0.0,
+            phase: region.start as f64,
+            sustained: false,
+            age: 0,
+        });
+    }
+
+    fn noise(&mut self) -> f32 {
+        // xorshift32
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    /// Renders interleaved stereo samples filling the whole `out`, adding them to its content.
+    pub fn render(&mut self, out: &mut [f32]) {
+        let sample_rate = self.sample_rate as f32;
+        let mixes = (0..16u8)
+            .map(|channel| Mix::new(&self.state, channel))
+            .collect::<Vec<_>>();
+        let data: &[i16] = match self.sound_font {
+            Some(font) => &font.data,
+            None => &[],
+        };
+        let mut voices = core::mem::take(&mut self.voices);
+        for voice in &mut voices {
+            let mix = mixes[voice.channel as usize];
+            let step = f64::from(
+                voice.rate / sample_rate * 2f32.powf((voice.pitch - voice.root) / 12.0) * mix.bend,
+            );
+            let gain = self.gain * voice.gain * mix.gain;
+            let pan = (mix.pan + voice.pan).clamp(0.0, 1.0) * PI / 2.0;
+            let (left, right) = (pan.cos(), pan.sin());
+            for frame in out.chunks_exact_mut(CHANNELS as usize) {
+                let level = voice.next_level(sample_rate);
+                if voice.stage == Stage::Finished {
+                    break;
+                }
+                let phase = voice.phase;
+                let sample = match voice.source {
+                    Source::Oscillator(waveform) => {
+                        voice.phase = (phase + step).fract();
+                        let phase = phase as f32;
+                        match waveform {
+                            Waveform::Sine => (2.0 * PI * phase).sin(),
+                            Waveform::Square if phase < 0.5 => 1.0,
+                            Waveform::Square => -1.0,
+                            Waveform::Sawtooth => 2.0 * phase - 1.0,
+                            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                            Waveform::Noise => self.noise(),
+                        }
+                    }
+                    Source::Sample {
+                        end,
+                        loop_start,
+                        loop_end,
+                        modes,
+                    } => {
+                        let looping = modes == 1 || modes == 3 && voice.stage != Stage::Release;
+                        let looping = looping && loop_end > loop_start;
+                        let (index, fraction) = (phase as usize, phase.fract() as f32);
+                        // the sample following the loop end is the loop start
+                        let next = match looping && phase + 1.0 >= loop_end {
+                            true => loop_start as usize,
+                            false => index + 1,
+                        };
+                        let (current, next) = match (data.get(index), data.get(next)) {
+                            (Some(&current), Some(&next)) if phase < end => (current, next),
+                            _ => {
+                                voice.stage = Stage::Finished;
+                                break;
+                            }
+                        };
+                        voice.phase += step;
+                        if looping && voice.phase >= loop_end {
+                            voice.phase -= loop_end - loop_start;
+                        }
+                        (f32::from(current) + (f32::from(next) - f32::from(current)) * fraction)
+                            / 32768.0
+                    }
+                } * level
+                    * gain;
+                frame[0] += sample * left;
+                frame[1] += sample * right;
+            }
+        }
+        voices.retain(|voice| voice.stage != Stage::Finished);
+        self.voices = voices;
+    }
+
+    /// Renders all tracks of `smf` to interleaved stereo samples.
+    ///
+    /// Notes still sounding at the end of the song are released and rendered until they fade
+    /// out.
+    pub fn render_smf(&mut self, smf: &Smf) -> Vec<f32> {
+        let tempo_map = TempoMap::new(smf);
+        let mut events = Vec::new();
+        let mut end = 0;
+        for track in &smf.tracks {
+            for (tick, event) in track.absolute_iter() {
+                end = end.max(tick);
+                if let EventKind::Midi(midi_event) = event.kind {
+                    events.push((tick, midi_event));
+                }
+            }
+        }
+        // stable sort keeps the order of tracks
+        events.sort_by_key(|&(tick, _)| tick);
+
+        let sample_rate = f64::from(self.sample_rate);
+        let frame = |tick| (tempo_map.seconds(tick) * sample_rate).round() as usize;
+        let mut samples = Vec::new();
+        for (tick, event) in events {
+            self.render_until(&mut samples, frame(tick));
+            self.send(event);
+        }
+        self.render_until(&mut samples, frame(end));
+
+        for voice in &mut self.voices {
+            voice.release(self.sample_rate as f32);
+        }
+        let tail = (MAX_TAIL * f64::from(self.sample_rate)) as usize;
+        let mut frames = samples.len() / CHANNELS as usize;
+        let last = frames + tail;
+        while !self.voices.is_empty() && frames < last {
+            frames = (frames + (self.sample_rate as usize / 10).max(1)).min(last);
+            self.render_until(&mut samples, frames);
+        }
+        samples
+    }
+
+    fn render_until(&mut self, samples: &mut Vec<f32>, frame: usize) {
+        let start = samples.len();
+        let end = frame * CHANNELS as usize;
+        if end > start {
+            samples.resize(end, 0.0);
+            self.render(&mut samples[start..]);
+        }
+    }
+}
+
+impl Output for Synth<'_> {
+    fn send(&mut self, event: MidiEvent) {
+        Synth::send(self, event)
+    }
+}
+
+/// Converts float samples to 16 bit samples, clipping values out of `-1.0..=1.0`.
+pub fn to_i16(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|sample| (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16)
+        .collect()
+}
+
+fn write_wav(format: u16, bits: u16, channels: u16, sample_rate: u32, data: &[u8]) -> Vec<u8> {
+    let block_align = channels * bits / 8;
+    let mut out = Vec::with_capacity(44 + data.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&format.to_le_bytes());
+    out.extend_from_slice(&channels.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+    out.extend_from_slice(&block_align.to_le_bytes());
+    out.extend_from_slice(&bits.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+/// Writes interleaved 16 bit PCM `samples` as `WAV` file.
+pub fn write_wav_i16(samples: &[i16], channels: u16, sample_rate: u32) -> Vec<u8> {
+    let data = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect::<Vec<_>>();
+    write_wav(1, 16, channels, sample_rate, &data)
+}
+
+/// Writes interleaved 32 bit float `samples` as `WAV` file.
+pub fn write_wav_f32(samples: &[f32], channels: u16, sample_rate: u32) -> Vec<u8> {
+    let data = samples
+        .iter()
+        .flat_map(|sample| sample.to_le_bytes())
+        .collect::<Vec<_>>();
+    write_wav(3, 32, channels, sample_rate, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sf2::{tests::sound_font, SoundFont};
+    use super::{to_i16, write_wav_i16, Synth, Waveform};
+    use crate::test_util::{note_off, note_on, smf};
+    use crate::{Format, MidiEvent, MidiEventKind};
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples
+            .iter()
+            .fold(0.0, |peak, sample| sample.abs().max(peak))
+    }
+
+    #[test]
+    fn test_render_smf() {
+        // half a second note at 120 bpm and 96 ppqn
+        let smf = smf(
+            Format::Single,
+            96,
+            vec![vec![note_on(0, 0, 69, 127), note_off(96, 0, 69)]],
+        );
+
+        let mut synth = Synth::new(1000).waveform(Waveform::Square);
+        let samples = synth.render_smf(&smf);
+        // release of 0.2 seconds follows the note
+        assert_eq!(samples.len(), 2 * 700);
+        assert!(peak(&samples[..1000]) > 0.02);
+        assert!(peak(&samples[1380..]) < 0.01);
+        assert_eq!(synth.active_voices(), 0);
+
+        // the tail is rendered in steps of at least one frame
+        let mut synth = Synth::new(5);
+        let samples = synth.render_smf(&smf);
+        assert!(samples.len() <= 2 * (3 + 5 * 5));
+        assert_eq!(synth.active_voices(), 0);
+    }
+
+    #[test]
+    fn test_sustain_and_volume() {
+        let event = |kind| MidiEvent { channel: 0, kind };
+        let mut synth = Synth::new(1000);
+        synth.send(event(MidiEventKind::ControllerChange {
+            number: 64,
+            value: 127,
+        }));
+        synth.send(event(MidiEventKind::NoteOn {
+            key: 60,
+            velocity: 100,
+        }));
+        synth.send(event(MidiEventKind::NoteOff {
+            key: 60,
+            velocity: 0,
+        }));
+
+        let mut samples = vec![0.0; 2 * 1000];
+        synth.render(&mut samples);
+        assert!(peak(&samples[1800..]) > 0.01);
+
+        synth.send(event(MidiEventKind::ControllerChange {
+            number: 7,
+            value: 0,
+        }));
+        let mut silent = vec![0.0; 2 * 100];
+        synth.render(&mut silent);
+        assert_eq!(peak(&silent), 0.0);
+
+        synth.send(event(MidiEventKind::ControllerChange {
+            number: 64,
+            value: 0,
+        }));
+        let mut released = vec![0.0; 2 * 1000];
+        synth.render(&mut released);
+        assert_eq!(synth.active_voices(), 0);
+    }
+
+    #[test]
+    fn test_sound_font() {
+        let data = sound_font();
+        let font = SoundFont::read(&data).unwrap();
+        let mut synth = Synth::new(8000).sound_font(&font);
+        let event = |channel, kind| MidiEvent { channel, kind };
+        let note_on = MidiEventKind::NoteOn {
+            key: 60,
+            velocity: 127,
+        };
+
+        // program 0 loops a positive and program 1 a negative constant
+        synth.send(event(0, note_on));
+        let mut samples = vec![0.0; 2 * 100];
+        synth.render(&mut samples);
+        assert!(samples[100..].iter().all(|&sample| sample > 0.01));
+
+        synth.send(event(0, MidiEventKind::ProgramChange(1)));
+        synth.send(event(1, MidiEventKind::ProgramChange(1)));
+        synth.send(event(1, note_on));
+        synth.send(event(1, note_on));
+        let mut samples = vec![0.0; 2 * 100];
+        synth.render(&mut samples);
+        assert!(samples[100..].iter().all(|&sample| sample < -0.01));
+        assert_eq!(synth.active_voices(), 3);
+    }
+
+    #[test]
+    fn test_wav() {
+        let samples = to_i16(&[0.0, 1.0, -2.0, 0.5]);
+        assert_eq!(samples, vec![0, 32767, -32767, 16384]);
+
+        let wav = write_wav_i16(&samples, 2, 8000);
+        assert_eq!(wav.len(), 44 + 8);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(&wav[4..8], &44u32.to_le_bytes());
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(&wav[22..24], &2u16.to_le_bytes());
+        assert_eq!(&wav[28..32], &32000u32.to_le_bytes());
+        assert_eq!(&wav[40..44], &8u32.to_le_bytes());
+        assert_eq!(&wav[44..46], &0i16.to_le_bytes());
+    }
+}
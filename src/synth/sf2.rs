@@ -0,0 +1,675 @@
+//! SoundFont 2 reader.
+//!
+//! [`SoundFont::read`] parses RIFF `sfbk` files into presets, instruments, their zones with
+//! generators and modulators, sample headers and 16 bit sample data. Set it with
+//! [`Synth::sound_font`] to render with samples instead of oscillators.
+//!
+//! [`SoundFont::read`]: struct.SoundFont.html#method.read
+//! [`Synth::sound_font`]: ../struct.Synth.html#method.sound_font
+
+use crate::read::{context, expect_bytes, read_bytes, read_riff_chunk, read_u32_le, RiffInfo};
+use crate::{Error, ErrorKind, Text};
+use core::convert::TryInto;
+use core::ops::Range;
+use std::vec::Vec;
+
+/// Generator of a [`Zone`], setting a single sound parameter.
+///
+/// [`Zone`]: struct.Zone.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Generator {
+    /// Generator operator, e.g. [`Generator::PAN`].
+    ///
+    /// [`Generator::PAN`]: #associatedconstant.PAN
+    pub operator: u16,
+    /// Raw amount, see [`signed`] and [`range`].
+    ///
+    /// [`signed`]: #method.signed
+    /// [`range`]: #method.range
+    pub amount: u16,
+}
+
+impl Generator {
+    pub const START_ADDRS_OFFSET: u16 = 0;
+    pub const END_ADDRS_OFFSET: u16 = 1;
+    pub const STARTLOOP_ADDRS_OFFSET: u16 = 2;
+    pub const ENDLOOP_ADDRS_OFFSET: u16 = 3;
+    pub const START_ADDRS_COARSE_OFFSET: u16 = 4;
+    pub const END_ADDRS_COARSE_OFFSET: u16 = 12;
+    pub const PAN: u16 = 17;
+    pub const ATTACK_VOL_ENV: u16 = 34;
+    pub const DECAY_VOL_ENV: u16 = 36;
+    pub const SUSTAIN_VOL_ENV: u16 = 37;
+    pub const RELEASE_VOL_ENV: u16 = 38;
+    pub const INSTRUMENT: u16 = 41;
+    pub const KEY_RANGE: u16 = 43;
+    pub const VEL_RANGE: u16 = 44;
+    pub const STARTLOOP_ADDRS_COARSE_OFFSET: u16 = 45;
+    pub const KEYNUM: u16 = 46;
+    pub const VELOCITY: u16 = 47;
+    pub const INITIAL_ATTENUATION: u16 = 48;
+    pub const ENDLOOP_ADDRS_COARSE_OFFSET: u16 = 50;
+    pub const COARSE_TUNE: u16 = 51;
+    pub const FINE_TUNE: u16 = 52;
+    pub const SAMPLE_ID: u16 = 53;
+    pub const SAMPLE_MODES: u16 = 54;
+    pub const SCALE_TUNING: u16 = 56;
+    pub const EXCLUSIVE_CLASS: u16 = 57;
+    pub const OVERRIDING_ROOT_KEY: u16 = 58;
+
+    /// Returns the amount as a signed number.
+    pub fn signed(&self) -> i16 {
+        self.amount as i16
+    }
+
+    /// Returns the amount as an inclusive `(low, high)` range, used by key and velocity ranges.
+    pub fn range(&self) -> (u8, u8) {
+        let [low, high] = self.amount.to_le_bytes();
+        (low, high)
+    }
+}
+
+/// Modulator of a [`Zone`], connecting a controller to a generator.
+///
+/// [`Zone`]: struct.Zone.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modulator {
+    pub source: u16,
+    pub destination: u16,
+    pub amount: i16,
+    pub amount_source: u16,
+    pub transform: u16,
+}
+
+/// Zone of a [`Preset`] or an [`Instrument`].
+///
+/// [`Preset`]: struct.Preset.html
+/// [`Instrument`]: struct.Instrument.html
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Zone {
+    pub generators: Vec<Generator>,
+    pub modulators: Vec<Modulator>,
+}
+
+impl Zone {
+    /// Returns the generator with given `operator`, the last one wins.
+    pub fn generator(&self, operator: u16) -> Option<Generator> {
+        self.generators
+            .iter()
+            .rev()
+            .find(|generator| generator.operator == operator)
+            .copied()
+    }
+
+    fn contains(&self, global: &Zone, operator: u16, value: u8) -> bool {
+        let (low, high) = self
+            .generator(operator)
+            .or_else(|| global.generator(operator))
+            .map_or((0, 127), |generator| generator.range());
+        (low..=high).contains(&value)
+    }
+}
+
+/// Preset selected by bank and program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset<'a> {
+    pub name: Text<'a>,
+    pub program: u16,
+    pub bank: u16,
+    /// Generators and modulators applying to all zones.
+    pub global: Zone,
+    /// Zones referring to instruments.
+    pub zones: Vec<Zone>,
+}
+
+/// Instrument used by presets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument<'a> {
+    pub name: Text<'a>,
+    /// Generators and modulators applying to all zones.
+    pub global: Zone,
+    /// Zones referring to samples.
+    pub zones: Vec<Zone>,
+}
+
+/// Sample header. Positions are indices into [`SoundFont::data`].
+///
+/// [`SoundFont::data`]: struct.SoundFont.html#structfield.data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample<'a> {
+    pub name: Text<'a>,
+    pub start: u32,
+    pub end: u32,
+    pub loop_start: u32,
+    pub loop_end: u32,
+    pub sample_rate: u32,
+    pub original_pitch: u8,
+    /// Pitch correction in cents.
+    pub pitch_correction: i8,
+    pub link: u16,
+    pub kind: u16,
+}
+
+/// SoundFont 2 bank.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundFont<'a> {
+    /// `INFO` list, e.g. `INAM` (name) or `ICOP` (copyright).
+    pub info: RiffInfo<'a>,
+    pub presets: Vec<Preset<'a>>,
+    pub instruments: Vec<Instrument<'a>>,
+    pub samples: Vec<Sample<'a>>,
+    /// 16 bit sample data of all samples.
+    pub data: Vec<i16>,
+}
+
+/// Chunks of `pdta` list in the order of [`SoundFont::read`] destructuring.
+///
+/// [`SoundFont::read`]: struct.SoundFont.html#method.read
+const PDTA: [&[u8; 4]; 9] = [
+    b"phdr", b"pbag", b"pmod", b"pgen", b"inst", b"ibag", b"imod", b"igen", b"shdr",
+];
+
+fn u16_at(record: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(record[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(record: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(record[offset..offset + 4].try_into().unwrap())
+}
+
+fn name(record: &[u8]) -> Text<'_> {
+    let name = &record[..20];
+    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    Text::new(&name[..end])
+}
+
+/// Splits `data` into records of `size` bytes. The terminal record is required.
+fn records(data: Option<&[u8]>, size: usize) -> Result<Vec<&[u8]>, ErrorKind> {
+    match data {
+        Some(data) if !data.is_empty() && data.len() % size == 0 => {
+            Ok(data.chunks_exact(size).collect())
+        }
+        _ => Err(ErrorKind::Invalid),
+    }
+}
+
+/// Returns the range between the index at `offset` of the record `index` and the next one.
+fn index_range(records: &[&[u8]], index: usize, offset: usize, len: usize) -> Option<Range<usize>> {
+    let start = usize::from(u16_at(records[index], offset));
+    let end = usize::from(u16_at(records.get(index + 1)?, offset));
+    match start <= end && end <= len {
+        true => Some(start..end),
+        false => None,
+    }
+}
+
+/// Reads zones of `bags`, the first zone without `terminal` generator is global.
+fn read_zones(
+    bags: &[&[u8]],
+    range: Range<usize>,
+    generators: &[Generator],
+    modulators: &[Modulator],
+    terminal: u16,
+) -> Option<(Zone, Vec<Zone>)> {
+    let mut global = Zone::default();
+    let mut zones = Vec::new();
+    let first = range.start;
+    for index in range {
+        let zone = Zone {
+            generators: generators[index_range(bags, index, 0, generators.len())?].to_vec(),
+            modulators: modulators[index_range(bags, index, 2, modulators.len())?].to_vec(),
+        };
+        match zone.generators.last() {
+            Some(generator) if generator.operator == terminal => zones.push(zone),
+            _ if index == first => global = zone,
+            // zones without terminal generator are ignored
+            _ => {}
+        }
+    }
+    Some((global, zones))
+}
+
+fn read_generators(data: Option<&[u8]>) -> Result<Vec<Generator>, ErrorKind> {
+    let generators = records(data, 4)?
+        .into_iter()
+        .map(|record| Generator {
+            operator: u16_at(record, 0),
+            amount: u16_at(record, 2),
+        })
+        .collect();
+    Ok(generators)
+}
+
+fn read_modulators(data: Option<&[u8]>) -> Result<Vec<Modulator>, ErrorKind> {
+    let modulators = records(data, 10)?
+        .into_iter()
+        .map(|record| Modulator {
+            source: u16_at(record, 0),
+            destination: u16_at(record, 2),
+            amount: u16_at(record, 4) as i16,
+            amount_source: u16_at(record, 6),
+            transform: u16_at(record, 8),
+        })
+        .collect();
+    Ok(modulators)
+}
+
+impl<'a> SoundFont<'a> {
+    /// Reads SoundFont 2 file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use midi;
+    /// use midi::synth::{sf2::SoundFont, Synth};
+    ///
+    /// # fn render(font: &[u8], bytes: &[u8]) -> Result<(), midi::Error> {
+    /// let font = SoundFont::read(font)?;
+    /// let smf = midi::Smf::read(bytes)?;
+    /// let samples = Synth::new(44_100).sound_font(&font).render_smf(&smf);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read(data: &'a [u8]) -> Result<SoundFont<'a>, Error> {
+        let mut cursor = data;
+        let bytes = &mut cursor;
+        expect_bytes(bytes, b"RIFF")
+            .map_err(context("SoundFont::read: container type must be 'RIFF'"))?;
+        let len =
+            read_u32_le(bytes).map_err(context("SoundFont::read: container must specify len"))?;
+        let mut body = read_bytes(bytes, len as usize)
+            .map_err(context("SoundFont::read: container must contain len bytes"))?;
+        let body = &mut body;
+        expect_bytes(body, b"sfbk")
+            .map_err(context("SoundFont::read: form type must be 'sfbk'"))?;
+
+        let mut info = RiffInfo::new(&[]);
+        let mut smpl: &[u8] = &[];
+        let mut pdta = [None; 9];
+        while !body.is_empty() {
+            let (id, data) = read_riff_chunk(body)
+                .map_err(context("SoundFont::read: chunk must contain data bytes"))?;
+            if &id != b"LIST" || data.len() < 4 {
+                continue;
+            }
+            let (kind, mut list) = data.split_at(4);
+            match kind {
+                b"INFO" => info = RiffInfo::new(list),
+                b"sdta" | b"pdta" => {
+                    while !list.is_empty() {
+                        let (id, data) = read_riff_chunk(&mut list)
+                            .map_err(context("SoundFont::read: chunk must contain data bytes"))?;
+                        if &id == b"smpl" {
+                            smpl = data;
+                        } else if let Some(index) = PDTA.iter().position(|&chunk| chunk == &id) {
+                            pdta[index] = Some(data);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let invalid = |kind| Error {
+            context: "SoundFont::read: 'pdta' list must contain all valid chunks",
+            kind,
+        };
+        let [phdr, pbag, pmod, pgen, inst, ibag, imod, igen, shdr] = pdta;
+        let phdr = records(phdr, 38).map_err(invalid)?;
+        let pbag = records(pbag, 4).map_err(invalid)?;
+        let pmod = read_modulators(pmod).map_err(invalid)?;
+        let pgen = read_generators(pgen).map_err(invalid)?;
+        let inst = records(inst, 22).map_err(invalid)?;
+        let ibag = records(ibag, 4).map_err(invalid)?;
+        let imod = read_modulators(imod).map_err(invalid)?;
+        let igen = read_generators(igen).map_err(invalid)?;
+        let shdr = records(shdr, 46).map_err(invalid)?;
+
+        let unordered = || Error {
+            context: "SoundFont::read: zone indices must be in order",
+            kind: ErrorKind::Invalid,
+        };
+        let mut presets = Vec::new();
+        for index in 0..phdr.len() - 1 {
+            let record = phdr[index];
+            let bags = index_range(&phdr, index, 24, pbag.len() - 1).ok_or_else(unordered)?;
+            let (global, zones) = read_zones(&pbag, bags, &pgen, &pmod, Generator::INSTRUMENT)
+                .ok_or_else(unordered)?;
+            presets.push(Preset {
+                name: name(record),
+                program: u16_at(record, 20),
+                bank: u16_at(record, 22),
+                global,
+                zones,
+            });
+        }
+
+        let mut instruments = Vec::new();
+        for index in 0..inst.len() - 1 {
+            let bags = index_range(&inst, index, 20, ibag.len() - 1).ok_or_else(unordered)?;
+            let (global, zones) = read_zones(&ibag, bags, &igen, &imod, Generator::SAMPLE_ID)
+                .ok_or_else(unordered)?;
+            instruments.push(Instrument {
+                name: name(inst[index]),
+                global,
+                zones,
+            });
+        }
+
+        let samples = shdr[..shdr.len() - 1]
+            .iter()
+            .map(|record| Sample {
+                name: name(record),
+                start: u32_at(record, 20),
+                end: u32_at(record, 24),
+                loop_start: u32_at(record, 28),
+                loop_end: u32_at(record, 32),
+                sample_rate: u32_at(record, 36),
+                original_pitch: record[40],
+                pitch_correction: record[41] as i8,
+                link: u16_at(record, 42),
+                kind: u16_at(record, 44),
+            })
+            .collect();
+
+        let data = smpl
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect();
+
+        let sound_font = SoundFont {
+            info,
+            presets,
+            instruments,
+            samples,
+            data,
+        };
+
+        Ok(sound_font)
+    }
+
+    /// Returns the preset with given `bank` and `program`.
+    pub fn preset(&self, bank: u16, program: u16) -> Option<&Preset<'a>> {
+        self.presets
+            .iter()
+            .find(|preset| preset.bank == bank && preset.program == program)
+    }
+
+    /// Returns playback parameters of every sample playing `key` with `velocity` in `preset`.
+    pub(super) fn regions(&self, preset: &Preset, key: u8, velocity: u8) -> Vec<Region> {
+        let mut regions = Vec::new();
+        for preset_zone in &preset.zones {
+            if !preset_zone.contains(&preset.global, Generator::KEY_RANGE, key)
+                || !preset_zone.contains(&preset.global, Generator::VEL_RANGE, velocity)
+            {
+                continue;
+            }
+            let instrument = match preset_zone
+                .generator(Generator::INSTRUMENT)
+                .and_then(|generator| self.instruments.get(usize::from(generator.amount)))
+            {
+                Some(instrument) => instrument,
+                None => continue,
+            };
+
+            for zone in &instrument.zones {
+                if !zone.contains(&instrument.global, Generator::KEY_RANGE, key)
+                    || !zone.contains(&instrument.global, Generator::VEL_RANGE, velocity)
+                {
+                    continue;
+                }
+                let sample = match zone
+                    .generator(Generator::SAMPLE_ID)
+                    .and_then(|generator| self.samples.get(usize::from(generator.amount)))
+                {
+                    Some(sample) => sample,
+                    None => continue,
+                };
+
+                // instrument generators are absolute, preset generators are added to them
+                let value = |operator, default: i16| {
+                    let absolute = zone
+                        .generator(operator)
+                        .or_else(|| instrument.global.generator(operator))
+                        .map_or(default, |generator| generator.signed());
+                    let relative = preset_zone
+                        .generator(operator)
+                        .or_else(|| preset.global.generator(operator))
+                        .map_or(0, |generator| generator.signed());
+                    i32::from(absolute) + i32::from(relative)
+                };
+                let instrument_value = |operator, default: i16| {
+                    zone.generator(operator)
+                        .or_else(|| instrument.global.generator(operator))
+                        .map_or(default, |generator| generator.signed())
+                };
+                let address = |base: u32, fine, coarse| {
+                    let offset = i64::from(instrument_value(fine, 0))
+                        + i64::from(instrument_value(coarse, 0)) * 32768;
+                    (i64::from(base) + offset).clamp(0, self.data.len() as i64) as usize
+                };
+                let seconds = |operator| 2f32.powf(value(operator, -12000) as f32 / 1200.0);
+
+                let root = match instrument_value(Generator::OVERRIDING_ROOT_KEY, -1) {
+                    root @ 0..=127 => root as f32,
+                    _ => f32::from(sample.original_pitch),
+                };
+                let key = match instrument_value(Generator::KEYNUM, -1) {
+                    key @ 0..=127 => key as f32,
+                    _ => f32::from(key),
+                };
+                let scale = value(Generator::SCALE_TUNING, 100) as f32 / 100.0;
+                let tune = value(Generator::COARSE_TUNE, 0) as f32
+                    + (value(Generator::FINE_TUNE, 0) as f32 + f32::from(sample.pitch_correction))
+                        / 100.0;
+
+                regions.push(Region {
+                    start: address(
+                        sample.start,
+                        Generator::START_ADDRS_OFFSET,
+                        Generator::START_ADDRS_COARSE_OFFSET,
+                    ),
+                    end: address(
+                        sample.end,
+                        Generator::END_ADDRS_OFFSET,
+                        Generator::END_ADDRS_COARSE_OFFSET,
+                    ),
+                    loop_start: address(
+                        sample.loop_start,
+                        Generator::STARTLOOP_ADDRS_OFFSET,
+                        Generator::STARTLOOP_ADDRS_COARSE_OFFSET,
+                    ),
+                    loop_end: address(
+                        sample.loop_end,
+                        Generator::ENDLOOP_ADDRS_OFFSET,
+                        Generator::ENDLOOP_ADDRS_COARSE_OFFSET,
+                    ),
+                    sample_modes: instrument_value(Generator::SAMPLE_MODES, 0) as u16 & 3,
+                    sample_rate: sample.sample_rate as f32,
+                    pitch: root + (key - root) * scale + tune,
+                    root,
+                    attenuation: 10f32.powf(
+                        -(value(Generator::INITIAL_ATTENUATION, 0).clamp(0, 1440) as f32) / 200.0,
+                    ),
+                    pan: value(Generator::PAN, 0).clamp(-500, 500) as f32 / 1000.0,
+                    attack: seconds(Generator::ATTACK_VOL_ENV),
+                    decay: seconds(Generator::DECAY_VOL_ENV),
+                    sustain: 10f32.powf(
+                        -(value(Generator::SUSTAIN_VOL_ENV, 0).clamp(0, 1440) as f32) / 200.0,
+                    ),
+                    release: seconds(Generator::RELEASE_VOL_ENV),
+                    exclusive_class: instrument_value(Generator::EXCLUSIVE_CLASS, 0) as u16,
+                });
+            }
+        }
+        regions
+    }
+}
+
+/// Playback parameters of a sample resolved from preset and instrument zones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Region {
+    pub start: usize,
+    pub end: usize,
+    pub loop_start: usize,
+    pub loop_end: usize,
+    /// 0 and 2 play once, 1 loops and 3 loops until the release.
+    pub sample_modes: u16,
+    pub sample_rate: f32,
+    /// Played pitch in semitones, including tuning.
+    pub pitch: f32,
+    pub root: f32,
+    /// Gain of initial attenuation.
+    pub attenuation: f32,
+    /// Pan offset between `-0.5` and `0.5`.
+    pub pan: f32,
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+    pub exclusive_class: u16,
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::{Generator, SoundFont};
+    use std::vec::Vec;
+
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn list(kind: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = kind.to_vec();
+        for chunk in chunks {
+            data.extend_from_slice(chunk);
+        }
+        chunk(b"LIST", &data)
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(20, 0);
+        bytes
+    }
+
+    fn generators(generators: &[(u16, u16)]) -> Vec<u8> {
+        generators
+            .iter()
+            .chain(&[(0, 0)])
+            .flat_map(|&(operator, amount)| [operator.to_le_bytes(), amount.to_le_bytes()])
+            .flatten()
+            .collect()
+    }
+
+    /// Creates a sound font with two looped presets, program 0 plays a positive and program 1
+    /// a negative constant. Program 1 has a global zone with full attenuation overridden by its
+    /// only instrument zone.
+    pub(in crate::synth) fn sound_font() -> Vec<u8> {
+        let mut smpl = Vec::new();
+        for value in [16384i16; 8]
+            .iter()
+            .chain(&[0; 46])
+            .chain(&[-16384; 8])
+            .chain(&[0; 46])
+        {
+            smpl.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut phdr = Vec::new();
+        for (preset, program, bag) in [("Up", 0u16, 0u16), ("Down", 1, 1), ("EOP", 0, 2)].iter() {
+            phdr.extend(name(preset));
+            for value in [*program, 0, *bag] {
+                phdr.extend_from_slice(&value.to_le_bytes());
+            }
+            phdr.extend_from_slice(&[0; 12]);
+        }
+        let pbag = [0u16, 0, 1, 0, 2, 0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        let pgen = generators(&[(Generator::INSTRUMENT, 0), (Generator::INSTRUMENT, 1)]);
+
+        let mut inst = Vec::new();
+        for (instrument, bag) in [("Up", 0u16), ("Down", 1), ("EOI", 3)].iter() {
+            inst.extend(name(instrument));
+            inst.extend_from_slice(&bag.to_le_bytes());
+        }
+        let ibag = [0u16, 0, 2, 0, 4, 0, 7, 0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        let igen = generators(&[
+            (Generator::SAMPLE_MODES, 1),
+            (Generator::SAMPLE_ID, 0),
+            (Generator::INITIAL_ATTENUATION, 1440),
+            (Generator::SAMPLE_MODES, 1),
+            (Generator::KEY_RANGE, u16::from_le_bytes([0, 100])),
+            (Generator::INITIAL_ATTENUATION, 0),
+            (Generator::SAMPLE_ID, 1),
+        ]);
+
+        let mut shdr = Vec::new();
+        for (sample, start) in [("Up", 0u32), ("Down", 54), ("EOS", 0)].iter() {
+            shdr.extend(name(sample));
+            for value in [start, &(start + 8), start, &(start + 8), &8000] {
+                shdr.extend_from_slice(&value.to_le_bytes());
+            }
+            shdr.extend_from_slice(&[69, 0, 0, 0, 1, 0]);
+        }
+
+        let mut body = b"sfbk".to_vec();
+        body.extend(list(
+            b"INFO",
+            &[chunk(b"ifil", &[2, 0, 1, 0]), chunk(b"INAM", b"Tiny\0")],
+        ));
+        body.extend(list(b"sdta", &[chunk(b"smpl", &smpl)]));
+        body.extend(list(
+            b"pdta",
+            &[
+                chunk(b"phdr", &phdr),
+                chunk(b"pbag", &pbag),
+                chunk(b"pmod", &[0; 10]),
+                chunk(b"pgen", &pgen),
+                chunk(b"inst", &inst),
+                chunk(b"ibag", &ibag),
+                chunk(b"imod", &[0; 10]),
+                chunk(b"igen", &igen),
+                chunk(b"shdr", &shdr),
+            ],
+        ));
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn test_read() {
+        let data = sound_font();
+        let font = SoundFont::read(&data).unwrap();
+        assert_eq!(font.info.get(b"INAM").unwrap().raw(), b"Tiny");
+        assert_eq!(font.presets.len(), 2);
+        assert_eq!(font.presets[1].name.raw(), b"Down");
+        assert_eq!(font.preset(0, 1), Some(&font.presets[1]));
+        assert_eq!(font.instruments.len(), 2);
+        assert_eq!(font.instruments[0].zones.len(), 1);
+        assert_eq!(font.instruments[1].global.generators.len(), 2);
+        assert_eq!(font.samples[1].start, 54);
+        assert_eq!(font.samples[1].sample_rate, 8000);
+        assert_eq!(font.data.len(), 108);
+
+        let regions = font.regions(&font.presets[1], 60, 100);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].start, 54);
+        assert_eq!(regions[0].attenuation, 1.0);
+        assert_eq!(regions[0].pitch, 60.0);
+        assert!(font.regions(&font.presets[1], 101, 100).is_empty());
+
+        assert!(SoundFont::read(&data[..100]).is_err());
+        assert!(SoundFont::read(b"RIFF\x04\0\0\0RMID").is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! Event and file fixtures shared by unit tests.
+
+use crate::{Event, EventKind, Format, MetaEvent, MidiEvent, MidiEventKind, Smf, Timing, Track};
+use alloc::vec::Vec;
+
+pub fn midi(time: u32, channel: u8, kind: MidiEventKind) -> Event<'static> {
+    Event {
+        time,
+        kind: EventKind::Midi(MidiEvent { channel, kind }),
+    }
+}
+
+pub fn meta(time: u32, meta_event: MetaEvent) -> Event {
+    Event {
+        time,
+        kind: EventKind::Meta(meta_event),
+    }
+}
+
+pub fn note_on(time: u32, channel: u8, key: u8, velocity: u8) -> Event<'static> {
+    midi(time, channel, MidiEventKind::NoteOn { key, velocity })
+}
+
+/// Note off with release velocity of 64.
+pub fn note_off(time: u32, channel: u8, key: u8) -> Event<'static> {
+    midi(time, channel, MidiEventKind::NoteOff { key, velocity: 64 })
+}
+
+pub fn tempo(time: u32, tempo: u32) -> Event<'static> {
+    meta(time, MetaEvent::SetTempo(tempo))
+}
+
+pub fn end_of_track(time: u32) -> Event<'static> {
+    meta(time, MetaEvent::EndOfTrack)
+}
+
+/// Metrical [`Smf`] with a track for every list of events.
+///
+/// [`Smf`]: ../struct.Smf.html
+pub fn smf(format: Format, ppqn: u16, tracks: Vec<Vec<Event>>) -> Smf {
+    Smf {
+        format,
+        timing: Timing::Metrical(ppqn),
+        tracks: tracks.into_iter().map(|events| Track { events }).collect(),
+        unknown_chunks: Vec::new(),
+        rmid: None,
+    }
+}
@@ -0,0 +1,619 @@
+//! Event transformations.
+//!
+//! [`Transform`]s change or drop [`MidiEvent`]s. They can be chained with [`Transform::then`]
+//! and applied to a whole [`Track`], [`OwnedTrack`] or lazily to a stream of events. Time of
+//! dropped events is added to the following event, so the timing of other events is preserved.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::transform::{Transform, Transpose, Velocity, VelocityCurve};
+//!
+//! # fn transform(bytes: &[u8]) -> Result<(), midi::Error> {
+//! let mut smf = midi::Smf::read(bytes)?;
+//! let mut transform = Transpose::new(2)
+//!     .skip_drums(true)
+//!     .then(Velocity::new(VelocityCurve::Scale(0.8)));
+//! for track in &mut smf.tracks {
+//!     transform.apply_track(track);
+//! }
+//!
+//! // streaming
+//! let reader = midi::read::SmfReader::new(bytes)?;
+//! for track_chunk in reader.track_chunk_iter() {
+//!     for event in Transpose::new(-12).stream(track_chunk?) {
+//!         let event = event?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Transform`]: trait.Transform.html
+//! [`Transform::then`]: trait.Transform.html#method.then
+//! [`MidiEvent`]: ../struct.MidiEvent.html
+//! [`Track`]: ../struct.Track.html
+//! [`OwnedTrack`]: ../struct.OwnedTrack.html
+
+use crate::{
+    Error, Event, EventKind, MidiEvent, MidiEventKind, OwnedEvent, OwnedEventKind, OwnedTrack,
+    Track,
+};
+use alloc::vec::Vec;
+
+/// Channel used by percussion in General MIDI (channel 10, zero based).
+pub const DRUM_CHANNEL: u8 = 9;
+
+/// Event which can be transformed, i.e. [`Event`] or [`OwnedEvent`].
+///
+/// [`Event`]: ../struct.Event.html
+/// [`OwnedEvent`]: ../struct.OwnedEvent.html
+pub trait TransformEvent {
+    /// Returns mutable delta time.
+    fn time_mut(&mut self) -> &mut u32;
+
+    /// Returns mutable midi event, if this is a midi event.
+    fn midi_event_mut(&mut self) -> Option<&mut MidiEvent>;
+}
+
+impl<'a> TransformEvent for Event<'a> {
+    fn time_mut(&mut self) -> &mut u32 {
+        &mut self.time
+    }
+
+    fn midi_event_mut(&mut self) -> Option<&mut MidiEvent> {
+        match self.kind {
+            EventKind::Midi(ref mut midi_event) => Some(midi_event),
+            _ => None,
+        }
+    }
+}
+
+impl TransformEvent for OwnedEvent {
+    fn time_mut(&mut self) -> &mut u32 {
+        &mut self.time
+    }
+
+    fn midi_event_mut(&mut self) -> Option<&mut MidiEvent> {
+        match self.kind {
+            OwnedEventKind::Midi(ref mut midi_event) => Some(midi_event),
+            _ => None,
+        }
+    }
+}
+
+/// Transformation of [`MidiEvent`]s.
+///
+/// Meta and sysex events are never transformed.
+///
+/// [`MidiEvent`]: ../struct.MidiEvent.html
+pub trait Transform {
+    /// Transforms `event`. Returns `None` if the event should be dropped.
+    fn transform(&mut self, event: MidiEvent) -> Option<MidiEvent>;
+
+    /// Creates [`Transform`] applying `self` and then `next`.
+    ///
+    /// [`Transform`]: trait.Transform.html
+    fn then<T: Transform>(self, next: T) -> Chain<Self, T>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+
+    /// Transforms single event. Returns `false` if the event should be dropped.
+    fn transform_event<E: TransformEvent>(&mut self, event: &mut E) -> bool {
+        match event.midi_event_mut() {
+            Some(midi_event) => match self.transform(*midi_event) {
+                Some(transformed) => {
+                    *midi_event = transformed;
+                    true
+                }
+                None => false,
+            },
+            None => true,
+        }
+    }
+
+    /// Transforms `events` in place.
+    fn apply<E: TransformEvent>(&mut self, events: &mut Vec<E>) {
+        let mut dropped_time = 0u32;
+        events.retain_mut(|event| {
+            let time = event.time_mut();
+            *time = time.saturating_add(dropped_time);
+            dropped_time = 0;
+            if self.transform_event(event) {
+                return true;
+            }
+            dropped_time = *event.time_mut();
+            false
+        });
+    }
+
+    /// Transforms all events of the `track`.
+    fn apply_track(&mut self, track: &mut Track) {
+        self.apply(&mut track.events)
+    }
+
+    /// Transforms all events of the owned `track`.
+    fn apply_owned_track(&mut self, track: &mut OwnedTrack) {
+        self.apply(&mut track.events)
+    }
+
+    /// Creates iterator lazily transforming `events`, e.g. [`TrackChunk`] or
+    /// [`TrackReader`].
+    ///
+    /// [`TrackChunk`]: ../read/struct.TrackChunk.html
+    /// [`TrackReader`]: ../struct.TrackReader.html
+    fn stream<I>(self, events: I) -> Stream<I::IntoIter, Self>
+    where
+        Self: Sized,
+        I: IntoIterator,
+    {
+        Stream {
+            events: events.into_iter(),
+            transform: self,
+            dropped_time: 0,
+        }
+    }
+}
+
+impl<F> Transform for F
+where
+    F: FnMut(MidiEvent) -> Option<MidiEvent>,
+{
+    fn transform(&mut self, event: MidiEvent) -> Option<MidiEvent> {
+        self(event)
+    }
+}
+
+/// Two chained [`Transform`]s.
+///
+/// Created using [`Transform::then`] method.
+///
+/// [`Transform`]: trait.Transform.html
+/// [`Transform::then`]: trait.Transform.html#method.then
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Transform, B: Transform> Transform for Chain<A, B> {
+    fn transform(&mut self, event: MidiEvent) -> Option<MidiEvent> {
+        self.first
+            .transform(event)
+            .and_then(|event| self.second.transform(event))
+    }
+}
+
+/// Iterator lazily transforming events.
+///
+/// Created using [`Transform::stream`] method.
+///
+/// [`Transform::stream`]: trait.Transform.html#method.stream
+#[derive(Debug, Clone)]
+pub struct Stream<I, T> {
+    events: I,
+    transform: T,
+    dropped_time: u32,
+}
+
+impl<I, T, E> Iterator for Stream<I, T>
+where
+    I: Iterator<Item = Result<E, Error>>,
+    T: Transform,
+    E: TransformEvent,
+{
+    type Item = Result<E, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut event = match self.events.next()? {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err)),
+            };
+            let time = event.time_mut();
+            *time = time.saturating_add(self.dropped_time);
+            self.dropped_time = 0;
+            if self.transform.transform_event(&mut event) {
+                return Some(Ok(event));
+            }
+            self.dropped_time = *event.time_mut();
+        }
+    }
+}
+
+fn key_mut(kind: &mut MidiEventKind) -> Option<&mut u8> {
+    match *kind {
+        MidiEventKind::NoteOff { ref mut key, .. }
+        | MidiEventKind::NoteOn { ref mut key, .. }
+        | MidiEventKind::PolyphonicKeyPressure { ref mut key, .. } => Some(key),
+        _ => None,
+    }
+}
+
+/// What to do with keys transposed out of `0..=127` range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutOfRange {
+    /// Use the lowest or the highest key.
+    Clamp,
+    /// Drop the event.
+    Drop,
+}
+
+/// Transposes notes and polyphonic key pressure by semitones.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{MidiEvent, MidiEventKind};
+/// use midi::transform::{OutOfRange, Transform, Transpose};
+///
+/// let mut transpose = Transpose::new(12).out_of_range(OutOfRange::Drop);
+/// let note = MidiEvent {
+///     channel: 0,
+///     kind: MidiEventKind::NoteOn { key: 120, velocity: 64 },
+/// };
+/// assert_eq!(transpose.transform(note), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transpose {
+    semitones: i8,
+    skip_drums: bool,
+    out_of_range: OutOfRange,
+}
+
+impl Transpose {
+    /// Creates new [`Transpose`] of all channels, clamping out of range keys.
+    ///
+    /// [`Transpose`]: struct.Transpose.html
+    pub fn new(semitones: i8) -> Self {
+        Transpose {
+            semitones,
+            skip_drums: false,
+            out_of_range: OutOfRange::Clamp,
+        }
+    }
+
+    /// Do not transpose [`DRUM_CHANNEL`], where keys select instruments.
+    ///
+    /// [`DRUM_CHANNEL`]: constant.DRUM_CHANNEL.html
+    pub fn skip_drums(mut self, skip_drums: bool) -> Self {
+        self.skip_drums = skip_drums;
+        self
+    }
+
+    /// Sets handling of keys out of range.
+    pub fn out_of_range(mut self, out_of_range: OutOfRange) -> Self {
+        self.out_of_range = out_of_range;
+        self
+    }
+}
+
+impl Transform for Transpose {
+    fn transform(&mut self, mut event: MidiEvent) -> Option<MidiEvent> {
+        if self.skip_drums && event.channel == DRUM_CHANNEL {
+            return Some(event);
+        }
+
+        if let Some(key) = key_mut(&mut event.kind) {
+            let transposed = i16::from(*key) + i16::from(self.semitones);
+            *key = match self.out_of_range {
+                OutOfRange::Clamp => transposed.clamp(0, 127) as u8,
+                OutOfRange::Drop if (0..=127).contains(&transposed) => transposed as u8,
+                OutOfRange::Drop => return None,
+            };
+        }
+        Some(event)
+    }
+}
+
+/// Velocity mapping used by [`Velocity`].
+///
+/// [`Velocity`]: struct.Velocity.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VelocityCurve {
+    /// Multiplies velocity by a factor.
+    Scale(f32),
+    /// Applies exponent to the normalized velocity. Values lower than 1 make soft notes
+    /// louder, values greater than 1 make them softer.
+    Power(f32),
+    /// Divides the part of velocity above `threshold` by `ratio`.
+    Compress { threshold: u8, ratio: f32 },
+    /// Maps velocity from `0..=127` to `min..=max` range.
+    Range { min: u8, max: u8 },
+    /// Sets all velocities to the same value.
+    Fixed(u8),
+}
+
+impl VelocityCurve {
+    fn apply(&self, velocity: u8) -> u8 {
+        let velocity = f32::from(velocity);
+        let mapped = match *self {
+            VelocityCurve::Scale(factor) => velocity * factor,
+            VelocityCurve::Power(exponent) => 127.0 * (velocity / 127.0).powf(exponent),
+            VelocityCurve::Compress { threshold, ratio } => {
+                let threshold = f32::from(threshold);
+                if velocity > threshold && ratio > 0.0 {
+                    threshold + (velocity - threshold) / ratio
+                } else {
+                    velocity
+                }
+            }
+            VelocityCurve::Range { min, max } => {
+                f32::from(min) + velocity * (f32::from(max) - f32::from(min)) / 127.0
+            }
+            VelocityCurve::Fixed(value) => f32::from(value),
+        };
+        // zero velocity would turn note on into note off
+        (mapped + 0.5).clamp(1.0, 127.0) as u8
+    }
+}
+
+/// Changes velocity of note on events.
+///
+/// Note on events with zero velocity, which are note off events, are not changed. Changed
+/// velocity is always in `1..=127` range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Velocity {
+    curve: VelocityCurve,
+    skip_drums: bool,
+}
+
+impl Velocity {
+    /// Creates new [`Velocity`] using the `curve`.
+    ///
+    /// [`Velocity`]: struct.Velocity.html
+    pub fn new(curve: VelocityCurve) -> Self {
+        Velocity {
+            curve,
+            skip_drums: false,
+        }
+    }
+
+    /// Do not change velocity on [`DRUM_CHANNEL`].
+    ///
+    /// [`DRUM_CHANNEL`]: constant.DRUM_CHANNEL.html
+    pub fn skip_drums(mut self, skip_drums: bool) -> Self {
+        self.skip_drums = skip_drums;
+        self
+    }
+}
+
+impl Transform for Velocity {
+    fn transform(&mut self, mut event: MidiEvent) -> Option<MidiEvent> {
+        if self.skip_drums && event.channel == DRUM_CHANNEL {
+            return Some(event);
+        }
+
+        if let MidiEventKind::NoteOn {
+            ref mut velocity, ..
+        } = event.kind
+        {
+            if *velocity > 0 {
+                *velocity = self.curve.apply(*velocity);
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Remaps channels of midi events.
+///
+/// # Example
+///
+/// ```
+/// use midi::transform::ChannelMap;
+///
+/// // swap channels 1 and 2 and drop channel 3
+/// let channel_map = ChannelMap::new().map(0, 1).map(1, 0).drop(2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelMap {
+    channels: [Option<u8>; 16],
+}
+
+impl Default for ChannelMap {
+    fn default() -> Self {
+        ChannelMap::new()
+    }
+}
+
+impl ChannelMap {
+    /// Creates new [`ChannelMap`] which does not change any channel.
+    ///
+    /// [`ChannelMap`]: struct.ChannelMap.html
+    pub fn new() -> Self {
+        let mut channels = [None; 16];
+        for (channel, mapped) in channels.iter_mut().enumerate() {
+            *mapped = Some(channel as u8);
+        }
+        ChannelMap { channels }
+    }
+
+    /// Moves events from channel `from` to channel `to`.
+    pub fn map(mut self, from: u8, to: u8) -> Self {
+        self.channels[(from & 0x0f) as usize] = Some(to & 0x0f);
+        self
+    }
+
+    /// Drops events of the `channel`.
+    pub fn drop(mut self, channel: u8) -> Self {
+        self.channels[(channel & 0x0f) as usize] = None;
+        self
+    }
+}
+
+impl Transform for ChannelMap {
+    fn transform(&mut self, mut event: MidiEvent) -> Option<MidiEvent> {
+        event.channel = self.channels[(event.channel & 0x0f) as usize]?;
+        Some(event)
+    }
+}
+
+/// Substitutes programs of program change events.
+///
+/// # Example
+///
+/// ```
+/// use midi::transform::ProgramMap;
+///
+/// // replace honky-tonk piano with acoustic grand piano
+/// let program_map = ProgramMap::new().map(3, 0).skip_drums(true);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgramMap {
+    programs: [u8; 128],
+    skip_drums: bool,
+}
+
+impl Default for ProgramMap {
+    fn default() -> Self {
+        ProgramMap::new()
+    }
+}
+
+impl ProgramMap {
+    /// Creates new [`ProgramMap`] which does not change any program.
+    ///
+    /// [`ProgramMap`]: struct.ProgramMap.html
+    pub fn new() -> Self {
+        let mut programs = [0; 128];
+        for (program, mapped) in programs.iter_mut().enumerate() {
+            *mapped = program as u8;
+        }
+        ProgramMap {
+            programs,
+            skip_drums: false,
+        }
+    }
+
+    /// Replaces program `from` with program `to`.
+    pub fn map(mut self, from: u8, to: u8) -> Self {
+        self.programs[(from & 0x7f) as usize] = to & 0x7f;
+        self
+    }
+
+    /// Do not change programs on [`DRUM_CHANNEL`], where programs select drum kits.
+    ///
+    /// [`DRUM_CHANNEL`]: constant.DRUM_CHANNEL.html
+    pub fn skip_drums(mut self, skip_drums: bool) -> Self {
+        self.skip_drums = skip_drums;
+        self
+    }
+}
+
+impl Transform for ProgramMap {
+    fn transform(&mut self, mut event: MidiEvent) -> Option<MidiEvent> {
+        if self.skip_drums && event.channel == DRUM_CHANNEL {
+            return Some(event);
+        }
+
+        if let MidiEventKind::ProgramChange(ref mut program) = event.kind {
+            *program = self.programs[(*program & 0x7f) as usize];
+        }
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChannelMap, OutOfRange, ProgramMap, Transform, Transpose, Velocity, VelocityCurve,
+    };
+    use crate::test_util::{end_of_track, midi, note_on};
+    use crate::{MidiEvent, MidiEventKind, OwnedEvent};
+
+    #[test]
+    fn test_transpose() {
+        let mut events = vec![
+            note_on(0, 0, 120, 64),
+            note_on(10, 9, 36, 64),
+            note_on(10, 0, 60, 64),
+            end_of_track(5),
+        ];
+        Transpose::new(10)
+            .skip_drums(true)
+            .out_of_range(OutOfRange::Drop)
+            .apply(&mut events);
+        assert_eq!(
+            events,
+            vec![
+                note_on(10, 9, 36, 64),
+                note_on(10, 0, 70, 64),
+                end_of_track(5),
+            ]
+        );
+
+        let mut clamped = vec![note_on(0, 0, 120, 64)];
+        Transpose::new(10).apply(&mut clamped);
+        assert_eq!(clamped, vec![note_on(0, 0, 127, 64)]);
+    }
+
+    #[test]
+    fn test_velocity() {
+        let apply = |curve: VelocityCurve, velocity: u8| {
+            Velocity::new(curve).transform(MidiEvent {
+                channel: 0,
+                kind: MidiEventKind::NoteOn { key: 60, velocity },
+            })
+        };
+        let velocity = |event: Option<MidiEvent>| match event.unwrap().kind {
+            MidiEventKind::NoteOn { velocity, .. } => velocity,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(velocity(apply(VelocityCurve::Scale(0.5), 100)), 50);
+        assert_eq!(velocity(apply(VelocityCurve::Scale(2.0), 100)), 127);
+        assert_eq!(velocity(apply(VelocityCurve::Scale(0.0), 100)), 1);
+        assert_eq!(velocity(apply(VelocityCurve::Scale(0.5), 0)), 0);
+        assert_eq!(velocity(apply(VelocityCurve::Power(1.0), 77)), 77);
+        assert_eq!(velocity(apply(VelocityCurve::Power(2.0), 127)), 127);
+        assert!(velocity(apply(VelocityCurve::Power(0.5), 32)) > 32);
+        let compress = VelocityCurve::Compress {
+            threshold: 80,
+            ratio: 2.0,
+        };
+        assert_eq!(velocity(apply(compress, 120)), 100);
+        assert_eq!(velocity(apply(compress, 60)), 60);
+        let range = VelocityCurve::Range { min: 40, max: 90 };
+        assert_eq!(velocity(apply(range, 127)), 90);
+        assert_eq!(velocity(apply(VelocityCurve::Fixed(100), 3)), 100);
+    }
+
+    #[test]
+    fn test_chain_and_stream() {
+        let events = vec![
+            Ok(note_on(0, 0, 60, 64)),
+            Ok(midi(5, 2, MidiEventKind::ProgramChange(3))),
+            Ok(midi(5, 1, MidiEventKind::ProgramChange(3))),
+            Ok(end_of_track(0)),
+        ];
+        let transform = ChannelMap::new()
+            .map(0, 1)
+            .drop(2)
+            .then(ProgramMap::new().map(3, 0));
+        let transformed = transform
+            .stream(events)
+            .map(|event| event.map(OwnedEvent::from))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected = vec![
+            note_on(0, 1, 60, 64),
+            midi(10, 1, MidiEventKind::ProgramChange(0)),
+            end_of_track(0),
+        ];
+        assert_eq!(
+            transformed,
+            expected
+                .into_iter()
+                .map(OwnedEvent::from)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,1162 @@
+//! MIDI 2.0 Universal MIDI Packet (`UMP`) types.
+//!
+//! [`Packet`] holds 1 to 4 raw 32 bit words, [`Ump`] is the decoded message. MIDI 1.0 channel
+//! voice messages can be carried losslessly by [`Ump::Midi1`] or translated to MIDI 2.0 channel
+//! voice messages with [`Upscaler`] and back with [`Midi2Event::to_midi1`], values are scaled
+//! as specified by the `UMP` specification (M2-104-UM).
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! use midi::ump::{read_packet, Ump, Upscaler};
+//!
+//! # fn foo(mut bytes: &[u8]) -> Result<(), midi::Error> {
+//! let mut upscaler = Upscaler::new();
+//! while !bytes.is_empty() {
+//!     let packet = read_packet(&mut bytes)?;
+//!     match Ump::from_packet(&packet)? {
+//!         Ump::Midi1 { group, event } => {
+//!             let midi2_event = upscaler.upscale(event);
+//!         }
+//!         Ump::Midi2 { group, event } => {
+//!             let midi1_events = event.to_midi1();
+//!         }
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Packet`]: struct.Packet.html
+//! [`Ump`]: enum.Ump.html
+//! [`Ump::Midi1`]: enum.Ump.html#variant.Midi1
+//! [`Upscaler`]: struct.Upscaler.html
+//! [`Midi2Event::to_midi1`]: struct.Midi2Event.html#method.to_midi1
+
+use crate::read::{read_bytes, read_midi_event, read_system_event};
+use crate::{Action, Error, ErrorKind, MidiEvent, MidiEventKind, SystemEvent};
+use core::convert::TryInto;
+
+/// Returns the number of 32 bit words of packets with given `message_type`.
+pub fn packet_size(message_type: u8) -> usize {
+    match message_type & 0x0f {
+        0x0..=0x2 | 0x6 | 0x7 => 1,
+        0x3 | 0x4 | 0x8..=0xa => 2,
+        0xb | 0xc => 3,
+        _ => 4,
+    }
+}
+
+/// Raw Universal MIDI Packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    words: [u32; 4],
+}
+
+impl Packet {
+    /// Creates new [`Packet`] from `words`. The number of words must match the message type.
+    ///
+    /// [`Packet`]: struct.Packet.html
+    pub fn new(words: &[u32]) -> Result<Packet, Error> {
+        match words.first() {
+            Some(&word) if packet_size((word >> 28) as u8) == words.len() => {
+                let mut packet = Packet { words: [0; 4] };
+                packet.words[..words.len()].copy_from_slice(words);
+                Ok(packet)
+            }
+            _ => Err(Error {
+                context: "Packet::new: number of words must match the message type",
+                kind: ErrorKind::Invalid,
+            }),
+        }
+    }
+
+    /// Returns the words of the packet.
+    pub fn words(&self) -> &[u32] {
+        &self.words[..self.size()]
+    }
+
+    /// Returns the number of words.
+    pub fn size(&self) -> usize {
+        packet_size(self.message_type())
+    }
+
+    /// Returns the message type, the upper nibble of the first word.
+    pub fn message_type(&self) -> u8 {
+        (self.words[0] >> 28) as u8
+    }
+
+    /// Returns the group, the second nibble of the first word.
+    pub fn group(&self) -> u8 {
+        (self.words[0] >> 24) as u8 & 0x0f
+    }
+}
+
+/// Low-level [`Packet`] reader.
+///
+/// Reads big endian words of a single packet, e.g. from a MIDI Clip File.
+///
+/// [`Packet`]: struct.Packet.html
+pub fn read_packet(bytes: &mut &[u8]) -> Result<Packet, Error> {
+    const MISSING: &str = "read_packet: packet must contain all words";
+    let mut packet = Packet { words: [0; 4] };
+    let mut read_word = || {
+        read_bytes(bytes, 4)
+            .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+            .map_err(|kind| Error {
+                context: MISSING,
+                kind,
+            })
+    };
+    packet.words[0] = read_word()?;
+    for index in 1..packet.size() {
+        packet.words[index] = read_word()?;
+    }
+    Ok(packet)
+}
+
+/// Status of multi-packet data messages, e.g. system exclusive or flex data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Form {
+    /// Complete message in a single packet.
+    Complete,
+    Start,
+    Continue,
+    End,
+}
+
+impl Form {
+    fn new(value: u32) -> Form {
+        match value & 0x3 {
+            0 => Form::Complete,
+            1 => Form::Start,
+            2 => Form::Continue,
+            _ => Form::End,
+        }
+    }
+
+    fn value(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Utility message, message type `0x0`. Utility messages do not have a group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Utility {
+    NoOp,
+    /// Jitter reduction clock in units of 1/31250 seconds.
+    JrClock(u16),
+    /// Jitter reduction timestamp in units of 1/31250 seconds.
+    JrTimestamp(u16),
+    /// Delta clockstamp ticks per quarter note.
+    TicksPerQuarterNote(u16),
+    /// Delta clockstamp, 20 bits of ticks since the previous one.
+    DeltaClockstamp(u32),
+}
+
+/// MIDI 2.0 channel voice message, message type `0x4`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Midi2Event {
+    pub channel: u8,
+    pub kind: Midi2EventKind,
+}
+
+/// [`Midi2Event`] variants.
+///
+/// [`Midi2Event`]: struct.Midi2Event.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Midi2EventKind {
+    NoteOff {
+        key: u8,
+        velocity: u16,
+        attribute_type: u8,
+        attribute: u16,
+    },
+    NoteOn {
+        key: u8,
+        velocity: u16,
+        attribute_type: u8,
+        attribute: u16,
+    },
+    PolyphonicKeyPressure {
+        key: u8,
+        value: u32,
+    },
+    RegisteredPerNoteController {
+        key: u8,
+        index: u8,
+        value: u32,
+    },
+    AssignablePerNoteController {
+        key: u8,
+        index: u8,
+        value: u32,
+    },
+    PerNoteManagement {
+        key: u8,
+        detach: bool,
+        reset: bool,
+    },
+    ControllerChange {
+        index: u8,
+        value: u32,
+    },
+    /// Registered parameter number, replaces `RPN` controllers of MIDI 1.0.
+    RegisteredController {
+        bank: u8,
+        index: u8,
+        value: u32,
+    },
+    /// Non-registered parameter number, replaces `NRPN` controllers of MIDI 1.0.
+    AssignableController {
+        bank: u8,
+        index: u8,
+        value: u32,
+    },
+    RelativeRegisteredController {
+        bank: u8,
+        index: u8,
+        value: i32,
+    },
+    RelativeAssignableController {
+        bank: u8,
+        index: u8,
+        value: i32,
+    },
+    /// Program change, optionally with bank select `(msb, lsb)`.
+    ProgramChange {
+        program: u8,
+        bank: Option<(u8, u8)>,
+    },
+    ChannelPressure(u32),
+    /// Pitch bend, `0x8000_0000` is the center.
+    PitchBend(u32),
+    PerNotePitchBend {
+        key: u8,
+        value: u32,
+    },
+}
+
+/// Decoded Universal MIDI Packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ump {
+    /// Message type `0x0`.
+    Utility(Utility),
+    /// System common and real-time message, message type `0x1`.
+    System { group: u8, event: SystemEvent },
+    /// MIDI 1.0 channel voice message, message type `0x2`.
+    Midi1 { group: u8, event: MidiEvent },
+    /// 7 bit system exclusive data without `0xf0` and `0xf7`, message type `0x3`.
+    Sysex7 {
+        group: u8,
+        form: Form,
+        data: [u8; 6],
+        len: u8,
+    },
+    /// MIDI 2.0 channel voice message, message type `0x4`.
+    Midi2 { group: u8, event: Midi2Event },
+    /// 8 bit system exclusive data, message type `0x5`.
+    Sysex8 {
+        group: u8,
+        form: Form,
+        stream: u8,
+        data: [u8; 13],
+        len: u8,
+    },
+    /// Mixed data set header (`status` 8) or payload (`status` 9), message type `0x5`.
+    MixedDataSet {
+        group: u8,
+        status: u8,
+        data: [u8; 14],
+    },
+    /// Message type `0xd`.
+    FlexData {
+        group: u8,
+        form: Form,
+        address: u8,
+        channel: u8,
+        status_bank: u8,
+        status: u8,
+        data: [u32; 3],
+    },
+    /// `UMP` stream message, message type `0xf`. Data contains the 14 bytes following the
+    /// status.
+    Stream {
+        form: Form,
+        status: u16,
+        data: [u8; 14],
+    },
+    /// Message of reserved message type.
+    Reserved(Packet),
+}
+
+/// Copies bytes of `words` starting at byte `start` of the first word into `out`.
+fn unpack(words: &[u32], start: usize, out: &mut [u8]) {
+    let bytes = words.iter().flat_map(|word| word.to_be_bytes());
+    for (out, byte) in out.iter_mut().zip(bytes.skip(start)) {
+        *out = byte;
+    }
+}
+
+/// Packs `data` into `words` starting at byte `start` of the first word.
+fn pack(words: &mut [u32], start: usize, data: &[u8]) {
+    for (index, &byte) in data.iter().enumerate() {
+        let index = start + index;
+        words[index / 4] |= u32::from(byte) << (24 - index % 4 * 8);
+    }
+}
+
+/// Returns controller number and value of MIDI 1.0 control change and channel mode messages.
+fn controller(kind: MidiEventKind) -> Option<(u8, u8)> {
+    let controller = match kind {
+        MidiEventKind::ControllerChange { number, value } => (number, value),
+        MidiEventKind::AllSoundOff => (0x78, 0),
+        MidiEventKind::ResetAllControllers => (0x79, 0),
+        MidiEventKind::LocalControl(Action::Disconnect) => (0x7a, 0),
+        MidiEventKind::LocalControl(Action::Reconnect) => (0x7a, 0x7f),
+        MidiEventKind::AllNotesOff => (0x7b, 0),
+        MidiEventKind::OmniModeOff => (0x7c, 0),
+        MidiEventKind::OmniModeOn => (0x7d, 0),
+        MidiEventKind::MonoModeOn(channels) => (0x7e, channels),
+        MidiEventKind::PolyModeOn => (0x7f, 0),
+        _ => return None,
+    };
+    Some(controller)
+}
+
+/// Returns status and data bytes of MIDI 1.0 channel voice message.
+fn midi1_bytes(event: &MidiEvent) -> [u8; 3] {
+    let status = event.channel & 0x0f;
+    let [status, data1, data2] = match event.kind {
+        MidiEventKind::NoteOff { key, velocity } => [0x80 | status, key, velocity],
+        MidiEventKind::NoteOn { key, velocity } => [0x90 | status, key, velocity],
+        MidiEventKind::PolyphonicKeyPressure { key, velocity } => [0xa0 | status, key, velocity],
+        MidiEventKind::ProgramChange(program) => [0xc0 | status, program, 0],
+        MidiEventKind::ChannelKeyPressure(pressure) => [0xd0 | status, pressure, 0],
+        MidiEventKind::PitchBend { lsb, msb } => [0xe0 | status, lsb, msb],
+        kind => {
+            let (number, value) = controller(kind).unwrap_or_default();
+            [0xb0 | status, number, value]
+        }
+    };
+    [status, data1 & 0x7f, data2 & 0x7f]
+}
+
+impl Ump {
+    /// Decodes `packet`.
+    pub fn from_packet(packet: &Packet) -> Result<Ump, Error> {
+        let words = packet.words();
+        let word = words[0];
+        let group = packet.group();
+        let invalid = |context| Error {
+            context,
+            kind: ErrorKind::Invalid,
+        };
+        let ump = match packet.message_type() {
+            0x0 => {
+                let data = word as u16;
+                let utility = match word >> 20 & 0xf {
+                    0x0 => Utility::NoOp,
+                    0x1 => Utility::JrClock(data),
+                    0x2 => Utility::JrTimestamp(data),
+                    0x3 => Utility::TicksPerQuarterNote(data),
+                    0x4 => Utility::DeltaClockstamp(word & 0xf_ffff),
+                    _ => return Err(invalid("Ump::from_packet: unknown utility message")),
+                };
+                Ump::Utility(utility)
+            }
+            0x1 => {
+                let bytes = &mut &word.to_be_bytes()[1..];
+                let event = read_system_event(bytes)
+                    .map_err(|_| invalid("Ump::from_packet: invalid system message"))?;
+                Ump::System { group, event }
+            }
+            0x2 => {
+                let [_, status, data1, data2] = word.to_be_bytes();
+                if !(0x80..0xf0).contains(&status) {
+                    return Err(invalid("Ump::from_packet: invalid MIDI 1.0 status"));
+                }
+                let event = read_midi_event(&mut &[data1, data2][..], status)
+                    .map_err(|_| invalid("Ump::from_packet: invalid MIDI 1.0 data"))?;
+                Ump::Midi1 { group, event }
+            }
+            0x3 => {
+                let len = (word >> 16 & 0xf) as u8;
+                if len > 6 {
+                    return Err(invalid("Ump::from_packet: sysex7 contains at most 6 bytes"));
+                }
+                let mut data = [0; 6];
+                unpack(words, 2, &mut data[..usize::from(len)]);
+                Ump::Sysex7 {
+                    group,
+                    form: Form::new(word >> 20),
+                    data,
+                    len,
+                }
+            }
+            0x4 => Ump::Midi2 {
+                group,
+                event: Midi2Event::from_words(word, words[1])?,
+            },
+            0x5 => match word >> 20 & 0xf {
+                form @ 0x0..=0x3 => {
+                    let len = (word >> 16 & 0xf) as u8;
+                    if len == 0 || len > 14 {
+                        return Err(invalid("Ump::from_packet: sysex8 contains 1 to 14 bytes"));
+                    }
+                    let mut data = [0; 13];
+                    unpack(words, 3, &mut data[..usize::from(len - 1)]);
+                    Ump::Sysex8 {
+                        group,
+                        form: Form::new(form),
+                        stream: (word >> 8) as u8,
+                        data,
+                        len: len - 1,
+                    }
+                }
+                status @ 0x8..=0x9 => {
+                    let mut data = [0; 14];
+                    unpack(words, 2, &mut data);
+                    Ump::MixedDataSet {
+                        group,
+                        status: status as u8,
+                        data,
+                    }
+                }
+                _ => return Err(invalid("Ump::from_packet: unknown data 128 message")),
+            },
+            0xd => Ump::FlexData {
+                group,
+                form: Form::new(word >> 22),
+                address: (word >> 20 & 0x3) as u8,
+                channel: (word >> 16 & 0xf) as u8,
+                status_bank: (word >> 8) as u8,
+                status: word as u8,
+                data: [words[1], words[2], words[3]],
+            },
+            0xf => {
+                let mut data = [0; 14];
+                unpack(words, 2, &mut data);
+                Ump::Stream {
+                    form: Form::new(word >> 26),
+                    status: (word >> 16 & 0x3ff) as u16,
+                    data,
+                }
+            }
+            _ => Ump::Reserved(*packet),
+        };
+
+        Ok(ump)
+    }
+
+    /// Encodes the message. Fields are truncated to their bit width.
+    pub fn to_packet(&self) -> Packet {
+        let mut words = [0u32; 4];
+        let header =
+            |message_type: u32, group: u8| message_type << 28 | u32::from(group & 0xf) << 24;
+        match *self {
+            Ump::Utility(utility) => {
+                words[0] = match utility {
+                    Utility::NoOp => 0,
+                    Utility::JrClock(time) => 0x1 << 20 | u32::from(time),
+                    Utility::JrTimestamp(time) => 0x2 << 20 | u32::from(time),
+                    Utility::TicksPerQuarterNote(ticks) => 0x3 << 20 | u32::from(ticks),
+                    Utility::DeltaClockstamp(ticks) => 0x4 << 20 | ticks & 0xf_ffff,
+                };
+            }
+            Ump::System { group, event } => {
+                let ([status, data1, data2], _) = event.to_bytes();
+                words[0] = header(0x1, group) | u32::from_be_bytes([0, status, data1, data2]);
+            }
+            Ump::Midi1 { group, event } => {
+                let [status, data1, data2] = midi1_bytes(&event);
+                words[0] = header(0x2, group) | u32::from_be_bytes([0, status, data1, data2]);
+            }
+            Ump::Sysex7 {
+                group,
+                form,
+                data,
+                len,
+            } => {
+                let len = len.min(6);
+                words[0] = header(0x3, group) | form.value() << 20 | u32::from(len) << 16;
+                pack(&mut words, 2, &data[..usize::from(len)]);
+            }
+            Ump::Midi2 { group, event } => {
+                let (word0, word1) = event.to_words();
+                words[0] = header(0x4, group) | word0;
+                words[1] = word1;
+            }
+            Ump::Sysex8 {
+                group,
+                form,
+                stream,
+                data,
+                len,
+            } => {
+                let len = len.min(13);
+                words[0] = header(0x5, group)
+                    | form.value() << 20
+                    | u32::from(len + 1) << 16
+                    | u32::from(stream) << 8;
+                pack(&mut words, 3, &data[..usize::from(len)]);
+            }
+            Ump::MixedDataSet {
+                group,
+                status,
+                data,
+            } => {
+                words[0] = header(0x5, group) | u32::from(status & 0xf) << 20;
+                pack(&mut words, 2, &data);
+            }
+            Ump::FlexData {
+                group,
+                form,
+                address,
+                channel,
+                status_bank,
+                status,
+                data,
+            } => {
+                words[0] = header(0xd, group)
+                    | form.value() << 22
+                    | u32::from(address & 0x3) << 20
+                    | u32::from(channel & 0xf) << 16
+                    | u32::from(status_bank) << 8
+                    | u32::from(status);
+                words[1..].copy_from_slice(&data);
+            }
+            Ump::Stream { form, status, data } => {
+                words[0] = 0xf << 28 | form.value() << 26 | u32::from(status & 0x3ff) << 16;
+                pack(&mut words, 2, &data);
+            }
+            Ump::Reserved(packet) => return packet,
+        }
+        Packet { words }
+    }
+}
+
+impl Midi2Event {
+    fn from_words(word: u32, data: u32) -> Result<Midi2Event, Error> {
+        let channel = (word >> 16 & 0xf) as u8;
+        let [_, _, byte2, byte3] = word.to_be_bytes();
+        let kind = match word >> 20 & 0xf {
+            0x0 => Midi2EventKind::RegisteredPerNoteController {
+                key: byte2,
+                index: byte3,
+                value: data,
+            },
+            0x1 => Midi2EventKind::AssignablePerNoteController {
+                key: byte2,
+                index: byte3,
+                value: data,
+            },
+            0x2 => Midi2EventKind::RegisteredController {
+                bank: byte2,
+                index: byte3,
+                value: data,
+            },
+            0x3 => Midi2EventKind::AssignableController {
+                bank: byte2,
+                index: byte3,
+                value: data,
+            },
+            0x4 => Midi2EventKind::RelativeRegisteredController {
+                bank: byte2,
+                index: byte3,
+                value: data as i32,
+            },
+            0x5 => Midi2EventKind::RelativeAssignableController {
+                bank: byte2,
+                index: byte3,
+                value: data as i32,
+            },
+            0x6 => Midi2EventKind::PerNotePitchBend {
+                key: byte2,
+                value: data,
+            },
+            0x8 => Midi2EventKind::NoteOff {
+                key: byte2,
+                velocity: (data >> 16) as u16,
+                attribute_type: byte3,
+                attribute: data as u16,
+            },
+            0x9 => Midi2EventKind::NoteOn {
+                key: byte2,
+                velocity: (data >> 16) as u16,
+                attribute_type: byte3,
+                attribute: data as u16,
+            },
+            0xa => Midi2EventKind::PolyphonicKeyPressure {
+                key: byte2,
+                value: data,
+            },
+            0xb => Midi2EventKind::ControllerChange {
+                index: byte2,
+                value: data,
+            },
+            0xc => {
+                let [program, _, msb, lsb] = data.to_be_bytes();
+                Midi2EventKind::ProgramChange {
+                    program,
+                    bank: match byte3 & 1 {
+                        1 => Some((msb, lsb)),
+                        _ => None,
+                    },
+                }
+            }
+            0xd => Midi2EventKind::ChannelPressure(data),
+            0xe => Midi2EventKind::PitchBend(data),
+            0xf => Midi2EventKind::PerNoteManagement {
+                key: byte2,
+                detach: byte3 & 0x2 != 0,
+                reset: byte3 & 0x1 != 0,
+            },
+            _ => {
+                return Err(Error {
+                    context: "Ump::from_packet: unknown MIDI 2.0 channel voice message",
+                    kind: ErrorKind::Invalid,
+                })
+            }
+        };
+
+        Ok(Midi2Event { channel, kind })
+    }
+
+    /// Returns both words without message type and group.
+    fn to_words(self) -> (u32, u32) {
+        let (opcode, byte2, byte3, data) = match self.kind {
+            Midi2EventKind::RegisteredPerNoteController { key, index, value } => {
+                (0x0, key, index, value)
+            }
+            Midi2EventKind::AssignablePerNoteController { key, index, value } => {
+                (0x1, key, index, value)
+            }
+            Midi2EventKind::RegisteredController { bank, index, value } => {
+                (0x2, bank, index, value)
+            }
+            Midi2EventKind::AssignableController { bank, index, value } => {
+                (0x3, bank, index, value)
+            }
+            Midi2EventKind::RelativeRegisteredController { bank, index, value } => {
+                (0x4, bank, index, value as u32)
+            }
+            Midi2EventKind::RelativeAssignableController { bank, index, value } => {
+                (0x5, bank, index, value as u32)
+            }
+            Midi2EventKind::PerNotePitchBend { key, value } => (0x6, key, 0, value),
+            Midi2EventKind::NoteOff {
+                key,
+                velocity,
+                attribute_type,
+                attribute,
+            } => (
+                0x8,
+                key,
+                attribute_type,
+                u32::from(velocity) << 16 | u32::from(attribute),
+            ),
+            Midi2EventKind::NoteOn {
+                key,
+                velocity,
+                attribute_type,
+                attribute,
+            } => (
+                0x9,
+                key,
+                attribute_type,
+                u32::from(velocity) << 16 | u32::from(attribute),
+            ),
+            Midi2EventKind::PolyphonicKeyPressure { key, value } => (0xa, key, 0, value),
+            Midi2EventKind::ControllerChange { index, value } => (0xb, index, 0, value),
+            Midi2EventKind::ProgramChange { program, bank } => {
+                let (msb, lsb) = bank.unwrap_or_default();
+                let data = u32::from_be_bytes([program, 0, msb, lsb]);
+                (0xc, 0, bank.is_some() as u8, data)
+            }
+            Midi2EventKind::ChannelPressure(value) => (0xd, 0, 0, value),
+            Midi2EventKind::PitchBend(value) => (0xe, 0, 0, value),
+            Midi2EventKind::PerNoteManagement { key, detach, reset } => {
+                (0xf, key, (detach as u8) << 1 | reset as u8, 0)
+            }
+        };
+        let word = opcode << 20
+            | u32::from(self.channel & 0xf) << 16
+            | u32::from(byte2) << 8
+            | u32::from(byte3);
+        (word, data)
+    }
+
+    /// Translates the message to MIDI 1.0 channel voice messages.
+    ///
+    /// Values are scaled down with [`scale_down`], note on velocity is at least 1. Program change
+    /// with bank, registered and assignable controllers translate to bank select and parameter
+    /// number controllers. Per-note and relative messages do not have a MIDI 1.0 equivalent and
+    /// produce no messages.
+    ///
+    /// [`scale_down`]: fn.scale_down.html
+    pub fn to_midi1(&self) -> impl Iterator<Item = MidiEvent> {
+        let channel = self.channel & 0x0f;
+        let event = |kind| Some(MidiEvent { channel, kind });
+        let cc = |number, value| {
+            event(MidiEventKind::ControllerChange {
+                number,
+                value: value & 0x7f,
+            })
+        };
+        let parameter = |msb_number, lsb_number, bank: u8, index: u8, value: u32| {
+            [
+                cc(msb_number, bank),
+                cc(lsb_number, index),
+                cc(6, (value >> 25) as u8),
+                cc(38, (value >> 18) as u8),
+            ]
+        };
+        let events = match self.kind {
+            Midi2EventKind::NoteOff { key, velocity, .. } => [
+                event(MidiEventKind::NoteOff {
+                    key: key & 0x7f,
+                    velocity: (velocity >> 9) as u8,
+                }),
+                None,
+                None,
+                None,
+            ],
+            Midi2EventKind::NoteOn { key, velocity, .. } => [
+                event(MidiEventKind::NoteOn {
+                    key: key & 0x7f,
+                    velocity: ((velocity >> 9) as u8).max(1),
+                }),
+                None,
+                None,
+                None,
+            ],
+            Midi2EventKind::PolyphonicKeyPressure { key, value } => [
+                event(MidiEventKind::PolyphonicKeyPressure {
+                    key: key & 0x7f,
+                    velocity: (value >> 25) as u8,
+                }),
+                None,
+                None,
+                None,
+            ],
+            Midi2EventKind::ControllerChange { index, value } => {
+                let mut bytes = &[index & 0x7f, (value >> 25) as u8][..];
+                let kind = read_midi_event(&mut bytes, 0xb0)
+                    .map(|event| event.kind)
+                    .unwrap_or(MidiEventKind::ControllerChange {
+                        number: index & 0x7f,
+                        value: (value >> 25) as u8,
+                    });
+                [event(kind), None, None, None]
+            }
+            Midi2EventKind::RegisteredController { bank, index, value } => {
+                parameter(101, 100, bank, index, value)
+            }
+            Midi2EventKind::AssignableController { bank, index, value } => {
+                parameter(99, 98, bank, index, value)
+            }
+            Midi2EventKind::ProgramChange { program, bank } => {
+                let program = event(MidiEventKind::ProgramChange(program & 0x7f));
+                match bank {
+                    Some((msb, lsb)) => [cc(0, msb), cc(32, lsb), program, None],
+                    None => [program, None, None, None],
+                }
+            }
+            Midi2EventKind::ChannelPressure(value) => [
+                event(MidiEventKind::ChannelKeyPressure((value >> 25) as u8)),
+                None,
+                None,
+                None,
+            ],
+            Midi2EventKind::PitchBend(value) => [
+                event(MidiEventKind::PitchBend {
+                    lsb: (value >> 18) as u8 & 0x7f,
+                    msb: (value >> 25) as u8,
+                }),
+                None,
+                None,
+                None,
+            ],
+            Midi2EventKind::RegisteredPerNoteController { .. }
+            | Midi2EventKind::AssignablePerNoteController { .. }
+            | Midi2EventKind::PerNoteManagement { .. }
+            | Midi2EventKind::RelativeRegisteredController { .. }
+            | Midi2EventKind::RelativeAssignableController { .. }
+            | Midi2EventKind::PerNotePitchBend { .. } => [None; 4],
+        };
+        IntoIterator::into_iter(events).flatten()
+    }
+}
+
+/// Scales `value` of `from_bits` up to `to_bits` using min-center-max scaling.
+///
+/// Zero stays zero, the center value of the source range maps to the center of the target range
+/// and the maximum maps to the maximum. `value` is returned unchanged if `from_bits` is zero,
+/// `to_bits` is smaller than `from_bits` or larger than 32.
+pub fn scale_up(value: u32, from_bits: u8, to_bits: u8) -> u32 {
+    if from_bits == 0 || to_bits < from_bits || to_bits > 32 {
+        return value;
+    }
+    let shift = to_bits - from_bits;
+    let shifted = value << shift;
+    if value <= 1 << (from_bits - 1) {
+        return shifted;
+    }
+
+    // repeat the bits below the most significant one to fill the lower bits
+    let repeat_bits = from_bits - 1;
+    let mut repeat = value & ((1 << repeat_bits) - 1);
+    if shift > repeat_bits {
+        repeat <<= shift - repeat_bits;
+    } else {
+        repeat >>= repeat_bits - shift;
+    }
+    let mut result = shifted;
+    while repeat != 0 {
+        result |= repeat;
+        repeat >>= repeat_bits;
+    }
+    result
+}
+
+/// Scales `value` of `from_bits` down to `to_bits` by dropping the lower bits.
+///
+/// `value` is returned unchanged if `to_bits` is larger than `from_bits` or `from_bits` is larger
+/// than 32.
+pub fn scale_down(value: u32, from_bits: u8, to_bits: u8) -> u32 {
+    if to_bits > from_bits || from_bits > 32 {
+        return value;
+    }
+    value
+        .checked_shr(u32::from(from_bits - to_bits))
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct UpscalerChannel {
+    bank_msb: Option<u8>,
+    bank_lsb: Option<u8>,
+    /// Registered and msb, lsb of the selected parameter number.
+    parameter: (bool, Option<u8>, Option<u8>),
+    data_msb: u8,
+}
+
+/// Translates MIDI 1.0 channel voice messages to MIDI 2.0 channel voice messages.
+///
+/// Translation is stateful: bank select is sent with the following program change and
+/// parameter number controllers are merged with data entry into registered and assignable
+/// controllers. Values are scaled up with [`scale_up`], note on with zero velocity translates to
+/// note off with the default velocity.
+///
+/// [`scale_up`]: fn.scale_up.html
+#[derive(Debug, Clone, Default)]
+pub struct Upscaler {
+    channels: [UpscalerChannel; 16],
+}
+
+impl Upscaler {
+    /// Creates new [`Upscaler`].
+    ///
+    /// [`Upscaler`]: struct.Upscaler.html
+    pub fn new() -> Self {
+        Upscaler::default()
+    }
+
+    /// Translates `event`, returns `None` if the event only updates the state.
+    pub fn upscale(&mut self, event: MidiEvent) -> Option<Midi2Event> {
+        let channel = event.channel & 0x0f;
+        let state = &mut self.channels[usize::from(channel)];
+        let velocity = |velocity: u8| scale_up(u32::from(velocity & 0x7f), 7, 16) as u16;
+        let value = |value: u8| scale_up(u32::from(value & 0x7f), 7, 32);
+        let kind = match event.kind {
+            MidiEventKind::NoteOn { key, velocity: 0 } => Midi2EventKind::NoteOff {
+                key,
+                velocity: 0x8000,
+                attribute_type: 0,
+                attribute: 0,
+            },
+            MidiEventKind::NoteOn { key, velocity: v } => Midi2EventKind::NoteOn {
+                key,
+                velocity: velocity(v),
+                attribute_type: 0,
+                attribute: 0,
+            },
+            MidiEventKind::NoteOff { key, velocity: v } => Midi2EventKind::NoteOff {
+                key,
+                velocity: velocity(v),
+                attribute_type: 0,
+                attribute: 0,
+            },
+            MidiEventKind::PolyphonicKeyPressure { key, velocity } => {
+                Midi2EventKind::PolyphonicKeyPressure {
+                    key,
+                    value: value(velocity),
+                }
+            }
+            MidiEventKind::ProgramChange(program) => Midi2EventKind::ProgramChange {
+                program,
+                bank: match (state.bank_msb, state.bank_lsb) {
+                    (None, None) => None,
+                    (msb, lsb) => Some((msb.unwrap_or(0), lsb.unwrap_or(0))),
+                },
+            },
+            MidiEventKind::ChannelKeyPressure(pressure) => {
+                Midi2EventKind::ChannelPressure(value(pressure))
+            }
+            MidiEventKind::PitchBend { lsb, msb } => {
+                let bend = u32::from(msb & 0x7f) << 7 | u32::from(lsb & 0x7f);
+                Midi2EventKind::PitchBend(scale_up(bend, 14, 32))
+            }
+            kind => {
+                let (number, v) = controller(kind)?;
+                match number {
+                    0 => {
+                        state.bank_msb = Some(v);
+                        return None;
+                    }
+                    32 => {
+                        state.bank_lsb = Some(v);
+                        return None;
+                    }
+                    99 | 101 => {
+                        state.parameter = (number == 101, Some(v), state.parameter.2);
+                        return None;
+                    }
+                    98 | 100 => {
+                        state.parameter = (number == 100, state.parameter.1, Some(v));
+                        return None;
+                    }
+                    6 | 38 => {
+                        let lsb = match number {
+                            6 => {
+                                state.data_msb = v;
+                                0
+                            }
+                            _ => v,
+                        };
+                        let (bank, index) = match state.parameter {
+                            (_, Some(127), Some(127)) | (_, None, _) | (_, _, None) => return None,
+                            (_, Some(bank), Some(index)) => (bank, index),
+                        };
+                        let data = u32::from(state.data_msb) << 7 | u32::from(lsb);
+                        let value = scale_up(data, 14, 32);
+                        match state.parameter.0 {
+                            true => Midi2EventKind::RegisteredController { bank, index, value },
+                            false => Midi2EventKind::AssignableController { bank, index, value },
+                        }
+                    }
+                    _ => Midi2EventKind::ControllerChange {
+                        index: number,
+                        value: value(v),
+                    },
+                }
+            }
+        };
+
+        Some(Midi2Event { channel, kind })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_packet, scale_down, scale_up, Form, Midi2Event, Midi2EventKind, Packet, Ump, Upscaler,
+        Utility,
+    };
+    use crate::{MidiEvent, MidiEventKind, SystemEvent};
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(scale_up(0, 7, 16), 0);
+        assert_eq!(scale_up(64, 7, 16), 0x8000);
+        assert_eq!(scale_up(127, 7, 16), 0xffff);
+        assert_eq!(scale_up(127, 7, 32), 0xffff_ffff);
+        assert_eq!(scale_up(0x2000, 14, 32), 0x8000_0000);
+        assert_eq!(scale_up(0x3fff, 14, 32), 0xffff_ffff);
+        for value in 0..128 {
+            assert_eq!(scale_down(scale_up(value, 7, 32), 32, 7), value);
+        }
+    }
+
+    #[test]
+    fn test_scale_invalid_bits() {
+        assert_eq!(scale_up(5, 0, 16), 5);
+        assert_eq!(scale_up(5, 16, 7), 5);
+        assert_eq!(scale_up(5, 7, 33), 5);
+        assert_eq!(scale_up(1, 1, 32), 0x8000_0000);
+        assert_eq!(scale_down(5, 7, 16), 5);
+        assert_eq!(scale_down(5, 40, 7), 5);
+        assert_eq!(scale_down(0xffff_ffff, 32, 0), 0);
+    }
+
+    #[test]
+    fn test_packets() {
+        let messages = [
+            Ump::Utility(Utility::DeltaClockstamp(0x12345)),
+            Ump::System {
+                group: 1,
+                event: SystemEvent::SongPosition(0x1234),
+            },
+            Ump::Midi1 {
+                group: 2,
+                event: MidiEvent {
+                    channel: 3,
+                    kind: MidiEventKind::LocalControl(crate::Action::Reconnect),
+                },
+            },
+            Ump::Sysex7 {
+                group: 3,
+                form: Form::Start,
+                data: [0x7e, 0x7f, 0x09, 0x01, 0, 0],
+                len: 4,
+            },
+            Ump::Midi2 {
+                group: 4,
+                event: Midi2Event {
+                    channel: 5,
+                    kind: Midi2EventKind::ProgramChange {
+                        program: 10,
+                        bank: Some((1, 2)),
+                    },
+                },
+            },
+            Ump::Sysex8 {
+                group: 5,
+                form: Form::End,
+                stream: 7,
+                data: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0, 0],
+                len: 11,
+            },
+            Ump::FlexData {
+                group: 6,
+                form: Form::Complete,
+                address: 1,
+                channel: 2,
+                status_bank: 0,
+                status: 0x01,
+                data: [1, 2, 3],
+            },
+            Ump::Stream {
+                form: Form::Complete,
+                status: 0x3ff,
+                data: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14],
+            },
+        ];
+        for message in messages.iter() {
+            let packet = message.to_packet();
+            assert_eq!(Ump::from_packet(&packet).unwrap(), *message);
+            assert_eq!(Packet::new(packet.words()).unwrap(), packet);
+        }
+
+        let packet = messages[4].to_packet();
+        assert_eq!(packet.words(), &[0x44c5_0001, 0x0a00_0102]);
+        let bytes = [0x44, 0xc5, 0, 1, 0x0a, 0, 1, 2, 0x20];
+        let mut cursor = &bytes[..];
+        assert_eq!(read_packet(&mut cursor).unwrap(), packet);
+        assert_eq!(cursor, &[0x20]);
+        assert!(read_packet(&mut cursor).is_err());
+        assert!(Packet::new(&[0x4000_0000]).is_err());
+    }
+
+    #[test]
+    fn test_upscale() {
+        let mut upscaler = Upscaler::new();
+        let mut upscale = |kind| {
+            upscaler
+                .upscale(MidiEvent { channel: 1, kind })
+                .map(|event| event.kind)
+        };
+        let cc = |number, value| MidiEventKind::ControllerChange { number, value };
+
+        assert_eq!(
+            upscale(MidiEventKind::NoteOn {
+                key: 60,
+                velocity: 64
+            }),
+            Some(Midi2EventKind::NoteOn {
+                key: 60,
+                velocity: 0x8000,
+                attribute_type: 0,
+                attribute: 0
+            })
+        );
+        assert_eq!(upscale(cc(0, 1)), None);
+        assert_eq!(
+            upscale(MidiEventKind::ProgramChange(5)),
+            Some(Midi2EventKind::ProgramChange {
+                program: 5,
+                bank: Some((1, 0))
+            })
+        );
+        assert_eq!(upscale(cc(101, 0)), None);
+        assert_eq!(upscale(cc(100, 0)), None);
+        assert_eq!(
+            upscale(cc(6, 12)),
+            Some(Midi2EventKind::RegisteredController {
+                bank: 0,
+                index: 0,
+                value: 12 << 25
+            })
+        );
+        assert_eq!(upscale(cc(101, 127)), None);
+        assert_eq!(upscale(cc(100, 127)), None);
+        assert_eq!(upscale(cc(6, 12)), None);
+        assert_eq!(
+            upscale(MidiEventKind::AllNotesOff),
+            Some(Midi2EventKind::ControllerChange {
+                index: 123,
+                value: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_midi1() {
+        let events = [
+            MidiEventKind::NoteOn {
+                key: 60,
+                velocity: 100,
+            },
+            MidiEventKind::NoteOff {
+                key: 60,
+                velocity: 10,
+            },
+            MidiEventKind::PolyphonicKeyPressure {
+                key: 60,
+                velocity: 127,
+            },
+            MidiEventKind::ControllerChange {
+                number: 7,
+                value: 90,
+            },
+            MidiEventKind::MonoModeOn(4),
+            MidiEventKind::ProgramChange(3),
+            MidiEventKind::ChannelKeyPressure(1),
+            MidiEventKind::PitchBend { lsb: 5, msb: 70 },
+        ];
+        let mut upscaler = Upscaler::new();
+        for &kind in events.iter() {
+            let event = MidiEvent { channel: 2, kind };
+            let upscaled = upscaler.upscale(event).unwrap();
+            assert!(upscaled.to_midi1().eq(Some(event)));
+        }
+
+        let registered = Midi2Event {
+            channel: 0,
+            kind: Midi2EventKind::RegisteredController {
+                bank: 0,
+                index: 1,
+                value: 0x8000_0000,
+            },
+        };
+        let cc = |number, value| MidiEvent {
+            channel: 0,
+            kind: MidiEventKind::ControllerChange { number, value },
+        };
+        let expected = [cc(101, 0), cc(100, 1), cc(6, 64), cc(38, 0)];
+        assert!(registered.to_midi1().eq(expected.iter().copied()));
+    }
+}
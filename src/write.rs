@@ -0,0 +1,492 @@
+//! Low-level `SMF` writing interface.
+//!
+//! # Example
+//!
+//! ```
+//! # use midi;
+//! # fn copy(bytes: &[u8]) -> Result<Vec<u8>, midi::Error> {
+//! let smf = midi::Smf::read(bytes)?;
+//! let bytes = smf.write()?;
+//! # Ok(bytes)
+//! # }
+//! ```
+
+use crate::{
+    read::{Chunk, HeaderChunk, Rmid, CLIP_SIGNATURE, END_OF_CLIP, START_OF_CLIP},
+    ump::Packet,
+    Action, Error, ErrorKind, Event, EventKind, Format, Fps, MetaEvent, MidiEvent, MidiEventKind,
+    SysexEvent, Text, Timing,
+};
+use alloc::vec::Vec;
+
+fn invalid(context: &'static str) -> Error {
+    Error {
+        context,
+        kind: ErrorKind::Invalid,
+    }
+}
+
+fn u7(value: u8, context: &'static str) -> Result<u8, Error> {
+    if value <= 0x7f {
+        Ok(value)
+    } else {
+        Err(invalid(context))
+    }
+}
+
+/// Writes variable length quantity.
+///
+/// Fails if the `value` does not fit into 28 bits.
+pub fn write_vlq(out: &mut Vec<u8>, value: u32) -> Result<(), Error> {
+    if value > 0x0fff_ffff {
+        return Err(invalid("write_vlq: value must fit into 28 bits"));
+    }
+
+    let mut bytes = [0u8; 4];
+    let mut len = 0;
+    let mut value = value;
+    loop {
+        bytes[len] = (value & 0x7f) as u8;
+        len += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+    }
+
+    for (i, byte) in bytes[..len].iter().enumerate().rev() {
+        let continuation = if i == 0 { 0 } else { 0x80 };
+        out.push(byte | continuation);
+    }
+
+    Ok(())
+}
+
+fn write_data(out: &mut Vec<u8>, data: &[u8]) -> Result<(), Error> {
+    if data.len() > 0x0fff_ffff {
+        return Err(invalid("write_data: data length must fit into 28 bits"));
+    }
+    write_vlq(out, data.len() as u32)?;
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+pub(crate) fn write_timing(out: &mut Vec<u8>, timing: Timing) -> Result<(), Error> {
+    let value = match timing {
+        Timing::Metrical(ppqn) => {
+            if ppqn & 0x8000 != 0 {
+                return Err(invalid("write_timing: ppqn must fit into 15 bits"));
+            }
+            ppqn
+        }
+        Timing::Timecode { fps, subframe } => {
+            let fps = match fps {
+                Fps::Fps24 => -24i8,
+                Fps::Fps25 => -25,
+                Fps::Fps30Drop => -29,
+                Fps::Fps30NonDrop => -30,
+            };
+            (u16::from(fps as u8) << 8) | u16::from(subframe)
+        }
+    };
+    out.extend_from_slice(&value.to_be_bytes());
+    Ok(())
+}
+
+/// Low-level [`HeaderChunk`] writer.
+///
+/// [`HeaderChunk`]: ../read/struct.HeaderChunk.html
+pub fn write_header_chunk(out: &mut Vec<u8>, header: &HeaderChunk) -> Result<(), Error> {
+    if let Format::Single = header.format {
+        if header.tracks != 1 {
+            return Err(invalid(
+                "write_header_chunk: format 0 header must specify exactly 1 track",
+            ));
+        }
+    }
+
+    let format: u16 = match header.format {
+        Format::Single => 0,
+        Format::MultiTrack => 1,
+        Format::MultiSequence => 2,
+    };
+
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&format.to_be_bytes());
+    out.extend_from_slice(&header.tracks.to_be_bytes());
+    write_timing(out, header.timing)
+}
+
+/// Writes chunk of any type.
+pub fn write_raw_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) -> Result<(), Error> {
+    if data.len() > u32::MAX as usize {
+        return Err(invalid(
+            "write_raw_chunk: chunk data length must fit into 32 bits",
+        ));
+    }
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+/// Writes [`Chunk`] read with [`read_chunk`] back unchanged.
+///
+/// [`Chunk`]: ../read/enum.Chunk.html
+/// [`read_chunk`]: ../read/fn.read_chunk.html
+pub fn write_chunk(out: &mut Vec<u8>, chunk: &Chunk) -> Result<(), Error> {
+    match *chunk {
+        Chunk::Track(ref track_chunk) => write_raw_chunk(out, b"MTrk", track_chunk.raw()),
+        Chunk::Unknown { ref id, data } => write_raw_chunk(out, id, data),
+    }
+}
+
+fn write_riff_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) -> Result<(), Error> {
+    if data.len() > u32::MAX as usize {
+        return Err(invalid(
+            "write_riff_chunk: chunk data length must fit into 32 bits",
+        ));
+    }
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    Ok(())
+}
+
+/// Writes `smf` wrapped in RIFF `RMID` container.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{Error, read::{RiffInfo, Rmid}, write::{write_riff_info, write_rmid}};
+/// # fn foo(smf: &[u8]) -> Result<Vec<u8>, Error> {
+/// let mut info = Vec::new();
+/// write_riff_info(&mut info, vec![(b"INAM", b"Song" as &[u8])])?;
+/// let rmid = Rmid {
+///     info: Some(RiffInfo::new(&info)),
+///     dls: None,
+/// };
+/// let mut out = Vec::new();
+/// write_rmid(&mut out, &rmid, smf)?;
+/// # Ok(out)
+/// # }
+/// ```
+pub fn write_rmid(out: &mut Vec<u8>, rmid: &Rmid, smf: &[u8]) -> Result<(), Error> {
+    let mut body = b"RMID".to_vec();
+    write_riff_chunk(&mut body, b"data", smf)?;
+    if let Some(info) = rmid.info {
+        let mut list = b"INFO".to_vec();
+        list.extend_from_slice(info.raw());
+        write_riff_chunk(&mut body, b"LIST", &list)?;
+    }
+    if let Some(dls) = rmid.dls {
+        write_riff_chunk(&mut body, b"RIFF", dls)?;
+    }
+    write_riff_chunk(out, b"RIFF", &body)
+}
+
+/// Writes RIFF `INFO` list data, usable with [`RiffInfo::new`].
+///
+/// Text is terminated with a zero byte.
+///
+/// [`RiffInfo::new`]: ../read/struct.RiffInfo.html#method.new
+pub fn write_riff_info<'a, I>(out: &mut Vec<u8>, entries: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = (&'a [u8; 4], &'a [u8])>,
+{
+    for (id, text) in entries {
+        let mut data = text.to_vec();
+        data.push(0);
+        write_riff_chunk(out, id, &data)?;
+    }
+    Ok(())
+}
+
+/// Writes MIDI Clip File (`SMF2CLIP`).
+///
+/// The configuration header contains `ticks_per_quarter_note` followed by `header` packets.
+/// Every packet of `events` is preceded by delta clockstamps of its delta time, end of clip
+/// message is added if the last packet is not one.
+///
+/// # Example
+///
+/// ```
+/// # use midi::{write::write_clip, ump::{Ump, Utility}};
+/// let mut out = Vec::new();
+/// let no_op = Ump::Utility(Utility::NoOp).to_packet();
+/// write_clip(&mut out, 480, &[], vec![(0, no_op), (480, no_op)]);
+/// ```
+pub fn write_clip<I>(out: &mut Vec<u8>, ticks_per_quarter_note: u16, header: &[Packet], events: I)
+where
+    I: IntoIterator<Item = (u32, Packet)>,
+{
+    fn write_packet(out: &mut Vec<u8>, words: &[u32]) {
+        for word in words {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+    }
+    fn write_delta(out: &mut Vec<u8>, mut delta: u32) {
+        loop {
+            let ticks = delta.min(0xf_ffff);
+            write_packet(out, &[0x0040_0000 | ticks]);
+            delta -= ticks;
+            if delta == 0 {
+                break;
+            }
+        }
+    }
+
+    out.extend_from_slice(CLIP_SIGNATURE);
+    write_delta(out, 0);
+    write_packet(out, &[0x0030_0000 | u32::from(ticks_per_quarter_note)]);
+    for packet in header {
+        write_delta(out, 0);
+        write_packet(out, packet.words());
+    }
+    write_delta(out, 0);
+    write_packet(out, &[START_OF_CLIP, 0, 0, 0]);
+
+    let mut ended = false;
+    for (delta, packet) in events {
+        write_delta(out, delta);
+        write_packet(out, packet.words());
+        ended = packet.words()[0] == END_OF_CLIP;
+    }
+    if !ended {
+        write_delta(out, 0);
+        write_packet(out, &[END_OF_CLIP, 0, 0, 0]);
+    }
+}
+
+/// Writes `MTrk` chunk containing `events`.
+pub fn write_track_chunk<'a, 'e, I>(out: &mut Vec<u8>, events: I) -> Result<(), Error>
+where
+    'a: 'e,
+    I: IntoIterator<Item = &'e Event<'a>>,
+{
+    let mut data = Vec::new();
+    for event in events {
+        write_event(&mut data, event)?;
+    }
+    write_raw_chunk(out, b"MTrk", &data)
+}
+
+/// Low-level [`Event`] writer.
+///
+/// Status byte is always written, running status is not used.
+///
+/// [`Event`]: ../struct.Event.html
+pub fn write_event(out: &mut Vec<u8>, event: &Event) -> Result<(), Error> {
+    write_vlq(out, event.time)?;
+    match event.kind {
+        EventKind::Midi(ref midi_event) => {
+            out.push(midi_status(midi_event)?);
+            write_midi_event_data(out, midi_event)
+        }
+        EventKind::Meta(ref meta_event) => {
+            out.push(0xff);
+            write_meta_event(out, meta_event)
+        }
+        EventKind::Sysex(SysexEvent::F0(data)) => {
+            out.push(0xf0);
+            write_data(out, data)
+        }
+        EventKind::Sysex(SysexEvent::F7(data)) => {
+            out.push(0xf7);
+            write_data(out, data)
+        }
+    }
+}
+
+/// Returns status byte of the [`MidiEvent`].
+///
+/// [`MidiEvent`]: ../struct.MidiEvent.html
+pub fn midi_status(midi_event: &MidiEvent) -> Result<u8, Error> {
+    if midi_event.channel > 0x0f {
+        return Err(invalid("midi_status: channel must fit into 4 bits"));
+    }
+
+    let status = match midi_event.kind {
+        MidiEventKind::NoteOff { .. } => 0x80,
+        MidiEventKind::NoteOn { .. } => 0x90,
+        MidiEventKind::PolyphonicKeyPressure { .. } => 0xa0,
+        MidiEventKind::ControllerChange { .. }
+        | MidiEventKind::AllSoundOff
+        | MidiEventKind::ResetAllControllers
+        | MidiEventKind::LocalControl(_)
+        | MidiEventKind::AllNotesOff
+        | MidiEventKind::OmniModeOff
+        | MidiEventKind::OmniModeOn
+        | MidiEventKind::MonoModeOn(_)
+        | MidiEventKind::PolyModeOn => 0xb0,
+        MidiEventKind::ProgramChange(_) => 0xc0,
+        MidiEventKind::ChannelKeyPressure(_) => 0xd0,
+        MidiEventKind::PitchBend { .. } => 0xe0,
+    };
+
+    Ok(status | midi_event.channel)
+}
+
+/// Writes data bytes of the [`MidiEvent`], without the status byte.
+///
+/// [`MidiEvent`]: ../struct.MidiEvent.html
+pub fn write_midi_event_data(out: &mut Vec<u8>, midi_event: &MidiEvent) -> Result<(), Error> {
+    const DATA: &str = "write_midi_event_data: data byte must fit into 7 bits";
+
+    match midi_event.kind {
+        MidiEventKind::NoteOff { key, velocity }
+        | MidiEventKind::NoteOn { key, velocity }
+        | MidiEventKind::PolyphonicKeyPressure { key, velocity } => {
+            out.push(u7(key, DATA)?);
+            out.push(u7(velocity, DATA)?);
+        }
+        MidiEventKind::ControllerChange { number, value } => {
+            out.push(u7(number, DATA)?);
+            out.push(u7(value, DATA)?);
+        }
+        MidiEventKind::ProgramChange(value) | MidiEventKind::ChannelKeyPressure(value) => {
+            out.push(u7(value, DATA)?);
+        }
+        MidiEventKind::PitchBend { lsb, msb } => {
+            out.push(u7(lsb, DATA)?);
+            out.push(u7(msb, DATA)?);
+        }
+        MidiEventKind::AllSoundOff => out.extend_from_slice(&[0x78, 0]),
+        MidiEventKind::ResetAllControllers => out.extend_from_slice(&[0x79, 0]),
+        MidiEventKind::LocalControl(Action::Disconnect) => out.extend_from_slice(&[0x7a, 0]),
+        MidiEventKind::LocalControl(Action::Reconnect) => out.extend_from_slice(&[0x7a, 0x7f]),
+        MidiEventKind::AllNotesOff => out.extend_from_slice(&[0x7b, 0]),
+        MidiEventKind::OmniModeOff => out.extend_from_slice(&[0x7c, 0]),
+        MidiEventKind::OmniModeOn => out.extend_from_slice(&[0x7d, 0]),
+        MidiEventKind::MonoModeOn(channels) => {
+            out.push(0x7e);
+            out.push(u7(channels, DATA)?);
+        }
+        MidiEventKind::PolyModeOn => out.extend_from_slice(&[0x7f, 0]),
+    }
+
+    Ok(())
+}
+
+fn write_text(out: &mut Vec<u8>, meta_type: u8, text: &Text) -> Result<(), Error> {
+    out.push(meta_type);
+    write_data(out, text.raw())
+}
+
+/// Writes [`MetaEvent`], without the leading `0xff` byte.
+///
+/// [`MetaEvent`]: ../enum.MetaEvent.html
+pub fn write_meta_event(out: &mut Vec<u8>, meta_event: &MetaEvent) -> Result<(), Error> {
+    match *meta_event {
+        MetaEvent::SequenceNumber(number) => {
+            out.extend_from_slice(&[0x00, 2]);
+            out.extend_from_slice(&number.to_be_bytes());
+        }
+        MetaEvent::Text(ref text) => write_text(out, 0x01, text)?,
+        MetaEvent::CopyrightNotice(ref text) => write_text(out, 0x02, text)?,
+        MetaEvent::Name(ref text) => write_text(out, 0x03, text)?,
+        MetaEvent::InstrumentName(ref text) => write_text(out, 0x04, text)?,
+        MetaEvent::Lyric(ref text) => write_text(out, 0x05, text)?,
+        MetaEvent::Marker(ref text) => write_text(out, 0x06, text)?,
+        MetaEvent::CuePoint(ref text) => write_text(out, 0x07, text)?,
+        MetaEvent::ChannelPrefix(channel) => out.extend_from_slice(&[0x20, 1, channel]),
+        MetaEvent::EndOfTrack => out.extend_from_slice(&[0x2f, 0]),
+        MetaEvent::SetTempo(tempo) => {
+            if tempo > 0x00ff_ffff {
+                return Err(invalid("write_meta_event: tempo must fit into 24 bits"));
+            }
+            out.extend_from_slice(&[0x51, 3]);
+            out.extend_from_slice(&tempo.to_be_bytes()[1..]);
+        }
+        MetaEvent::SMTPEOffset { hh, mm, ss, fr, ff } => {
+            out.extend_from_slice(&[0x54, 5, hh, mm, ss, fr, ff])
+        }
+        MetaEvent::TimeSignature { nn, dd, cc, bb } => {
+            out.extend_from_slice(&[0x58, 4, nn, dd, cc, bb])
+        }
+        MetaEvent::KeySignature { sf, mi } => out.extend_from_slice(&[0x59, 2, sf, mi]),
+        MetaEvent::SequencerSpecific(data) => {
+            out.push(0x7f);
+            write_data(out, data)?;
+        }
+        MetaEvent::Unknown { meta_type, data } => {
+            out.push(meta_type);
+            write_data(out, data)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_event, write_midi_event_data, write_vlq};
+    use crate::{read::read_event, Event, EventKind, MetaEvent, MidiEvent, MidiEventKind};
+
+    #[test]
+    fn test_write_vlq() {
+        fn write_vlq_u(value: u32) -> Vec<u8> {
+            let mut out = Vec::new();
+            write_vlq(&mut out, value).unwrap();
+            out
+        }
+
+        assert_eq!(write_vlq_u(0), vec![0]);
+        assert_eq!(write_vlq_u(0x7f), vec![0x7f]);
+        assert_eq!(write_vlq_u(0x80), vec![0x81, 0x00]);
+        assert_eq!(write_vlq_u(0x3fff), vec![0xff, 0x7f]);
+        assert_eq!(write_vlq_u(0x3e8), vec![0x87, 0x68]);
+        assert_eq!(write_vlq_u(0xf4240), vec![0xbd, 0x84, 0x40]);
+        assert!(write_vlq(&mut Vec::new(), 0x1000_0000).is_err());
+    }
+
+    #[test]
+    fn test_write_event() {
+        fn round_trip(event: Event) {
+            let mut out = Vec::new();
+            write_event(&mut out, &event).unwrap();
+            let mut cursor = &out[..];
+            assert_eq!(read_event(&mut cursor).unwrap(), event);
+            assert!(cursor.is_empty());
+        }
+
+        round_trip(Event {
+            time: 0x80,
+            kind: EventKind::Midi(MidiEvent {
+                channel: 9,
+                kind: MidiEventKind::NoteOn {
+                    key: 36,
+                    velocity: 100,
+                },
+            }),
+        });
+        round_trip(Event {
+            time: 0,
+            kind: EventKind::Midi(MidiEvent {
+                channel: 0,
+                kind: MidiEventKind::MonoModeOn(4),
+            }),
+        });
+        round_trip(Event {
+            time: 10,
+            kind: EventKind::Meta(MetaEvent::SetTempo(500_000)),
+        });
+        round_trip(Event {
+            time: 10,
+            kind: EventKind::Meta(MetaEvent::Unknown {
+                meta_type: 0x60,
+                data: b"data",
+            }),
+        });
+
+        let mono = MidiEvent {
+            channel: 0,
+            kind: MidiEventKind::MonoModeOn(0x80),
+        };
+        assert!(write_midi_event_data(&mut Vec::new(), &mono).is_err());
+    }
+}
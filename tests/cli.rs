@@ -0,0 +1,105 @@
+#![cfg(feature = "cli")]
+
+use midi::{Format, Smf};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+/// Creates empty temporary directory for the test `name`.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("midi-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn midi<I: IntoIterator<Item = S>, S: AsRef<std::ffi::OsStr>>(args: I) {
+    let output = Command::new(env!("CARGO_BIN_EXE_midi"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Splits `data` and returns the written files ordered by name.
+fn split(dir: &Path, data: &[u8]) -> Vec<PathBuf> {
+    let input = dir.join("input.mid");
+    fs::write(&input, data).unwrap();
+    let prefix = dir.join("part");
+    midi(["split".as_ref(), input.as_os_str(), prefix.as_os_str()]);
+
+    let mut parts = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("part")
+        })
+        .collect::<Vec<_>>();
+    parts.sort();
+    parts
+}
+
+#[test]
+fn test_split_and_merge() {
+    let dir = temp_dir("split-merge");
+    let data = include_bytes!("res/pirates.mid");
+    let smf = Smf::read(data).unwrap();
+
+    let parts = split(&dir, data);
+    assert_eq!(parts.len(), smf.tracks.len() - 1);
+    for (index, path) in parts.iter().enumerate() {
+        let part_data = fs::read(path).unwrap();
+        let part = Smf::read(&part_data).unwrap();
+        assert_eq!(
+            part.tracks,
+            vec![smf.tracks[0].clone(), smf.tracks[index + 1].clone()]
+        );
+    }
+
+    let merged_path = dir.join("merged.mid");
+    let mut args = vec!["merge".into(), merged_path.clone().into_os_string()];
+    args.extend(parts.iter().map(|path| path.clone().into_os_string()));
+    midi(args);
+    let merged_data = fs::read(&merged_path).unwrap();
+    let merged = Smf::read(&merged_data).unwrap();
+    assert_eq!(merged.tracks.len(), smf.tracks.len());
+    assert_eq!(merged.tracks[1..], smf.tracks[1..]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_split_multi_sequence() {
+    let dir = temp_dir("split-multi-sequence");
+    let mut smf = Smf::read(include_bytes!("res/pirates.mid")).unwrap();
+    smf.format = Format::MultiSequence;
+
+    let parts = split(&dir, &smf.write().unwrap());
+    assert_eq!(parts.len(), smf.tracks.len());
+    for (path, track) in parts.iter().zip(&smf.tracks) {
+        let part_data = fs::read(path).unwrap();
+        let part = Smf::read(&part_data).unwrap();
+        assert_eq!(part.format, Format::Single);
+        assert_eq!(part.tracks, vec![track.clone()]);
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_split_single_track() {
+    let dir = temp_dir("split-single-track");
+    let mut smf = Smf::read(include_bytes!("res/pirates.mid")).unwrap();
+    smf.tracks.truncate(1);
+
+    let parts = split(&dir, &smf.write().unwrap());
+    assert_eq!(parts.len(), 1);
+    let part_data = fs::read(&parts[0]).unwrap();
+    assert_eq!(Smf::read(&part_data).unwrap().tracks, smf.tracks);
+    fs::remove_dir_all(dir).unwrap();
+}
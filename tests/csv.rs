@@ -0,0 +1,15 @@
+#![cfg(feature = "alloc")]
+
+fn test_csv_round_trip(data: &[u8]) {
+    let smf = midi::Smf::read(data).unwrap();
+    let csv = midi::csv::write(&smf).unwrap();
+    let owned = midi::csv::read(&csv).unwrap();
+    assert_eq!(owned.as_smf(), smf);
+    assert_eq!(owned.as_smf().write().unwrap(), data);
+}
+
+#[test]
+fn test_smf_csv_round_trip() {
+    test_csv_round_trip(include_bytes!("res/super_mario_64.mid"));
+    test_csv_round_trip(include_bytes!("res/pirates.mid"));
+}
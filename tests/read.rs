@@ -1,3 +1,5 @@
+#![allow(clippy::single_component_path_imports)]
+
 use midi;
 
 fn test_data(data: &[u8]) {
@@ -13,8 +15,94 @@ fn test_data(data: &[u8]) {
         .unwrap();
 }
 
+fn test_track(data: &[u8]) {
+    let smf_reader = midi::read::SmfReader::new(data).unwrap();
+    let mut tracks = 0;
+    for (index, track_chunk) in smf_reader.track_chunk_iter().enumerate() {
+        let events = track_chunk.unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        let track_chunk = smf_reader.track(index).unwrap();
+        assert_eq!(track_chunk.collect::<Result<Vec<_>, _>>().unwrap(), events);
+        tracks += 1;
+    }
+    assert!(smf_reader.track(tracks).is_err());
+}
+
+#[cfg(feature = "alloc")]
+fn test_chunk_index(data: &[u8]) {
+    let smf = midi::Smf::read(data).unwrap();
+    let smf_reader = midi::read::SmfReader::new(data).unwrap();
+    let chunk_index = smf_reader.chunk_index().unwrap();
+    assert_eq!(chunk_index.track_count(), smf.tracks.len());
+    for (index, track) in smf.tracks.iter().enumerate() {
+        let entry = chunk_index.track(index).unwrap();
+        let track_chunk = smf_reader.track_chunk_at(entry).unwrap();
+        assert_eq!(
+            track_chunk.collect::<Result<Vec<_>, _>>().unwrap(),
+            track.events
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_smf_chunk_index() {
+    test_chunk_index(include_bytes!("res/super_mario_64.mid"));
+    test_chunk_index(include_bytes!("res/pirates.mid"));
+}
+
 #[test]
 fn test_smf_reader() {
     test_data(include_bytes!("res/super_mario_64.mid"));
     test_data(include_bytes!("res/pirates.mid"));
 }
+
+#[test]
+fn test_smf_reader_track() {
+    test_track(include_bytes!("res/super_mario_64.mid"));
+    test_track(include_bytes!("res/pirates.mid"));
+
+    // track chunks beyond the number of tracks in the header are accessible
+    let mut data = include_bytes!("res/pirates.mid").to_vec();
+    data[10..12].copy_from_slice(&[0, 2]);
+    test_track(&data);
+    let smf_reader = midi::read::SmfReader::new(&data).unwrap();
+    assert!(smf_reader.track(2).is_ok());
+    assert!(smf_reader.track_chunk_iter().count() > 2);
+}
+
+#[cfg(feature = "std")]
+fn test_stream_data(data: &[u8]) {
+    let smf = midi::Smf::read(data).unwrap();
+    let mut stream_reader = midi::StreamReader::new(data).unwrap();
+    for track in &smf.tracks {
+        let events = stream_reader
+            .next_track()
+            .unwrap()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let events = events.iter().map(|e| e.as_event()).collect::<Vec<_>>();
+        assert_eq!(events, track.events);
+    }
+    assert!(stream_reader.next_track().is_none());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_stream_reader() {
+    test_stream_data(include_bytes!("res/super_mario_64.mid"));
+    test_stream_data(include_bytes!("res/pirates.mid"));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_smf_wrong_track_count() {
+    let data = include_bytes!("res/pirates.mid");
+    let tracks = midi::Smf::read(data).unwrap().tracks;
+    // header chunk specifies the number of tracks at offset 10
+    for count in [1, 5] {
+        let mut data = data.to_vec();
+        data[10..12].copy_from_slice(&[0, count]);
+        assert_eq!(midi::Smf::read(&data).unwrap().tracks, tracks);
+    }
+}
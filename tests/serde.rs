@@ -0,0 +1,76 @@
+#![cfg(feature = "serde")]
+
+use midi::{Event, EventKind, MetaEvent, MidiEvent, MidiEventKind, OwnedEvent, OwnedSmf, Text};
+
+fn test_json_round_trip(data: &[u8]) {
+    let smf = midi::Smf::read(data).unwrap();
+    let json = serde_json::to_string(&smf).unwrap();
+    let owned: OwnedSmf = serde_json::from_str(&json).unwrap();
+    assert_eq!(owned.as_smf(), smf);
+    assert_eq!(serde_json::to_string(&owned).unwrap(), json);
+    assert_eq!(owned.as_smf().write().unwrap(), data);
+}
+
+#[test]
+fn test_smf_json_round_trip() {
+    test_json_round_trip(include_bytes!("res/super_mario_64.mid"));
+    test_json_round_trip(include_bytes!("res/pirates.mid"));
+}
+
+#[test]
+fn test_smf_bincode_round_trip() {
+    let data = include_bytes!("res/pirates.mid");
+    let smf = midi::Smf::read(data).unwrap();
+    let bytes = bincode::serialize(&smf).unwrap();
+    let owned: OwnedSmf = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(owned.as_smf(), smf);
+    assert_eq!(bincode::serialize(&owned).unwrap(), bytes);
+
+    // invalid utf8 text is serialized as bytes as well
+    let lyric = Event {
+        time: 0,
+        kind: EventKind::Meta(MetaEvent::Lyric(Text::new(&[0x82, 0xa0]))),
+    };
+    let bytes = bincode::serialize(&lyric).unwrap();
+    let owned: OwnedEvent = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(owned.as_event(), lyric);
+}
+
+#[test]
+fn test_event_json() {
+    let event = Event {
+        time: 96,
+        kind: EventKind::Midi(MidiEvent {
+            channel: 9,
+            kind: MidiEventKind::NoteOn {
+                key: 36,
+                velocity: 100,
+            },
+        }),
+    };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"time":96,"kind":{"midi":{"channel":9,"kind":{"note_on":{"key":36,"velocity":100}}}}}"#
+    );
+
+    let name = Event {
+        time: 0,
+        kind: EventKind::Meta(MetaEvent::Name(Text::new(b"Drums"))),
+    };
+    let json = serde_json::to_string(&name).unwrap();
+    assert_eq!(json, r#"{"time":0,"kind":{"meta":{"name":"Drums"}}}"#);
+    let owned: OwnedEvent = serde_json::from_str(&json).unwrap();
+    assert_eq!(owned.as_event(), name);
+}
+
+#[test]
+fn test_invalid_utf8_text_json() {
+    let lyric = Event {
+        time: 0,
+        kind: EventKind::Meta(MetaEvent::Lyric(Text::new(&[0x82, 0xa0]))),
+    };
+    let json = serde_json::to_string(&lyric).unwrap();
+    assert_eq!(json, r#"{"time":0,"kind":{"meta":{"lyric":[130,160]}}}"#);
+    let owned: OwnedEvent = serde_json::from_str(&json).unwrap();
+    assert_eq!(owned.as_event(), lyric);
+}
@@ -0,0 +1,146 @@
+#![cfg(feature = "alloc")]
+
+fn test_round_trip(data: &[u8]) {
+    let smf = midi::Smf::read(data).unwrap();
+    let written = smf.write().unwrap();
+    assert_eq!(midi::Smf::read(&written).unwrap(), smf);
+    assert_eq!(written, data);
+}
+
+#[test]
+fn test_smf_round_trip() {
+    test_round_trip(include_bytes!("res/super_mario_64.mid"));
+    test_round_trip(include_bytes!("res/pirates.mid"));
+}
+
+#[test]
+fn test_single_format_track_count() {
+    // format 0 files with several track chunks are read, but cannot be written
+    let mut data = include_bytes!("res/pirates.mid").to_vec();
+    data[8..12].copy_from_slice(&[0, 0, 0, 1]);
+    let smf = midi::Smf::read(&data).unwrap();
+    assert!(smf.tracks.len() > 1);
+    let err = smf.write().unwrap_err();
+    assert_eq!(
+        err.context,
+        "Smf::write: format 0 smf must contain exactly 1 track"
+    );
+}
+
+#[rustfmt::skip]
+const UNKNOWN_CHUNKS: &[u8] = &[
+    // header
+    0x4d, 0x54, 0x68, 0x64, 0, 0, 0, 6, 0, 1, 0, 1, 0, 0x60,
+    // unknown chunk before the track
+    0x63, 0x61, 0x6b, 0x65, 0, 0, 0, 2, 0xaa, 0xbb,
+    // track
+    0x4d, 0x54, 0x72, 0x6b, 0, 0, 0, 4, 0x00, 0xff, 0x2f, 0x00,
+    // unknown chunk after the track
+    0x73, 0x6f, 0x6e, 0x72, 0, 0, 0, 1, 0xcc,
+];
+
+#[test]
+fn test_unknown_chunks_round_trip() {
+    let data = UNKNOWN_CHUNKS;
+
+    let smf_reader = midi::read::SmfReader::new(data).unwrap();
+    let chunks = smf_reader
+        .chunk_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(chunks.len(), 3);
+    match chunks[0] {
+        midi::read::Chunk::Unknown { id, data } => {
+            assert_eq!(&id, b"cake");
+            assert_eq!(data, &[0xaa, 0xbb]);
+        }
+        _ => panic!("expected unknown chunk"),
+    }
+
+    let mut written = data[..14].to_vec();
+    for chunk in &chunks {
+        midi::write::write_chunk(&mut written, chunk).unwrap();
+    }
+    assert_eq!(written, data);
+
+    let smf = midi::Smf::read(data).unwrap();
+    assert_eq!(smf.unknown_chunks.len(), 2);
+    assert_eq!(smf.unknown_chunks[1].position, 1);
+    assert_eq!(smf.write().unwrap(), data);
+}
+
+fn rmid(smf: &[u8]) -> Vec<u8> {
+    let mut info = Vec::new();
+    midi::write::write_riff_info(
+        &mut info,
+        vec![(b"INAM", b"Pirates" as &[u8]), (b"ICOP", b"(c)")],
+    )
+    .unwrap();
+    let rmid = midi::read::Rmid {
+        info: Some(midi::read::RiffInfo::new(&info)),
+        dls: None,
+    };
+    let mut out = Vec::new();
+    midi::write::write_rmid(&mut out, &rmid, smf).unwrap();
+    out
+}
+
+#[test]
+fn test_rmid_round_trip() {
+    let data = include_bytes!("res/pirates.mid");
+    let container = rmid(data);
+    assert!(midi::read::is_rmid(&container));
+
+    let smf = midi::Smf::read(&container).unwrap();
+    assert_eq!(smf.tracks, midi::Smf::read(data).unwrap().tracks);
+    let info = smf.rmid.unwrap().info.unwrap();
+    assert_eq!(info.get(b"INAM"), Some(midi::Text::new(b"Pirates")));
+    assert_eq!(info.get(b"ICOP"), Some(midi::Text::new(b"(c)")));
+    assert_eq!(info.get(b"IART"), None);
+    assert_eq!(smf.write().unwrap(), container);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_rmid_stream_reader() {
+    let data = include_bytes!("res/pirates.mid");
+    let container = rmid(data);
+    let mut stream_reader = midi::StreamReader::new(&container[..]).unwrap();
+    let smf_reader = midi::read::SmfReader::new(data).unwrap();
+    assert_eq!(
+        stream_reader.header_chunk().tracks,
+        smf_reader.header_chunk().tracks
+    );
+    assert!(stream_reader
+        .next_track()
+        .unwrap()
+        .unwrap()
+        .all(|e| e.is_ok()));
+}
+
+#[test]
+fn test_lossless_round_trip() {
+    for data in [
+        &include_bytes!("res/super_mario_64.mid")[..],
+        &include_bytes!("res/pirates.mid")[..],
+        UNKNOWN_CHUNKS,
+    ] {
+        let (smf, layout) = midi::Smf::read_lossless(data).unwrap();
+        assert_eq!(smf, midi::Smf::read(data).unwrap());
+        assert_eq!(smf.write_lossless(&layout).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_rmid_lossless_round_trip() {
+    let container = rmid(include_bytes!("res/pirates.mid"));
+    let (mut smf, layout) = midi::Smf::read_lossless(&container).unwrap();
+    assert_eq!(smf.write_lossless(&layout).unwrap(), container);
+
+    // odd length of the edited smf requires a pad byte
+    smf.tracks[0].events[0].time = 0x80;
+    let written = smf.write_lossless(&layout).unwrap();
+    assert_eq!(written.len(), container.len() + 2);
+    assert_eq!(midi::Smf::read(&written).unwrap(), smf);
+    assert_eq!(written, smf.write().unwrap());
+}